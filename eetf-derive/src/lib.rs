@@ -0,0 +1,496 @@
+//! Derive macros for the [`ToTerm`] and [`FromTerm`] traits of the [eetf] crate.
+//!
+//! Use them through the `derive` feature of `eetf` rather than depending on this crate directly.
+//!
+//! [eetf]: https://docs.rs/eetf
+//! [`ToTerm`]: https://docs.rs/eetf/latest/eetf/convert/trait.ToTerm.html
+//! [`FromTerm`]: https://docs.rs/eetf/latest/eetf/convert/trait.FromTerm.html
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use syn::{
+    Attribute, Data, DeriveInput, Fields, Generics, Ident, LitStr, Member, parse_macro_input,
+    parse_quote,
+};
+
+/// Derives `eetf::ToTerm`. See its documentation for the representation of each type.
+#[proc_macro_derive(ToTerm, attributes(eetf))]
+pub fn derive_to_term(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input, Direction::ToTerm)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Derives `eetf::FromTerm`. See the documentation of `eetf::ToTerm` for the representation of each type.
+#[proc_macro_derive(FromTerm, attributes(eetf))]
+pub fn derive_from_term(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input, Direction::FromTerm)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[derive(Clone, Copy)]
+enum Direction {
+    ToTerm,
+    FromTerm,
+}
+
+/// Options given by `#[eetf(...)]` attributes.
+#[derive(Default)]
+struct Attrs {
+    rename: Option<LitStr>,
+    default: bool,
+    record: bool,
+    map: bool,
+    tuple: bool,
+    elixir_struct: Option<LitStr>,
+}
+impl Attrs {
+    fn parse(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut this = Attrs::default();
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("eetf")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    this.rename = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("default") {
+                    this.default = true;
+                } else if meta.path.is_ident("record") {
+                    this.record = true;
+                } else if meta.path.is_ident("map") {
+                    this.map = true;
+                } else if meta.path.is_ident("tuple") {
+                    this.tuple = true;
+                } else if meta.path.is_ident("elixir_struct") {
+                    this.elixir_struct = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("unknown eetf attribute"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(this)
+    }
+
+    /// Fails if any option other than `allowed` is given.
+    fn allow(&self, attrs: &[Attribute], allowed: &[&str], place: &str) -> syn::Result<()> {
+        let given = [
+            ("rename", self.rename.is_some()),
+            ("default", self.default),
+            ("record", self.record),
+            ("map", self.map),
+            ("tuple", self.tuple),
+            ("elixir_struct", self.elixir_struct.is_some()),
+        ];
+        if let Some((name, _)) = given
+            .iter()
+            .find(|(name, given)| *given && !allowed.contains(name))
+        {
+            let span = attrs
+                .iter()
+                .find(|attr| attr.path().is_ident("eetf"))
+                .map_or_else(proc_macro2::Span::call_site, |attr| {
+                    attr.path().get_ident().expect("checked").span()
+                });
+            return Err(syn::Error::new(
+                span,
+                format!("`{name}` is not supported on {place}"),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns the atom naming a type or variant: the `rename` option or the snake case of `ident`.
+    fn name(&self, ident: &Ident) -> String {
+        self.rename
+            .as_ref()
+            .map_or_else(|| snake_case(&ident.unraw().to_string()), LitStr::value)
+    }
+}
+
+/// Converts `UpperCamelCase` into `snake_case`, keeping acronyms together (`HTTPError` is `http_error`).
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut result = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|c| c.is_lowercase());
+            if prev.is_lowercase()
+                || prev.is_ascii_digit()
+                || (prev.is_uppercase() && next_is_lower)
+            {
+                result.push('_');
+            }
+        }
+        result.extend(c.to_lowercase());
+    }
+    result
+}
+
+struct Field {
+    member: Member,
+    binding: Ident,
+    key: String,
+    default: bool,
+}
+
+fn fields(fields: &Fields, allow_default: bool) -> syn::Result<Vec<Field>> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let attrs = Attrs::parse(&field.attrs)?;
+            let allowed: &[&str] = if allow_default {
+                &["rename", "default"]
+            } else {
+                &[]
+            };
+            attrs.allow(&field.attrs, allowed, "this field")?;
+            let member = match &field.ident {
+                Some(ident) => Member::Named(ident.clone()),
+                None => Member::Unnamed(i.into()),
+            };
+            let key = match (&attrs.rename, &field.ident) {
+                (Some(rename), _) => rename.value(),
+                // `r#type` is the `type` key.
+                (None, Some(ident)) => ident.unraw().to_string(),
+                (None, None) => i.to_string(),
+            };
+            Ok(Field {
+                member,
+                binding: format_ident!("__field{}", i),
+                key,
+                default: attrs.default,
+            })
+        })
+        .collect()
+}
+
+/// Representation of a struct.
+enum Layout {
+    /// `name`.
+    Atom(String),
+    /// `{tag, f1, f2, ...}`.
+    Record(String),
+    /// `{f1, f2, ...}`.
+    Tuple,
+    /// `#{f1 => ..., f2 => ...}`, with `__struct__ => 'Elixir.Module'` if the module is given.
+    Map(Option<String>),
+    /// The only field as is.
+    Transparent,
+}
+
+fn private() -> TokenStream2 {
+    quote!(::eetf::convert::__private)
+}
+
+fn expand(input: &DeriveInput, direction: Direction) -> syn::Result<TokenStream2> {
+    let attrs = Attrs::parse(&input.attrs)?;
+    let body = match &input.data {
+        Data::Struct(data) => {
+            let layout = if matches!(data.fields, Fields::Unit) {
+                attrs.allow(&input.attrs, &["rename"], "unit structs")?;
+                Layout::Atom(attrs.name(&input.ident))
+            } else if attrs.record {
+                attrs.allow(&input.attrs, &["record", "rename"], "records")?;
+                Layout::Record(attrs.name(&input.ident))
+            } else if attrs.tuple {
+                attrs.allow(&input.attrs, &["tuple"], "tuples")?;
+                Layout::Tuple
+            } else if matches!(data.fields, Fields::Named(_)) {
+                attrs.allow(&input.attrs, &["map", "elixir_struct"], "maps")?;
+                Layout::Map(attrs.elixir_struct.as_ref().map(|module| {
+                    let module = module.value();
+                    if module.starts_with("Elixir.") {
+                        module
+                    } else {
+                        format!("Elixir.{module}")
+                    }
+                }))
+            } else if data.fields.len() == 1 {
+                attrs.allow(&input.attrs, &[], "newtype structs")?;
+                Layout::Transparent
+            } else {
+                attrs.allow(&input.attrs, &[], "tuple structs")?;
+                Layout::Tuple
+            };
+            let fields = fields(&data.fields, matches!(layout, Layout::Map(_)))?;
+            match direction {
+                Direction::ToTerm => struct_to_term(&layout, &fields),
+                Direction::FromTerm => struct_from_term(&layout, &fields),
+            }
+        }
+        Data::Enum(data) => {
+            attrs.allow(&input.attrs, &[], "enums")?;
+            let mut variants = Vec::new();
+            for variant in &data.variants {
+                let attrs = Attrs::parse(&variant.attrs)?;
+                attrs.allow(&variant.attrs, &["rename"], "enum variants")?;
+                let fields = fields(&variant.fields, false)?;
+                variants.push((&variant.ident, attrs.name(&variant.ident), fields));
+            }
+            match direction {
+                Direction::ToTerm => enum_to_term(&variants),
+                Direction::FromTerm => enum_from_term(&variants),
+            }
+        }
+        Data::Union(data) => {
+            return Err(syn::Error::new(
+                data.union_token.span,
+                "unions are not supported",
+            ));
+        }
+    };
+
+    let ident = &input.ident;
+    let generics = add_bounds(&input.generics, direction);
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(match direction {
+        Direction::ToTerm => quote! {
+            #[automatically_derived]
+            impl #impl_generics ::eetf::ToTerm for #ident #ty_generics #where_clause {
+                #[allow(unused_mut)]
+                fn to_term(&self) -> ::core::result::Result<::eetf::Term, ::eetf::ConvertError> {
+                    #body
+                }
+            }
+        },
+        Direction::FromTerm => quote! {
+            #[automatically_derived]
+            impl #impl_generics ::eetf::FromTerm for #ident #ty_generics #where_clause {
+                #[allow(unused_mut)]
+                fn from_term(term: ::eetf::Term) -> ::core::result::Result<Self, ::eetf::ConvertError> {
+                    #body
+                }
+            }
+        },
+    })
+}
+
+fn add_bounds(generics: &Generics, direction: Direction) -> Generics {
+    let mut generics = generics.clone();
+    let params: Vec<Ident> = generics
+        .type_params()
+        .map(|param| param.ident.clone())
+        .collect();
+    let where_clause = generics.make_where_clause();
+    for param in params {
+        where_clause.predicates.push(match direction {
+            Direction::ToTerm => parse_quote!(#param: ::eetf::ToTerm),
+            Direction::FromTerm => parse_quote!(#param: ::eetf::FromTerm),
+        });
+    }
+    generics
+}
+
+/// Makes a tuple of the optional `tag` followed by `values`.
+fn elements_to_term(tag: Option<&str>, values: &[TokenStream2]) -> TokenStream2 {
+    let p = private();
+    let offset = usize::from(tag.is_some());
+    let elements = values.iter().enumerate().map(|(i, value)| {
+        let index = i + offset;
+        quote!(#p::to_element(#value, #index)?)
+    });
+    let tag = tag.map(|tag| quote!(#p::atom(#tag),));
+    quote!(::core::result::Result::Ok(#p::tuple(::std::vec![#tag #(#elements),*])))
+}
+
+/// Constructs `path` from the elements left in the `elements` iterator.
+fn elements_from_term(path: TokenStream2, fields: &[Field], offset: usize) -> TokenStream2 {
+    let p = private();
+    let inits = fields.iter().enumerate().map(|(i, field)| {
+        let member = &field.member;
+        let index = i + offset;
+        quote!(#member: #p::element(&mut elements, #index)?)
+    });
+    quote!(::core::result::Result::Ok(#path { #(#inits),* }))
+}
+
+fn expected_tuple(tag: Option<&str>, len: usize) -> String {
+    let mut elements: Vec<&str> = tag.into_iter().collect();
+    elements.extend(std::iter::repeat_n("_", len));
+    format!("{{{}}}", elements.join(", "))
+}
+
+fn struct_to_term(layout: &Layout, fields: &[Field]) -> TokenStream2 {
+    let p = private();
+    let values: Vec<_> = fields
+        .iter()
+        .map(|field| {
+            let member = &field.member;
+            quote!(&self.#member)
+        })
+        .collect();
+    match layout {
+        Layout::Atom(name) => quote!(::core::result::Result::Ok(#p::atom(#name))),
+        Layout::Record(tag) => elements_to_term(Some(tag), &values),
+        Layout::Tuple => elements_to_term(None, &values),
+        Layout::Map(elixir_struct) => {
+            let elixir_struct = elixir_struct
+                .as_ref()
+                .map(|module| quote!(map.insert(#p::atom("__struct__"), #p::atom(#module));));
+            let inserts = fields.iter().zip(&values).map(|(field, value)| {
+                let key = &field.key;
+                quote!(#p::insert_field(&mut map, #key, #value)?;)
+            });
+            quote! {
+                let mut map = #p::HashMap::new();
+                #elixir_struct
+                #(#inserts)*
+                ::core::result::Result::Ok(#p::map(map))
+            }
+        }
+        Layout::Transparent => {
+            let value = &values[0];
+            quote!(::eetf::ToTerm::to_term(#value))
+        }
+    }
+}
+
+fn struct_from_term(layout: &Layout, fields: &[Field]) -> TokenStream2 {
+    let p = private();
+    let mismatch = |expected: String| quote!(::core::result::Result::Err(::eetf::ConvertError::unexpected_type(term, #expected)));
+    match layout {
+        Layout::Atom(name) => {
+            let mismatch = mismatch(format!("the atom {name}"));
+            quote! {
+                match #p::variant(&term) {
+                    ::core::option::Option::Some((#name, ::core::option::Option::None)) => {
+                        ::core::result::Result::Ok(Self)
+                    }
+                    _ => #mismatch,
+                }
+            }
+        }
+        Layout::Record(tag) => {
+            let len = fields.len();
+            let construct = elements_from_term(quote!(Self), fields, 1);
+            let mismatch = mismatch(format!("a record {}", expected_tuple(Some(tag), len)));
+            quote! {
+                match #p::variant(&term) {
+                    ::core::option::Option::Some((#tag, ::core::option::Option::Some(#len))) => {
+                        let mut elements = #p::into_elements(term);
+                        elements.next();
+                        #construct
+                    }
+                    _ => #mismatch,
+                }
+            }
+        }
+        Layout::Tuple => {
+            let len = fields.len();
+            let construct = elements_from_term(quote!(Self), fields, 0);
+            let mismatch = mismatch(format!("a tuple of {len} elements"));
+            quote! {
+                match #p::tuple_len(&term) {
+                    ::core::option::Option::Some(#len) => {
+                        let mut elements = #p::into_elements(term);
+                        #construct
+                    }
+                    _ => #mismatch,
+                }
+            }
+        }
+        Layout::Map(elixir_struct) => {
+            let (elixir_struct, expected) = match elixir_struct {
+                Some(module) => (
+                    quote!(::core::option::Option::Some(#module)),
+                    format!("a %{}{{}} struct", &module["Elixir.".len()..]),
+                ),
+                None => (quote!(::core::option::Option::None), "a map".to_owned()),
+            };
+            let inits = fields.iter().map(|field| {
+                let member = &field.member;
+                let key = &field.key;
+                let missing = if field.default {
+                    quote!(::core::default::Default::default())
+                } else {
+                    quote!(return ::core::result::Result::Err(#p::missing_field(#key)))
+                };
+                quote! {
+                    #member: match #p::field(&mut map, #key)? {
+                        ::core::option::Option::Some(value) => value,
+                        ::core::option::Option::None => #missing,
+                    }
+                }
+            });
+            quote! {
+                let mut map = #p::into_map(term, #elixir_struct, #expected)?;
+                ::core::result::Result::Ok(Self { #(#inits),* })
+            }
+        }
+        Layout::Transparent => {
+            let member = &fields[0].member;
+            quote!(::core::result::Result::Ok(Self { #member: ::eetf::FromTerm::from_term(term)? }))
+        }
+    }
+}
+
+type Variant<'a> = (&'a Ident, String, Vec<Field>);
+
+fn enum_to_term(variants: &[Variant<'_>]) -> TokenStream2 {
+    if variants.is_empty() {
+        return quote!(match *self {});
+    }
+    let p = private();
+    let arms = variants.iter().map(|(ident, name, fields)| {
+        let members = fields.iter().map(|field| &field.member);
+        let bindings: Vec<_> = fields.iter().map(|field| &field.binding).collect();
+        let body = if fields.is_empty() {
+            quote!(::core::result::Result::Ok(#p::atom(#name)))
+        } else {
+            let values: Vec<_> = bindings.iter().map(|binding| quote!(#binding)).collect();
+            elements_to_term(Some(name), &values)
+        };
+        quote!(Self::#ident { #(#members: #bindings),* } => #body,)
+    });
+    quote! {
+        match self {
+            #(#arms)*
+        }
+    }
+}
+
+fn enum_from_term(variants: &[Variant<'_>]) -> TokenStream2 {
+    let p = private();
+    let arms = variants.iter().map(|(ident, name, fields)| {
+        if fields.is_empty() {
+            quote! {
+                ::core::option::Option::Some((#name, ::core::option::Option::None)) => {
+                    ::core::result::Result::Ok(Self::#ident {})
+                }
+            }
+        } else {
+            let len = fields.len();
+            let construct = elements_from_term(quote!(Self::#ident), fields, 1);
+            quote! {
+                ::core::option::Option::Some((#name, ::core::option::Option::Some(#len))) => {
+                    let mut elements = #p::into_elements(term);
+                    elements.next();
+                    #construct
+                }
+            }
+        }
+    });
+    let expected = variants
+        .iter()
+        .map(|(_, name, fields)| {
+            if fields.is_empty() {
+                name.clone()
+            } else {
+                expected_tuple(Some(name), fields.len())
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let expected = format!("one of {expected}");
+    quote! {
+        match #p::variant(&term) {
+            #(#arms)*
+            _ => ::core::result::Result::Err(::eetf::ConvertError::unexpected_type(term, #expected)),
+        }
+    }
+}
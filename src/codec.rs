@@ -1,14 +1,177 @@
 use super::*;
 use crate::convert::TryAsRef;
 use num_bigint::BigInt;
+use std::borrow::Cow;
+use std::collections::HashSet;
 use std::convert::From;
 use std::io;
+use std::io::Read;
 use std::io::Write;
 use std::str;
+use std::sync::Arc;
 
-/// Errors which can occur when decoding a term
+pub use self::flags::DistributionFlags;
+use self::incremental::Inflate;
+pub use self::incremental::{DecodeStatus, IncrementalDecoder};
+pub use self::stream::{TermReader, TermWriter};
+
+#[cfg(feature = "tokio-util")]
+pub use self::framed::{EtfFramedCodec, FramedCodecError};
+
+#[cfg(feature = "tokio")]
+pub(crate) mod async_io;
+mod flags;
+#[cfg(feature = "tokio-util")]
+mod framed;
+mod incremental;
+mod stream;
+
+/// Error which can occur when decoding a term.
+///
+/// Besides its [`DecodeErrorKind`], the error tells where in the input it occurred:
+/// the byte offset and the path from the root term to the innermost term being decoded.
+///
+/// # Migrating from 0.12
+///
+/// Up to 0.12, `DecodeError` was an enum. Its variants are now those of [`DecodeErrorKind`],
+/// with the same names and fields: match on [`DecodeError::kind`] (or [`DecodeError::into_kind`])
+/// instead of the error itself, and build errors with `DecodeError::from(DecodeErrorKind::...)`.
+pub struct DecodeError {
+    inner: Box<ErrorInner>,
+}
 #[derive(Debug)]
-pub enum DecodeError {
+struct ErrorInner {
+    kind: DecodeErrorKind,
+    offset: Option<usize>,
+    path: Vec<PathSegment>,
+}
+impl DecodeError {
+    pub fn kind(&self) -> &DecodeErrorKind {
+        &self.inner.kind
+    }
+
+    pub fn into_kind(self) -> DecodeErrorKind {
+        self.inner.kind
+    }
+
+    /// Byte offset of the innermost term being decoded when the error occurred,
+    /// counted from the first byte read by the decoder.
+    ///
+    /// For a compressed term, this is the offset in the decompressed data
+    /// (which starts with the tag of the term).
+    /// This is `None` for errors which are not caused by decoding, such as `Float::try_from(f64::NAN)`.
+    pub fn offset(&self) -> Option<usize> {
+        self.inner.offset
+    }
+
+    /// Path from the root term to the innermost term being decoded when the error occurred.
+    pub fn path(&self) -> &[PathSegment] {
+        &self.inner.path
+    }
+
+    /// Sets the offset unless it is already known.
+    pub(crate) fn at(mut self, offset: usize) -> Self {
+        self.inner.offset.get_or_insert(offset);
+        self
+    }
+
+    /// Prepends a segment to the path, as the error propagates to the parent term.
+    pub(crate) fn within(mut self, segment: PathSegment) -> Self {
+        self.inner.path.insert(0, segment);
+        self
+    }
+}
+
+impl fmt::Debug for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DecodeError")
+            .field("kind", &self.inner.kind)
+            .field("offset", &self.inner.offset)
+            .field("path", &self.inner.path)
+            .finish()
+    }
+}
+
+impl std::fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.inner.kind)?;
+        if let Some(offset) = self.inner.offset {
+            write!(f, " at byte {offset}")?;
+        }
+        for (i, segment) in self.inner.path.iter().enumerate() {
+            let separator = if i == 0 { " in " } else { " -> " };
+            write!(f, "{separator}{segment}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for DecodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        if let DecodeErrorKind::Io(error) = &self.inner.kind {
+            Some(error)
+        } else {
+            None
+        }
+    }
+}
+
+impl From<DecodeErrorKind> for DecodeError {
+    fn from(kind: DecodeErrorKind) -> Self {
+        DecodeError {
+            inner: Box::new(ErrorInner {
+                kind,
+                offset: None,
+                path: Vec::new(),
+            }),
+        }
+    }
+}
+
+impl From<std::io::Error> for DecodeError {
+    fn from(value: std::io::Error) -> Self {
+        DecodeErrorKind::Io(value).into()
+    }
+}
+
+/// Step in the path from the root term to a nested term.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSegment {
+    /// Element of a tuple at the given index.
+    TupleElement(usize),
+
+    /// Element of a list at the given index.
+    ListElement(usize),
+
+    /// Tail of an (improper) list.
+    ListTail,
+
+    /// Key of the map entry at the given index in the encoded order.
+    MapKey(usize),
+
+    /// Value of the map entry with the given key.
+    MapValue(Term),
+
+    /// Free variable of a fun at the given index.
+    FreeVariable(usize),
+}
+
+impl std::fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TupleElement(i) => write!(f, "tuple[{i}]"),
+            Self::ListElement(i) => write!(f, "list[{i}]"),
+            Self::ListTail => write!(f, "list tail"),
+            Self::MapKey(i) => write!(f, "map key #{i}"),
+            Self::MapValue(key) => write!(f, "map value for key {key}"),
+            Self::FreeVariable(i) => write!(f, "fun free variable[{i}]"),
+        }
+    }
+}
+
+/// Kinds of errors which can occur when decoding a term.
+#[derive(Debug)]
+pub enum DecodeErrorKind {
     /// I/O error.
     Io(io::Error),
 
@@ -29,9 +192,44 @@ pub enum DecodeError {
 
     /// Non-finite float.
     NonFiniteFloat,
+
+    /// A distribution header was found where a plain term was expected.
+    UnexpectedDistributionHeader,
+
+    /// A distribution header was expected but a term with the given tag was found.
+    MissingDistributionHeader { tag: u8 },
+
+    /// `ATOM_CACHE_REF` does not refer to an entry of the distribution header.
+    InvalidAtomCacheRef { index: u8 },
+
+    /// The distribution header refers to an atom cache entry that has not been set.
+    MissingAtomCacheEntry { index: u16 },
+
+    /// A limit specified by [`DecodeOptions`] was exceeded.
+    LimitExceeded { limit: DecodeLimit, max: usize },
+
+    /// An atom rejected by [`DecodeOptions::allowed_atoms`] or [`DecodeOptions::atom_filter`].
+    DisallowedAtom { name: String },
+
+    /// Bytes following the term in the input, which was expected to end with the term.
+    TrailingBytes { offset: usize },
+
+    /// The name of a UTF-8 atom is not valid UTF-8.
+    InvalidUtf8Atom { error: str::Utf8Error },
+
+    /// The sign byte of a big integer is neither `0` nor `1`.
+    InvalidSign { sign: u8 },
+
+    /// The number of bits used in the last byte of a bitstring is out of range.
+    ///
+    /// It must be `1..=8`, or `0` if the bitstring is empty.
+    InvalidBitCount { size: usize, tail_bits_size: u8 },
+
+    /// The text of a `FLOAT_EXT` is not a float.
+    InvalidFloat { text: String },
 }
 
-impl std::fmt::Display for DecodeError {
+impl std::fmt::Display for DecodeErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Io(error) => write!(f, "I/O error: {error}"),
@@ -42,40 +240,85 @@ impl std::fmt::Display for DecodeError {
             Self::UnexpectedType { value, expected } => write!(f, "{value} is not a {expected}"),
             Self::OutOfRange { value, range } => write!(f, "{value} is out of range {range:?}"),
             Self::NonFiniteFloat => write!(f, "tried to convert non-finite float"),
+            Self::UnexpectedDistributionHeader => {
+                write!(
+                    f,
+                    "unexpected distribution header (an atom cache is required)"
+                )
+            }
+            Self::MissingDistributionHeader { tag } => {
+                write!(f, "expected a distribution header but found tag {tag}")
+            }
+            Self::InvalidAtomCacheRef { index } => {
+                write!(f, "invalid atom cache reference index {index}")
+            }
+            Self::MissingAtomCacheEntry { index } => {
+                write!(f, "atom cache entry {index} is not set")
+            }
+            Self::LimitExceeded { limit, max } => {
+                write!(f, "{limit} exceeds the limit of {max}")
+            }
+            Self::DisallowedAtom { name } => write!(f, "the atom {name:?} is not allowed"),
+            Self::TrailingBytes { offset } => {
+                write!(
+                    f,
+                    "unexpected trailing bytes after the term at offset {offset}"
+                )
+            }
+            Self::InvalidUtf8Atom { error } => write!(f, "invalid UTF-8 atom name: {error}"),
+            Self::InvalidSign { sign } => {
+                write!(
+                    f,
+                    "the sign byte of an integer must be 0 or 1, but is {sign}"
+                )
+            }
+            Self::InvalidBitCount {
+                size,
+                tail_bits_size,
+            } => write!(
+                f,
+                "a bitstring of {size} bytes cannot have {tail_bits_size} bits in the last byte"
+            ),
+            Self::InvalidFloat { text } => write!(f, "{text:?} is not a float"),
         }
     }
 }
 
-impl std::error::Error for DecodeError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        if let Self::Io(error) = self {
-            Some(error)
-        } else {
-            None
-        }
-    }
-}
-
-impl From<std::io::Error> for DecodeError {
-    fn from(value: std::io::Error) -> Self {
-        Self::Io(value)
-    }
-}
-
 /// Errors which can occur when encoding a term
 #[derive(Debug)]
 pub enum EncodeError {
     /// I/O error.
     Io(io::Error),
 
-    /// Too long atom name.
+    /// Too long atom name (more than 255 characters).
     TooLongAtomName(Atom),
 
     /// Too large integer value.
     TooLargeInteger(BigInteger),
 
-    /// Too large reference ID.
+    /// Too large reference ID (more than 5 words).
     TooLargeReferenceId(Reference),
+
+    /// A term which cannot be encoded without the `required` distribution flags,
+    /// as the peer lacks them and there is no fallback encoding.
+    UnsupportedByPeer {
+        term: Term,
+        required: DistributionFlags,
+    },
+
+    /// Too many elements in a list, tuple or map, or too many free variables of a fun.
+    TooManyElements { len: usize },
+
+    /// Too large binary or bitstring.
+    TooLargeBinary { len: usize },
+
+    /// Too large fun (including its free variables).
+    TooLargeFun { size: usize },
+
+    /// The number of bits used in the last byte of a bitstring is out of range.
+    ///
+    /// It must be `1..=8` unless the bitstring is empty.
+    InvalidBitCount { size: usize, tail_bits_size: u8 },
 }
 
 impl std::fmt::Display for EncodeError {
@@ -83,7 +326,11 @@ impl std::fmt::Display for EncodeError {
         match self {
             Self::Io(error) => write!(f, "I/O error: {error}"),
             Self::TooLongAtomName(atom) => {
-                write!(f, "too long atom name: {} bytes", atom.name.len())
+                write!(
+                    f,
+                    "too long atom name: {} characters",
+                    atom.name.chars().count()
+                )
             }
             Self::TooLargeInteger(integer) => {
                 write!(
@@ -99,6 +346,36 @@ impl std::fmt::Display for EncodeError {
                     reference.id.len() * 4
                 )
             }
+            Self::UnsupportedByPeer { term, required } => {
+                write!(
+                    f,
+                    "{term} cannot be encoded without the distribution flags {required}"
+                )
+            }
+            Self::TooManyElements { len } => {
+                write!(f, "too many elements: {len} (the maximum is {})", u32::MAX)
+            }
+            Self::TooLargeBinary { len } => {
+                write!(
+                    f,
+                    "too large binary: {len} bytes (the maximum is {})",
+                    u32::MAX
+                )
+            }
+            Self::TooLargeFun { size } => {
+                write!(
+                    f,
+                    "too large fun: {size} bytes (the maximum is {})",
+                    u32::MAX
+                )
+            }
+            Self::InvalidBitCount {
+                size,
+                tail_bits_size,
+            } => write!(
+                f,
+                "a bitstring of {size} bytes cannot have {tail_bits_size} bits in the last byte"
+            ),
         }
     }
 }
@@ -122,40 +399,281 @@ impl From<std::io::Error> for EncodeError {
 pub type DecodeResult = Result<Term, DecodeError>;
 pub type EncodeResult = Result<(), EncodeError>;
 
-const VERSION: u8 = 131;
-
-const DISTRIBUTION_HEADER: u8 = 68;
-const NEW_FLOAT_EXT: u8 = 70;
-const BIT_BINARY_EXT: u8 = 77;
-const COMPRESSED_TERM: u8 = 80;
-const ATOM_CACHE_REF: u8 = 82;
-const NEW_PID_EXT: u8 = 88;
-const NEW_PORT_EXT: u8 = 89;
-const NEWER_REFERENCE_EXT: u8 = 90;
-const SMALL_INTEGER_EXT: u8 = 97;
-const INTEGER_EXT: u8 = 98;
-const FLOAT_EXT: u8 = 99;
-const ATOM_EXT: u8 = 100; // deprecated
-const REFERENCE_EXT: u8 = 101; // deprecated
-const PORT_EXT: u8 = 102;
-const PID_EXT: u8 = 103;
-const SMALL_TUPLE_EXT: u8 = 104;
-const LARGE_TUPLE_EXT: u8 = 105;
-const NIL_EXT: u8 = 106;
-const STRING_EXT: u8 = 107;
-const LIST_EXT: u8 = 108;
-const BINARY_EXT: u8 = 109;
-const SMALL_BIG_EXT: u8 = 110;
-const LARGE_BIG_EXT: u8 = 111;
-const NEW_FUN_EXT: u8 = 112;
-const EXPORT_EXT: u8 = 113;
-const NEW_REFERENCE_EXT: u8 = 114;
-const SMALL_ATOM_EXT: u8 = 115; // deprecated
-const MAP_EXT: u8 = 116;
-const FUN_EXT: u8 = 117;
-const ATOM_UTF8_EXT: u8 = 118;
-const SMALL_ATOM_UTF8_EXT: u8 = 119;
-const V4_PORT_EXT: u8 = 120;
+pub(crate) const VERSION: u8 = 131;
+
+pub(crate) const DISTRIBUTION_HEADER: u8 = 68;
+pub(crate) const NEW_FLOAT_EXT: u8 = 70;
+pub(crate) const BIT_BINARY_EXT: u8 = 77;
+pub(crate) const COMPRESSED_TERM: u8 = 80;
+pub(crate) const ATOM_CACHE_REF: u8 = 82;
+pub(crate) const NEW_PID_EXT: u8 = 88;
+pub(crate) const NEW_PORT_EXT: u8 = 89;
+pub(crate) const NEWER_REFERENCE_EXT: u8 = 90;
+pub(crate) const SMALL_INTEGER_EXT: u8 = 97;
+pub(crate) const INTEGER_EXT: u8 = 98;
+pub(crate) const FLOAT_EXT: u8 = 99;
+pub(crate) const ATOM_EXT: u8 = 100; // deprecated
+pub(crate) const REFERENCE_EXT: u8 = 101; // deprecated
+pub(crate) const PORT_EXT: u8 = 102;
+pub(crate) const PID_EXT: u8 = 103;
+pub(crate) const SMALL_TUPLE_EXT: u8 = 104;
+pub(crate) const LARGE_TUPLE_EXT: u8 = 105;
+pub(crate) const NIL_EXT: u8 = 106;
+pub(crate) const STRING_EXT: u8 = 107;
+pub(crate) const LIST_EXT: u8 = 108;
+pub(crate) const BINARY_EXT: u8 = 109;
+pub(crate) const SMALL_BIG_EXT: u8 = 110;
+pub(crate) const LARGE_BIG_EXT: u8 = 111;
+pub(crate) const NEW_FUN_EXT: u8 = 112;
+pub(crate) const EXPORT_EXT: u8 = 113;
+pub(crate) const NEW_REFERENCE_EXT: u8 = 114;
+pub(crate) const SMALL_ATOM_EXT: u8 = 115; // deprecated
+pub(crate) const MAP_EXT: u8 = 116;
+pub(crate) const FUN_EXT: u8 = 117;
+pub(crate) const ATOM_UTF8_EXT: u8 = 118;
+pub(crate) const SMALL_ATOM_UTF8_EXT: u8 = 119;
+pub(crate) const V4_PORT_EXT: u8 = 120;
+
+/// Kinds of limits of [`DecodeOptions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DecodeLimit {
+    /// See [`DecodeOptions::max_allocated_bytes`].
+    AllocatedBytes,
+
+    /// See [`DecodeOptions::max_binary_size`].
+    BinarySize,
+
+    /// See [`DecodeOptions::max_list_length`].
+    ListLength,
+
+    /// See [`DecodeOptions::max_tuple_size`].
+    TupleSize,
+
+    /// See [`DecodeOptions::max_map_size`].
+    MapSize,
+
+    /// See [`DecodeOptions::max_depth`].
+    Depth,
+
+    /// See [`DecodeOptions::max_atoms`].
+    AtomCount,
+}
+impl fmt::Display for DecodeLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AllocatedBytes => write!(f, "the number of allocated bytes"),
+            Self::BinarySize => write!(f, "the size of a binary"),
+            Self::ListLength => write!(f, "the length of a list"),
+            Self::TupleSize => write!(f, "the size of a tuple"),
+            Self::MapSize => write!(f, "the size of a map"),
+            Self::Depth => write!(f, "the nesting depth"),
+            Self::AtomCount => write!(f, "the number of atoms"),
+        }
+    }
+}
+
+#[derive(Clone)]
+enum AtomFilter {
+    Allow(Arc<HashSet<String>>),
+    Predicate(Arc<dyn Fn(&str) -> bool + Send + Sync>),
+}
+impl AtomFilter {
+    fn allows(&self, name: &str) -> bool {
+        match self {
+            Self::Allow(names) => names.contains(name),
+            Self::Predicate(f) => f(name),
+        }
+    }
+}
+impl fmt::Debug for AtomFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Allow(names) => f.debug_tuple("Allow").field(names).finish(),
+            Self::Predicate(_) => f.debug_tuple("Predicate").finish_non_exhaustive(),
+        }
+    }
+}
+
+/// Options for [`Decoder`].
+///
+/// All limits but the nesting depth are disabled by default.
+/// They should be set when decoding payloads from untrusted sources.
+#[derive(Debug, Clone)]
+pub struct DecodeOptions {
+    max_allocated_bytes: Option<usize>,
+    max_binary_size: Option<usize>,
+    max_list_length: Option<usize>,
+    max_tuple_size: Option<usize>,
+    max_map_size: Option<usize>,
+    max_depth: Option<usize>,
+    max_atoms: Option<usize>,
+    atom_filter: Option<AtomFilter>,
+}
+impl DecodeOptions {
+    /// Default options: a maximum depth of [`DecodeOptions::DEFAULT_MAX_DEPTH`], and no other limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Default maximum nesting depth, which keeps the recursive decoder within the stack
+    /// of a thread (2 MiB for spawned threads).
+    pub const DEFAULT_MAX_DEPTH: usize = 512;
+
+    /// Sets the maximum number of bytes that a decoded term may allocate.
+    ///
+    /// The number is estimated from the length fields before allocation,
+    /// counting the bytes of binaries, strings, atoms and integers,
+    /// and `size_of::<Term>()` bytes per list, tuple or map element.
+    #[must_use]
+    pub fn max_allocated_bytes(mut self, n: usize) -> Self {
+        self.max_allocated_bytes = Some(n);
+        self
+    }
+
+    /// Sets the maximum size in bytes of a binary or bitstring.
+    #[must_use]
+    pub fn max_binary_size(mut self, n: usize) -> Self {
+        self.max_binary_size = Some(n);
+        self
+    }
+
+    /// Sets the maximum number of elements in a list (including byte lists).
+    #[must_use]
+    pub fn max_list_length(mut self, n: usize) -> Self {
+        self.max_list_length = Some(n);
+        self
+    }
+
+    /// Sets the maximum number of elements in a tuple.
+    #[must_use]
+    pub fn max_tuple_size(mut self, n: usize) -> Self {
+        self.max_tuple_size = Some(n);
+        self
+    }
+
+    /// Sets the maximum number of key-value pairs in a map.
+    #[must_use]
+    pub fn max_map_size(mut self, n: usize) -> Self {
+        self.max_map_size = Some(n);
+        self
+    }
+
+    /// Sets the maximum nesting depth (the root term is at depth `0`).
+    ///
+    /// Defaults to [`DecodeOptions::DEFAULT_MAX_DEPTH`]. Raising it may overflow the stack
+    /// on deeply nested input; `usize::MAX` disables the limit.
+    #[must_use]
+    pub fn max_depth(mut self, n: usize) -> Self {
+        self.max_depth = Some(n);
+        self
+    }
+
+    /// Sets the maximum number of atoms (including node names and module names).
+    #[must_use]
+    pub fn max_atoms(mut self, n: usize) -> Self {
+        self.max_atoms = Some(n);
+        self
+    }
+
+    /// Only accepts the given atoms, like `erlang:binary_to_term(Binary, [safe])`.
+    ///
+    /// Any other atom, including those in pids, ports, references and funs,
+    /// results in [`DecodeErrorKind::DisallowedAtom`].
+    /// Note that `true`, `false` and node names also need to be listed if they may appear.
+    ///
+    /// This replaces a filter set by [`DecodeOptions::atom_filter`].
+    #[must_use]
+    pub fn allowed_atoms<I, S>(mut self, atoms: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let atoms = atoms.into_iter().map(Into::into).collect();
+        self.atom_filter = Some(AtomFilter::Allow(Arc::new(atoms)));
+        self
+    }
+
+    /// Only accepts atoms for which `f` returns `true`.
+    ///
+    /// This replaces a list set by [`DecodeOptions::allowed_atoms`].
+    #[must_use]
+    pub fn atom_filter<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        self.atom_filter = Some(AtomFilter::Predicate(Arc::new(f)));
+        self
+    }
+}
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            max_allocated_bytes: None,
+            max_binary_size: None,
+            max_list_length: None,
+            max_tuple_size: None,
+            max_map_size: None,
+            max_depth: Some(Self::DEFAULT_MAX_DEPTH),
+            max_atoms: None,
+            atom_filter: None,
+        }
+    }
+}
+
+/// Per-connection cache of atoms referred to by distribution headers.
+///
+/// See [Distribution Header](https://www.erlang.org/doc/apps/erts/erl_ext_dist.html#distribution-header).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AtomCache {
+    entries: Vec<Option<Atom>>,
+}
+impl AtomCache {
+    /// The number of the entries of a cache.
+    pub const SIZE: usize = 2048;
+
+    /// Makes an empty cache.
+    pub fn new() -> Self {
+        AtomCache {
+            entries: vec![None; Self::SIZE],
+        }
+    }
+
+    /// Returns the atom stored at `index` if it exists.
+    pub fn get(&self, index: usize) -> Option<&Atom> {
+        self.entries.get(index).and_then(Option::as_ref)
+    }
+
+    /// Removes all entries.
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+    }
+}
+impl Default for AtomCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Message sent over an Erlang distribution connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DistributionMessage {
+    /// The control message (e.g., `{2, Unused, ToPid}` for `SEND`).
+    pub control: Term,
+
+    /// The message that follows the control message, if any.
+    pub payload: Option<Term>,
+}
+impl DistributionMessage {
+    /// Decodes a message prefixed by a distribution header.
+    pub fn decode<R: io::Read>(reader: R, cache: &mut AtomCache) -> Result<Self, DecodeError> {
+        Decoder::new(reader).decode_distribution(cache)
+    }
+
+    /// Encodes the message with a distribution header.
+    pub fn encode<W: io::Write>(&self, writer: W, cache: &mut AtomCache) -> EncodeResult {
+        Encoder::new(writer).encode_distribution(self, cache)
+    }
+}
 
 struct ZlibReader<R> {
     inner: R,
@@ -201,38 +719,239 @@ impl<R: io::Read> io::Read for ZlibReader<R> {
     }
 }
 
+/// Reader which counts the bytes read, to locate decoding errors.
+struct CountingReader<R> {
+    inner: R,
+    position: usize,
+}
+impl<R: io::Read> io::Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.position += n;
+        Ok(n)
+    }
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.inner.read_exact(buf)?;
+        self.position += buf.len();
+        Ok(())
+    }
+}
+impl<T: AsRef<[u8]>> CountingReader<io::Cursor<T>> {
+    fn get_ref(&self) -> &T {
+        self.inner.get_ref()
+    }
+    fn position(&self) -> u64 {
+        self.inner.position()
+    }
+    fn set_position(&mut self, position: u64) {
+        let current = self.inner.position();
+        self.position =
+            (self.position as u64).wrapping_add(position.wrapping_sub(current)) as usize;
+        self.inner.set_position(position);
+    }
+}
+
+/// Maximum number of characters in an atom accepted by the Erlang VM.
+const MAX_ATOM_CHARACTERS: usize = 255;
+
+/// Maximum number of ID words in a reference accepted by the Erlang VM.
+const MAX_REFERENCE_ID_WORDS: usize = 5;
+
+/// Upper bound of the capacity reserved in advance based on an untrusted length field.
+const MAX_PREALLOCATION: usize = 1024;
+
+/// Decoder of a term.
+///
+/// Decoding never panics, whatever the input is: malformed input results in a [`DecodeError`].
+/// The decoder is recursive, so its nesting depth is bounded by [`DecodeOptions::max_depth`],
+/// which is [`DecodeOptions::DEFAULT_MAX_DEPTH`] by default.
 pub struct Decoder<R> {
-    reader: R,
+    reader: CountingReader<R>,
     buf: Vec<u8>,
+    atom_cache_refs: Vec<Atom>,
+    options: DecodeOptions,
+    allocated_bytes: usize,
+    atoms: usize,
+    depth: usize,
 }
 impl<R: io::Read> Decoder<R> {
     pub fn new(reader: R) -> Self {
+        Self::with_options(reader, DecodeOptions::new())
+    }
+    pub fn with_options(reader: R, options: DecodeOptions) -> Self {
         Decoder {
-            reader,
+            reader: CountingReader {
+                inner: reader,
+                position: 0,
+            },
             buf: Vec::new(),
+            atom_cache_refs: Vec::new(),
+            options,
+            allocated_bytes: 0,
+            atoms: 0,
+            depth: 0,
         }
     }
     pub fn decode(mut self) -> DecodeResult {
-        let version = self.reader.read_u8()?;
-        if version != VERSION {
-            return Err(DecodeError::UnsupportedVersion { version });
-        }
+        let result = self.decode_root();
+        result.map_err(|e| e.at(self.reader.position))
+    }
+    fn decode_root(&mut self) -> DecodeResult {
+        self.decode_version()?;
         let tag = self.reader.read_u8()?;
         match tag {
             COMPRESSED_TERM => self.decode_compressed_term(),
-            DISTRIBUTION_HEADER => unimplemented!(),
+            DISTRIBUTION_HEADER => {
+                Err(DecodeError::from(DecodeErrorKind::UnexpectedDistributionHeader).at(1))
+            }
             _ => self.decode_term_with_tag(tag),
         }
     }
+    fn decode_version(&mut self) -> Result<(), DecodeError> {
+        let offset = self.reader.position;
+        let version = self.reader.read_u8()?;
+        if version != VERSION {
+            return Err(
+                DecodeError::from(DecodeErrorKind::UnsupportedVersion { version }).at(offset),
+            );
+        }
+        Ok(())
+    }
+
+    /// Decodes a distribution header followed by a control message and an optional payload.
+    ///
+    /// New atom cache entries in the header are stored into `cache`,
+    /// so the same cache must be used for all messages received on a connection.
+    ///
+    /// The reader is expected to hold exactly one message,
+    /// as the payload is considered absent if the input ends after the control message.
+    pub fn decode_distribution(
+        mut self,
+        cache: &mut AtomCache,
+    ) -> Result<DistributionMessage, DecodeError> {
+        let result = self.decode_distribution_message(cache);
+        result.map_err(|e| e.at(self.reader.position))
+    }
+    fn decode_distribution_message(
+        &mut self,
+        cache: &mut AtomCache,
+    ) -> Result<DistributionMessage, DecodeError> {
+        self.decode_version()?;
+        let tag = self.reader.read_u8()?;
+        if tag != DISTRIBUTION_HEADER {
+            return Err(
+                DecodeError::from(DecodeErrorKind::MissingDistributionHeader { tag }).at(1),
+            );
+        }
+        self.decode_distribution_header(cache)?;
+        let control = self.decode_term()?;
+        let payload = match self.reader.read_u8_or_eof()? {
+            None => None,
+            Some(tag) => Some(self.decode_term_with_tag(tag)?),
+        };
+        Ok(DistributionMessage { control, payload })
+    }
+    fn decode_distribution_header(&mut self, cache: &mut AtomCache) -> Result<(), DecodeError> {
+        let count = self.reader.read_u8()? as usize;
+        if count == 0 {
+            return Ok(());
+        }
+        let mut flags = vec![0; count / 2 + 1];
+        self.reader.read_exact(&mut flags)?;
+        let flag = |i: usize| (flags[i / 2] >> ((i % 2) * 4)) & 0x0F;
+        let long_atoms = flag(count) & 0x01 != 0;
+        for i in 0..count {
+            let segment_index = u16::from(flag(i) & 0x07);
+            let index = (segment_index << 8) | u16::from(self.reader.read_u8()?);
+            if flag(i) & 0x08 != 0 {
+                let len = if long_atoms {
+                    self.reader.read_u16()? as usize
+                } else {
+                    self.reader.read_u8()? as usize
+                };
+                self.allocate_atom(len)?;
+                self.read_into_buf(len)?;
+                let name = aux::utf8_atom_name(&self.buf)?;
+                self.check_atom(name)?;
+                cache.entries[index as usize] = Some(Atom::from(name));
+            }
+            let atom = cache.get(index as usize).ok_or_else(|| {
+                DecodeError::from(DecodeErrorKind::MissingAtomCacheEntry { index })
+            })?;
+            self.check_atom(&atom.name)?;
+            self.atom_cache_refs.push(atom.clone());
+        }
+        Ok(())
+    }
     fn decode_term(&mut self) -> DecodeResult {
         let tag = self.reader.read_u8()?;
         self.decode_term_with_tag(tag)
     }
+    fn decode_nested_term(&mut self) -> DecodeResult {
+        self.depth += 1;
+        aux::check_limit(DecodeLimit::Depth, self.options.max_depth, self.depth)?;
+        let result = self.decode_term();
+        self.depth -= 1;
+        result
+    }
+    fn allocate(&mut self, bytes: usize) -> Result<(), DecodeError> {
+        self.allocated_bytes = self.allocated_bytes.saturating_add(bytes);
+        aux::check_limit(
+            DecodeLimit::AllocatedBytes,
+            self.options.max_allocated_bytes,
+            self.allocated_bytes,
+        )
+    }
+    fn allocate_elements(
+        &mut self,
+        count: usize,
+        limit: DecodeLimit,
+        max: Option<usize>,
+    ) -> Result<(), DecodeError> {
+        aux::check_limit(limit, max, count)?;
+        self.allocate(count.saturating_mul(std::mem::size_of::<Term>()))
+    }
+    fn allocate_atom(&mut self, len: usize) -> Result<(), DecodeError> {
+        self.atoms += 1;
+        aux::check_limit(DecodeLimit::AtomCount, self.options.max_atoms, self.atoms)?;
+        self.allocate(len)
+    }
+    fn check_atom(&self, name: &str) -> Result<(), DecodeError> {
+        match &self.options.atom_filter {
+            Some(filter) if !filter.allows(name) => Err(DecodeErrorKind::DisallowedAtom {
+                name: name.to_owned(),
+            }
+            .into()),
+            _ => Ok(()),
+        }
+    }
+    fn read_bytes(&mut self, size: usize) -> Result<Vec<u8>, DecodeError> {
+        let mut buf = Vec::with_capacity(size.min(MAX_PREALLOCATION));
+        (&mut self.reader).take(size as u64).read_to_end(&mut buf)?;
+        if buf.len() < size {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        Ok(buf)
+    }
+    fn read_into_buf(&mut self, size: usize) -> Result<(), DecodeError> {
+        self.buf.clear();
+        (&mut self.reader)
+            .take(size as u64)
+            .read_to_end(&mut self.buf)?;
+        if self.buf.len() < size {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        Ok(())
+    }
     fn decode_term_with_tag(&mut self, tag: u8) -> DecodeResult {
+        let offset = self.reader.position - 1;
+        self.decode_term_body(tag).map_err(|e| e.at(offset))
+    }
+    fn decode_term_body(&mut self, tag: u8) -> DecodeResult {
         match tag {
             NEW_FLOAT_EXT => self.decode_new_float_ext(),
             BIT_BINARY_EXT => self.decode_bit_binary_ext(),
-            ATOM_CACHE_REF => unimplemented!(),
+            ATOM_CACHE_REF => self.decode_atom_cache_ref(),
             SMALL_INTEGER_EXT => self.decode_small_integer_ext(),
             INTEGER_EXT => self.decode_integer_ext(),
             FLOAT_EXT => self.decode_float_ext(),
@@ -260,13 +979,40 @@ impl<R: io::Read> Decoder<R> {
             ATOM_UTF8_EXT => self.decode_atom_utf8_ext(),
             SMALL_ATOM_UTF8_EXT => self.decode_small_atom_utf8_ext(),
             NEWER_REFERENCE_EXT => self.decode_newer_reference_ext(),
-            _ => Err(DecodeError::UnknownTag { tag }),
+            _ => Err(DecodeErrorKind::UnknownTag { tag }.into()),
         }
     }
     fn decode_compressed_term(&mut self) -> DecodeResult {
-        let _uncompressed_size = self.reader.read_u32()? as usize;
-        let mut decoder = Decoder::new(ZlibReader::new(&mut self.reader));
-        decoder.decode_term()
+        let uncompressed_size = self.reader.read_u32()? as usize;
+        aux::check_limit(
+            DecodeLimit::AllocatedBytes,
+            self.options.max_allocated_bytes,
+            uncompressed_size,
+        )?;
+        let mut zlib = ZlibReader::new(&mut self.reader);
+        let mut decoder = Decoder::with_options(
+            io::Read::take(&mut zlib, uncompressed_size as u64),
+            self.options.clone(),
+        );
+        let term = decoder.decode_term()?;
+        if decoder.reader.position != uncompressed_size {
+            return Err(unfilled_decompressed_data());
+        }
+        if io::Read::read(&mut zlib, &mut [0])? != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "decompressed data exceeds the uncompressed size",
+            )
+            .into());
+        }
+        Ok(term)
+    }
+    fn decode_atom_cache_ref(&mut self) -> DecodeResult {
+        let index = self.reader.read_u8()?;
+        self.atom_cache_refs
+            .get(index as usize)
+            .map(|atom| Term::from(atom.clone()))
+            .ok_or_else(|| DecodeErrorKind::InvalidAtomCacheRef { index }.into())
     }
     #[allow(clippy::unnecessary_wraps)]
     fn decode_nil_ext(&mut self) -> DecodeResult {
@@ -274,17 +1020,24 @@ impl<R: io::Read> Decoder<R> {
     }
     fn decode_string_ext(&mut self) -> DecodeResult {
         let size = self.reader.read_u16()? as usize;
-        let mut bytes = vec![0; size];
-        self.reader.read_exact(&mut bytes)?;
+        aux::check_limit(DecodeLimit::ListLength, self.options.max_list_length, size)?;
+        self.allocate(size)?;
+        let bytes = self.read_bytes(size)?;
         Ok(Term::from(ByteList::from(bytes)))
     }
     fn decode_list_ext(&mut self) -> DecodeResult {
         let count = self.reader.read_u32()? as usize;
-        let mut elements = Vec::with_capacity(count);
-        for _ in 0..count {
-            elements.push(self.decode_term()?);
+        self.allocate_elements(count, DecodeLimit::ListLength, self.options.max_list_length)?;
+        let mut elements = Vec::with_capacity(count.min(MAX_PREALLOCATION));
+        for i in 0..count {
+            let element = self
+                .decode_nested_term()
+                .map_err(|e| e.within(PathSegment::ListElement(i)))?;
+            elements.push(element);
         }
-        let last = self.decode_term()?;
+        let last = self
+            .decode_nested_term()
+            .map_err(|e| e.within(PathSegment::ListTail))?;
         if last.try_as_ref().map(List::is_nil).unwrap_or(false) {
             Ok(Term::from(List::from(elements)))
         } else {
@@ -293,49 +1046,65 @@ impl<R: io::Read> Decoder<R> {
     }
     fn decode_small_tuple_ext(&mut self) -> DecodeResult {
         let count = self.reader.read_u8()? as usize;
+        self.allocate_elements(count, DecodeLimit::TupleSize, self.options.max_tuple_size)?;
         let mut elements = Vec::with_capacity(count);
-        for _ in 0..count {
-            elements.push(self.decode_term()?);
+        for i in 0..count {
+            let element = self
+                .decode_nested_term()
+                .map_err(|e| e.within(PathSegment::TupleElement(i)))?;
+            elements.push(element);
         }
         Ok(Term::from(Tuple::from(elements)))
     }
     fn decode_large_tuple_ext(&mut self) -> DecodeResult {
         let count = self.reader.read_u32()? as usize;
-        let mut elements = Vec::with_capacity(count);
-        for _ in 0..count {
-            elements.push(self.decode_term()?);
+        self.allocate_elements(count, DecodeLimit::TupleSize, self.options.max_tuple_size)?;
+        let mut elements = Vec::with_capacity(count.min(MAX_PREALLOCATION));
+        for i in 0..count {
+            let element = self
+                .decode_nested_term()
+                .map_err(|e| e.within(PathSegment::TupleElement(i)))?;
+            elements.push(element);
         }
         Ok(Term::from(Tuple::from(elements)))
     }
     fn decode_map_ext(&mut self) -> DecodeResult {
         let count = self.reader.read_u32()? as usize;
+        aux::check_limit(DecodeLimit::MapSize, self.options.max_map_size, count)?;
+        self.allocate(count.saturating_mul(2 * std::mem::size_of::<Term>()))?;
         let mut map = HashMap::<Term, Term>::new();
-        for _ in 0..count {
-            let k = self.decode_term()?;
-            let v = self.decode_term()?;
+        for i in 0..count {
+            let k = self
+                .decode_nested_term()
+                .map_err(|e| e.within(PathSegment::MapKey(i)))?;
+            let v = self
+                .decode_nested_term()
+                .map_err(|e| e.within(PathSegment::MapValue(k.clone())))?;
             map.insert(k, v);
         }
         Ok(Term::from(Map::from(map)))
     }
     fn decode_binary_ext(&mut self) -> DecodeResult {
         let size = self.reader.read_u32()? as usize;
-        let mut buf = vec![0; size];
-        self.reader.read_exact(&mut buf)?;
+        aux::check_limit(DecodeLimit::BinarySize, self.options.max_binary_size, size)?;
+        self.allocate(size)?;
+        let buf = self.read_bytes(size)?;
         Ok(Term::from(Binary::from(buf)))
     }
     fn decode_bit_binary_ext(&mut self) -> DecodeResult {
         let size = self.reader.read_u32()? as usize;
         let tail_bits_size = self.reader.read_u8()?;
-        let mut buf = vec![0; size];
-        self.reader.read_exact(&mut buf)?;
-        if !buf.is_empty() {
-            let last = buf[size - 1] >> (8 - tail_bits_size);
-            buf[size - 1] = last;
+        aux::check_limit(DecodeLimit::BinarySize, self.options.max_binary_size, size)?;
+        self.allocate(size)?;
+        aux::check_bit_count(size, tail_bits_size)?;
+        let mut buf = self.read_bytes(size)?;
+        if let Some(last) = buf.last_mut() {
+            *last >>= 8 - tail_bits_size;
         }
         Ok(Term::from(BitBinary::from((buf, tail_bits_size))))
     }
     fn decode_pid_ext(&mut self) -> DecodeResult {
-        let node = self.decode_term().and_then(aux::term_into_atom)?;
+        let node = self.decode_nested_term().and_then(aux::term_into_atom)?;
         Ok(Term::from(Pid {
             node,
             id: self.reader.read_u32()?,
@@ -344,7 +1113,7 @@ impl<R: io::Read> Decoder<R> {
         }))
     }
     fn decode_new_pid_ext(&mut self) -> DecodeResult {
-        let node = self.decode_term().and_then(aux::term_into_atom)?;
+        let node = self.decode_nested_term().and_then(aux::term_into_atom)?;
         Ok(Term::from(Pid {
             node,
             id: self.reader.read_u32()?,
@@ -353,12 +1122,7 @@ impl<R: io::Read> Decoder<R> {
         }))
     }
     fn decode_port_ext(&mut self) -> DecodeResult {
-        let node: Atom = self.decode_term().and_then(|t| {
-            t.try_into().map_err(|t| DecodeError::UnexpectedType {
-                value: t,
-                expected: "Atom".to_string(),
-            })
-        })?;
+        let node = self.decode_nested_term().and_then(aux::term_into_atom)?;
         Ok(Term::from(Port {
             node,
             id: u64::from(self.reader.read_u32()?),
@@ -366,12 +1130,7 @@ impl<R: io::Read> Decoder<R> {
         }))
     }
     fn decode_new_port_ext(&mut self) -> DecodeResult {
-        let node: Atom = self.decode_term().and_then(|t| {
-            t.try_into().map_err(|t| DecodeError::UnexpectedType {
-                value: t,
-                expected: "Atom".to_string(),
-            })
-        })?;
+        let node = self.decode_nested_term().and_then(aux::term_into_atom)?;
         Ok(Term::from(Port {
             node,
             id: u64::from(self.reader.read_u32()?),
@@ -379,12 +1138,7 @@ impl<R: io::Read> Decoder<R> {
         }))
     }
     fn decode_v4_port_ext(&mut self) -> DecodeResult {
-        let node: Atom = self.decode_term().and_then(|t| {
-            t.try_into().map_err(|t| DecodeError::UnexpectedType {
-                value: t,
-                expected: "Atom".to_string(),
-            })
-        })?;
+        let node = self.decode_nested_term().and_then(aux::term_into_atom)?;
         Ok(Term::from(Port {
             node,
             id: self.reader.read_u64()?,
@@ -392,7 +1146,7 @@ impl<R: io::Read> Decoder<R> {
         }))
     }
     fn decode_reference_ext(&mut self) -> DecodeResult {
-        let node = self.decode_term().and_then(aux::term_into_atom)?;
+        let node = self.decode_nested_term().and_then(aux::term_into_atom)?;
         Ok(Term::from(Reference {
             node,
             id: vec![self.reader.read_u32()?],
@@ -401,8 +1155,9 @@ impl<R: io::Read> Decoder<R> {
     }
     fn decode_new_reference_ext(&mut self) -> DecodeResult {
         let id_count = self.reader.read_u16()? as usize;
-        let node = self.decode_term().and_then(aux::term_into_atom)?;
+        let node = self.decode_nested_term().and_then(aux::term_into_atom)?;
         let creation = u32::from(self.reader.read_u8()?);
+        self.allocate(id_count * 4)?;
         let mut id = Vec::with_capacity(id_count);
         for _ in 0..id_count {
             id.push(self.reader.read_u32()?);
@@ -411,8 +1166,9 @@ impl<R: io::Read> Decoder<R> {
     }
     fn decode_newer_reference_ext(&mut self) -> DecodeResult {
         let id_count = self.reader.read_u16()? as usize;
-        let node = self.decode_term().and_then(aux::term_into_atom)?;
+        let node = self.decode_nested_term().and_then(aux::term_into_atom)?;
         let creation = self.reader.read_u32()?;
+        self.allocate(id_count * 4)?;
         let mut id = Vec::with_capacity(id_count);
         for _ in 0..id_count {
             id.push(self.reader.read_u32()?);
@@ -420,10 +1176,10 @@ impl<R: io::Read> Decoder<R> {
         Ok(Term::from(Reference { node, id, creation }))
     }
     fn decode_export_ext(&mut self) -> DecodeResult {
-        let module = self.decode_term().and_then(aux::term_into_atom)?;
-        let function = self.decode_term().and_then(aux::term_into_atom)?;
+        let module = self.decode_nested_term().and_then(aux::term_into_atom)?;
+        let function = self.decode_nested_term().and_then(aux::term_into_atom)?;
         let arity = self
-            .decode_term()
+            .decode_nested_term()
             .and_then(|t| aux::term_into_ranged_integer(t, 0..0xFF))? as u8;
         Ok(Term::from(ExternalFun {
             module,
@@ -433,13 +1189,25 @@ impl<R: io::Read> Decoder<R> {
     }
     fn decode_fun_ext(&mut self) -> DecodeResult {
         let num_free = self.reader.read_u32()?;
-        let pid = self.decode_term().and_then(aux::term_into_pid)?;
-        let module = self.decode_term().and_then(aux::term_into_atom)?;
-        let index = self.decode_term().and_then(aux::term_into_fix_integer)?;
-        let uniq = self.decode_term().and_then(aux::term_into_fix_integer)?;
-        let mut vars = Vec::with_capacity(num_free as usize);
-        for _ in 0..num_free {
-            vars.push(self.decode_term()?);
+        let pid = self.decode_nested_term().and_then(aux::term_into_pid)?;
+        let module = self.decode_nested_term().and_then(aux::term_into_atom)?;
+        let index = self
+            .decode_nested_term()
+            .and_then(aux::term_into_fix_integer)?;
+        let uniq = self
+            .decode_nested_term()
+            .and_then(aux::term_into_fix_integer)?;
+        self.allocate_elements(
+            num_free as usize,
+            DecodeLimit::ListLength,
+            self.options.max_list_length,
+        )?;
+        let mut vars = Vec::with_capacity((num_free as usize).min(MAX_PREALLOCATION));
+        for i in 0..num_free as usize {
+            let var = self
+                .decode_nested_term()
+                .map_err(|e| e.within(PathSegment::FreeVariable(i)))?;
+            vars.push(var);
         }
         Ok(Term::from(InternalFun::Old {
             module,
@@ -456,13 +1224,25 @@ impl<R: io::Read> Decoder<R> {
         self.reader.read_exact(&mut uniq)?;
         let index = self.reader.read_u32()?;
         let num_free = self.reader.read_u32()?;
-        let module = self.decode_term().and_then(aux::term_into_atom)?;
-        let old_index = self.decode_term().and_then(aux::term_into_fix_integer)?;
-        let old_uniq = self.decode_term().and_then(aux::term_into_fix_integer)?;
-        let pid = self.decode_term().and_then(aux::term_into_pid)?;
-        let mut vars = Vec::with_capacity(num_free as usize);
-        for _ in 0..num_free {
-            vars.push(self.decode_term()?);
+        let module = self.decode_nested_term().and_then(aux::term_into_atom)?;
+        let old_index = self
+            .decode_nested_term()
+            .and_then(aux::term_into_fix_integer)?;
+        let old_uniq = self
+            .decode_nested_term()
+            .and_then(aux::term_into_fix_integer)?;
+        let pid = self.decode_nested_term().and_then(aux::term_into_pid)?;
+        self.allocate_elements(
+            num_free as usize,
+            DecodeLimit::ListLength,
+            self.options.max_list_length,
+        )?;
+        let mut vars = Vec::with_capacity((num_free as usize).min(MAX_PREALLOCATION));
+        for i in 0..num_free as usize {
+            let var = self
+                .decode_nested_term()
+                .map_err(|e| e.within(PathSegment::FreeVariable(i)))?;
+            vars.push(var);
         }
         Ok(Term::from(InternalFun::New {
             module,
@@ -482,12 +1262,16 @@ impl<R: io::Read> Decoder<R> {
     fn decode_float_ext(&mut self) -> DecodeResult {
         let mut buf = [0; 31];
         self.reader.read_exact(&mut buf)?;
-        let float_str = str::from_utf8(&buf)
-            .or_else(|e| aux::invalid_data_error(e.to_string()))?
-            .trim_end_matches(0 as char);
-        let value = float_str
-            .parse::<f32>()
-            .or_else(|e| aux::invalid_data_error(e.to_string()))?;
+        let text = aux::latin1_bytes_to_string(&buf);
+        let text = text.trim_end_matches('\0');
+        // Rust also accepts "inf" and "NaN", which are not valid Erlang floats
+        // and are rejected by `Float::try_from` below.
+        let value = text
+            .trim()
+            .parse::<f64>()
+            .map_err(|_| DecodeErrorKind::InvalidFloat {
+                text: text.to_owned(),
+            })?;
         Ok(Term::from(Float::try_from(value)?))
     }
     fn decode_small_integer_ext(&mut self) -> DecodeResult {
@@ -501,59 +1285,507 @@ impl<R: io::Read> Decoder<R> {
     fn decode_small_big_ext(&mut self) -> DecodeResult {
         let count = self.reader.read_u8()? as usize;
         let sign = self.reader.read_u8()?;
-        self.buf.resize(count, 0);
-        self.reader.read_exact(&mut self.buf)?;
+        self.allocate(count)?;
+        self.read_into_buf(count)?;
         let value = BigInt::from_bytes_le(aux::byte_to_sign(sign)?, &self.buf);
         Ok(Term::from(BigInteger { value }))
     }
     fn decode_large_big_ext(&mut self) -> DecodeResult {
         let count = self.reader.read_u32()? as usize;
         let sign = self.reader.read_u8()?;
-        self.buf.resize(count, 0);
-        self.reader.read_exact(&mut self.buf)?;
+        self.allocate(count)?;
+        self.read_into_buf(count)?;
         let value = BigInt::from_bytes_le(aux::byte_to_sign(sign)?, &self.buf);
         Ok(Term::from(BigInteger { value }))
     }
     fn decode_atom_ext(&mut self) -> DecodeResult {
         let len = self.reader.read_u16()?;
-        self.buf.resize(len as usize, 0);
-        self.reader.read_exact(&mut self.buf)?;
-        let name = aux::latin1_bytes_to_string(&self.buf)?;
+        self.allocate_atom(len as usize)?;
+        self.read_into_buf(len as usize)?;
+        let name = aux::latin1_bytes_to_string(&self.buf);
+        self.check_atom(&name)?;
         Ok(Term::from(Atom { name }))
     }
     fn decode_small_atom_ext(&mut self) -> DecodeResult {
         let len = self.reader.read_u8()?;
-        self.buf.resize(len as usize, 0);
-        self.reader.read_exact(&mut self.buf)?;
-        let name = aux::latin1_bytes_to_string(&self.buf)?;
+        self.allocate_atom(len as usize)?;
+        self.read_into_buf(len as usize)?;
+        let name = aux::latin1_bytes_to_string(&self.buf);
+        self.check_atom(&name)?;
         Ok(Term::from(Atom { name }))
     }
     fn decode_atom_utf8_ext(&mut self) -> DecodeResult {
         let len = self.reader.read_u16()?;
-        self.buf.resize(len as usize, 0);
-        self.reader.read_exact(&mut self.buf)?;
-        let name = str::from_utf8(&self.buf).or_else(|e| aux::invalid_data_error(e.to_string()))?;
+        self.allocate_atom(len as usize)?;
+        self.read_into_buf(len as usize)?;
+        let name = aux::utf8_atom_name(&self.buf)?;
+        self.check_atom(name)?;
         Ok(Term::from(Atom::from(name)))
     }
     fn decode_small_atom_utf8_ext(&mut self) -> DecodeResult {
         let len = self.reader.read_u8()?;
-        self.buf.resize(len as usize, 0);
-        self.reader.read_exact(&mut self.buf)?;
-        let name = str::from_utf8(&self.buf).or_else(|e| aux::invalid_data_error(e.to_string()))?;
+        self.allocate_atom(len as usize)?;
+        self.read_into_buf(len as usize)?;
+        let name = aux::utf8_atom_name(&self.buf)?;
+        self.check_atom(name)?;
         Ok(Term::from(Atom::from(name)))
     }
 }
 
+impl<'a> Decoder<io::Cursor<&'a [u8]>> {
+    /// Decodes a term borrowing atoms, binaries and byte lists from the input slice.
+    pub fn decode_borrowed(mut self) -> Result<TermRef<'a>, DecodeError> {
+        let result = self.decode_borrowed_root();
+        result.map_err(|e| e.at(self.reader.position))
+    }
+    fn decode_borrowed_root(&mut self) -> Result<TermRef<'a>, DecodeError> {
+        self.decode_version()?;
+        let tag = self.reader.read_u8()?;
+        match tag {
+            COMPRESSED_TERM => self.decode_compressed_slice().map(TermRef::from),
+            DISTRIBUTION_HEADER => {
+                Err(DecodeError::from(DecodeErrorKind::UnexpectedDistributionHeader).at(1))
+            }
+            _ => self.decode_term_ref_with_tag(tag),
+        }
+    }
+
+    /// Decodes a term and returns it along with the number of bytes it occupies in the input.
+    ///
+    /// Unlike [`Decoder::decode`], this never reads past the end of a compressed term,
+    /// so the input may contain other data after the term.
+    pub fn decode_from_slice(mut self) -> Result<(Term, usize), DecodeError> {
+        let start = self.reader.position();
+        let result = self.decode_slice_root();
+        let term = result.map_err(|e| e.at(self.reader.position))?;
+        Ok((term, (self.reader.position() - start) as usize))
+    }
+    fn decode_slice_root(&mut self) -> DecodeResult {
+        self.decode_version()?;
+        let tag = self.reader.read_u8()?;
+        match tag {
+            COMPRESSED_TERM => self.decode_compressed_slice(),
+            DISTRIBUTION_HEADER => {
+                Err(DecodeError::from(DecodeErrorKind::UnexpectedDistributionHeader).at(1))
+            }
+            _ => self.decode_term_with_tag(tag),
+        }
+    }
+
+    /// Decodes a term that must extend to the end of the input.
+    ///
+    /// If any bytes follow the term, [`DecodeErrorKind::TrailingBytes`] is returned
+    /// with the offset of the first of them.
+    pub fn decode_exact(self) -> DecodeResult {
+        let start = self.reader.position() as usize;
+        let len = self.reader.get_ref().len();
+        let (term, consumed) = self.decode_from_slice()?;
+        let offset = start + consumed;
+        if offset < len {
+            return Err(DecodeError::from(DecodeErrorKind::TrailingBytes { offset }).at(consumed));
+        }
+        Ok(term)
+    }
+
+    fn decode_compressed_slice(&mut self) -> DecodeResult {
+        let bytes: &'a [u8] = self.reader.get_ref();
+        let start = (self.reader.position() as usize).min(bytes.len());
+        let (body, consumed) = inflate_compressed_term(&bytes[start..], &self.options)?;
+        self.reader.set_position((start + consumed) as u64);
+        decode_inflated_term(&body, &self.options)
+    }
+
+    fn decode_nested_term_ref(&mut self) -> Result<TermRef<'a>, DecodeError> {
+        self.depth += 1;
+        aux::check_limit(DecodeLimit::Depth, self.options.max_depth, self.depth)?;
+        let tag = self.reader.read_u8()?;
+        let result = self.decode_term_ref_with_tag(tag);
+        self.depth -= 1;
+        result
+    }
+    fn decode_term_ref_with_tag(&mut self, tag: u8) -> Result<TermRef<'a>, DecodeError> {
+        let offset = self.reader.position - 1;
+        self.decode_term_ref_body(tag).map_err(|e| e.at(offset))
+    }
+    fn decode_term_ref_body(&mut self, tag: u8) -> Result<TermRef<'a>, DecodeError> {
+        match tag {
+            ATOM_EXT | ATOM_UTF8_EXT => {
+                let len = self.reader.read_u16()?;
+                self.decode_atom_ref(len as usize, tag == ATOM_EXT)
+            }
+            SMALL_ATOM_EXT | SMALL_ATOM_UTF8_EXT => {
+                let len = self.reader.read_u8()?;
+                self.decode_atom_ref(len as usize, tag == SMALL_ATOM_EXT)
+            }
+            STRING_EXT => {
+                let size = self.reader.read_u16()? as usize;
+                aux::check_limit(DecodeLimit::ListLength, self.options.max_list_length, size)?;
+                Ok(TermRef::ByteList(Cow::Borrowed(self.read_slice(size)?)))
+            }
+            BINARY_EXT => {
+                let size = self.reader.read_u32()? as usize;
+                aux::check_limit(DecodeLimit::BinarySize, self.options.max_binary_size, size)?;
+                Ok(TermRef::Binary(Cow::Borrowed(self.read_slice(size)?)))
+            }
+            BIT_BINARY_EXT => {
+                let size = self.reader.read_u32()? as usize;
+                let tail_bits_size = self.reader.read_u8()?;
+                aux::check_limit(DecodeLimit::BinarySize, self.options.max_binary_size, size)?;
+                aux::check_bit_count(size, tail_bits_size)?;
+                Ok(TermRef::BitBinary {
+                    bytes: Cow::Borrowed(self.read_slice(size)?),
+                    tail_bits_size,
+                })
+            }
+            LIST_EXT => {
+                let count = self.reader.read_u32()? as usize;
+                self.allocate_elements(
+                    count,
+                    DecodeLimit::ListLength,
+                    self.options.max_list_length,
+                )?;
+                let mut elements = Vec::with_capacity(count.min(MAX_PREALLOCATION));
+                for i in 0..count {
+                    let element = self
+                        .decode_nested_term_ref()
+                        .map_err(|e| e.within(PathSegment::ListElement(i)))?;
+                    elements.push(element);
+                }
+                let last = self
+                    .decode_nested_term_ref()
+                    .map_err(|e| e.within(PathSegment::ListTail))?;
+                match last {
+                    TermRef::List(nil) if nil.is_empty() => Ok(TermRef::List(elements)),
+                    last => Ok(TermRef::ImproperList {
+                        elements,
+                        last: Box::new(last),
+                    }),
+                }
+            }
+            SMALL_TUPLE_EXT | LARGE_TUPLE_EXT => {
+                let count = if tag == SMALL_TUPLE_EXT {
+                    self.reader.read_u8()? as usize
+                } else {
+                    self.reader.read_u32()? as usize
+                };
+                self.allocate_elements(count, DecodeLimit::TupleSize, self.options.max_tuple_size)?;
+                let mut elements = Vec::with_capacity(count.min(MAX_PREALLOCATION));
+                for i in 0..count {
+                    let element = self
+                        .decode_nested_term_ref()
+                        .map_err(|e| e.within(PathSegment::TupleElement(i)))?;
+                    elements.push(element);
+                }
+                Ok(TermRef::Tuple(elements))
+            }
+            MAP_EXT => {
+                let count = self.reader.read_u32()? as usize;
+                aux::check_limit(DecodeLimit::MapSize, self.options.max_map_size, count)?;
+                self.allocate(count.saturating_mul(2 * std::mem::size_of::<TermRef<'_>>()))?;
+                let mut pairs = Vec::with_capacity(count.min(MAX_PREALLOCATION));
+                for i in 0..count {
+                    let k = self
+                        .decode_nested_term_ref()
+                        .map_err(|e| e.within(PathSegment::MapKey(i)))?;
+                    let v = self
+                        .decode_nested_term_ref()
+                        .map_err(|e| e.within(PathSegment::MapValue(k.to_owned())))?;
+                    pairs.push((k, v));
+                }
+                Ok(TermRef::Map(pairs))
+            }
+            _ => self.decode_term_with_tag(tag).map(TermRef::from),
+        }
+    }
+    fn decode_atom_ref(&mut self, len: usize, latin1: bool) -> Result<TermRef<'a>, DecodeError> {
+        self.atoms += 1;
+        aux::check_limit(DecodeLimit::AtomCount, self.options.max_atoms, self.atoms)?;
+        let bytes = self.read_slice(len)?;
+        let name = if latin1 && !bytes.is_ascii() {
+            Cow::Owned(aux::latin1_bytes_to_string(bytes))
+        } else {
+            Cow::Borrowed(aux::utf8_atom_name(bytes)?)
+        };
+        self.check_atom(&name)?;
+        Ok(TermRef::Atom(name))
+    }
+    fn read_slice(&mut self, size: usize) -> Result<&'a [u8], DecodeError> {
+        let bytes: &'a [u8] = self.reader.get_ref();
+        let start = self.reader.position() as usize;
+        let slice = start
+            .checked_add(size)
+            .and_then(|end| bytes.get(start..end))
+            .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+        self.reader.set_position((start + size) as u64);
+        Ok(slice)
+    }
+}
+
+/// Returns the number of bytes occupied by the term at `offset` in `bytes`,
+/// which is not prefixed by the version byte.
+///
+/// The offsets of errors are counted from the beginning of `bytes`.
+#[cfg(feature = "serde")]
+pub(crate) fn term_size(
+    bytes: &[u8],
+    offset: usize,
+    options: DecodeOptions,
+) -> Result<usize, DecodeError> {
+    let mut decoder = Decoder::with_options(io::Cursor::new(bytes), options);
+    decoder.reader.set_position(offset as u64);
+    let tag = decoder
+        .reader
+        .read_u8()
+        .map_err(|e| DecodeError::from(e).at(offset))?;
+    decoder.decode_term_ref_with_tag(tag)?;
+    Ok(decoder.reader.position - offset)
+}
+
+/// Decodes the inflated body of a `COMPRESSED_TERM`, which must hold exactly one term.
+pub(crate) fn decode_inflated_term(body: &[u8], options: &DecodeOptions) -> DecodeResult {
+    let mut decoder = Decoder::with_options(body, options.clone());
+    let term = decoder.decode_term()?;
+    if decoder.reader.position != body.len() {
+        return Err(unfilled_decompressed_data());
+    }
+    Ok(term)
+}
+
+fn unfilled_decompressed_data() -> DecodeError {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        "compressed term is shorter than the decompressed data",
+    )
+    .into()
+}
+
+/// Inflates the body of a `COMPRESSED_TERM`, where `input` starts at its uncompressed size field.
+///
+/// Returns the body along with the number of bytes it occupies in the input.
+pub(crate) fn inflate_compressed_term(
+    input: &[u8],
+    options: &DecodeOptions,
+) -> Result<(Vec<u8>, usize), DecodeError> {
+    let mut reader = input;
+    let uncompressed_size = reader.read_u32()? as usize;
+    aux::check_limit(
+        DecodeLimit::AllocatedBytes,
+        options.max_allocated_bytes,
+        uncompressed_size,
+    )?;
+    let mut inflate = Inflate::new(uncompressed_size);
+    let mut body = Vec::new();
+    let rest = inflate.feed(reader, &mut body)?;
+    if !inflate.is_finished() {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "zlib stream ended before finish",
+        )
+        .into());
+    }
+    Ok((body, input.len() - rest.len()))
+}
+
+/// Options for [`Encoder`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EncodeOptions {
+    compression_level: Option<u8>,
+    always_compress: bool,
+    deterministic: bool,
+    distribution_flags: Option<DistributionFlags>,
+}
+impl EncodeOptions {
+    /// Default options: the term is encoded without compression.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Emits the term as `COMPRESSED_TERM` using the given zlib compression level.
+    ///
+    /// This corresponds to `erlang:term_to_binary(Term, [{compressed, Level}])`.
+    /// Like ERTS, the compressed form is only used if it is smaller than the
+    /// uncompressed one (see [`EncodeOptions::always_compress`]).
+    ///
+    /// Level `0` stores the data without actual compression.
+    /// The underlying deflate encoder has no other compression levels, so levels `1` to `9`
+    /// are clamped to zlib's default level `6`:
+    /// `EncodeOptions::new().compressed(1) == EncodeOptions::new().compressed(6)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `level` is greater than `9`.
+    #[must_use]
+    pub fn compressed(mut self, level: u8) -> Self {
+        assert!(level <= 9, "compression level must be 0..=9: level={level}");
+        self.compression_level = Some(if level == 0 { 0 } else { 6 });
+        self
+    }
+
+    /// Emits `COMPRESSED_TERM` even if it is not smaller than the uncompressed form.
+    ///
+    /// This has no effect unless [`EncodeOptions::compressed`] is also specified.
+    #[must_use]
+    pub fn always_compress(mut self) -> Self {
+        self.always_compress = true;
+        self
+    }
+
+    /// Encodes map entries ordered by their keys, so equal terms always produce the same bytes.
+    ///
+    /// This corresponds to `erlang:term_to_binary(Term, [deterministic])`.
+    /// Keys are sorted in map key order: Erlang term order, except that all integers
+    /// are ordered before all floats (`#{2 => a, 1.0 => b}` puts `2` first).
+    #[must_use]
+    pub fn deterministic(mut self) -> Self {
+        self.deterministic = true;
+        self
+    }
+
+    /// Restricts the encoding to what a peer with the given capabilities understands.
+    ///
+    /// By default, [`DistributionFlags::all`] is assumed.
+    /// For missing flags, the encoder falls back to older tags as ERTS does:
+    ///
+    /// - `BIG_CREATION`: `PID_EXT`, `PORT_EXT` and `NEW_REFERENCE_EXT` with 8-bit creations
+    /// - `EXTENDED_REFERENCES`: `REFERENCE_EXT` (references with a single ID word only)
+    /// - `UTF8_ATOMS`: latin-1 `ATOM_EXT` (or `SMALL_ATOM_EXT` with `SMALL_ATOM_TAGS`)
+    /// - `NEW_FLOATS`: textual `FLOAT_EXT`
+    /// - `BIT_BINARIES`: `{Binary, Bits}` tuples
+    /// - `EXPORT_PTR_TAG`: `{Module, Function}` tuples
+    /// - `V4_NC`: ports with 32-bit IDs and references with up to 3 ID words only
+    /// - `MAP_TAG`: no fallback
+    ///
+    /// If a term cannot be represented, [`EncodeError::UnsupportedByPeer`] is returned.
+    #[must_use]
+    pub fn distribution_flags(mut self, flags: DistributionFlags) -> Self {
+        self.distribution_flags = Some(flags);
+        self
+    }
+
+    fn has_flags(&self, flags: DistributionFlags) -> bool {
+        self.distribution_flags
+            .unwrap_or(DistributionFlags::all())
+            .contains(flags)
+    }
+}
+
+/// Encoder of a term.
 pub struct Encoder<W> {
     writer: W,
+    options: EncodeOptions,
+    atom_cache_refs: HashMap<Atom, u8>,
 }
 impl<W: io::Write> Encoder<W> {
     pub fn new(writer: W) -> Self {
-        Encoder { writer }
+        Self::with_options(writer, EncodeOptions::new())
+    }
+    pub fn with_options(writer: W, options: EncodeOptions) -> Self {
+        Encoder {
+            writer,
+            options,
+            atom_cache_refs: HashMap::new(),
+        }
     }
     pub fn encode(mut self, term: &Term) -> EncodeResult {
         self.writer.write_u8(VERSION)?;
-        self.encode_term(term)
+        if let Some(level) = self.options.compression_level {
+            self.encode_compressed_term(term, level)
+        } else {
+            self.encode_term(term)
+        }
+    }
+
+    /// Encodes a distribution header followed by the control message and the payload.
+    ///
+    /// Atoms in the message are registered to `cache` and encoded as `ATOM_CACHE_REF`
+    /// where possible, so the same cache must be used for all messages sent on a connection.
+    /// Compression is never applied to distribution messages.
+    pub fn encode_distribution(
+        mut self,
+        message: &DistributionMessage,
+        cache: &mut AtomCache,
+    ) -> EncodeResult {
+        let mut atoms = Vec::new();
+        aux::collect_atoms(&message.control, &mut atoms);
+        if let Some(payload) = &message.payload {
+            aux::collect_atoms(payload, &mut atoms);
+        }
+
+        let mut refs = Vec::<(u16, bool, &Atom)>::new();
+        let mut used_indices = HashSet::new();
+        for atom in atoms {
+            if refs.len() == usize::from(u8::MAX) {
+                break;
+            }
+            if self.atom_cache_refs.contains_key(atom)
+                || atom.name.chars().count() > MAX_ATOM_CHARACTERS
+            {
+                continue;
+            }
+            let index = aux::atom_cache_index(atom);
+            if !used_indices.insert(index) {
+                // Another atom in this message already occupies the entry.
+                continue;
+            }
+            let is_new = cache.get(usize::from(index)) != Some(atom);
+            if is_new {
+                cache.entries[usize::from(index)] = Some(atom.clone());
+            }
+            self.atom_cache_refs.insert(atom.clone(), refs.len() as u8);
+            refs.push((index, is_new, atom));
+        }
+
+        self.writer.write_u8(VERSION)?;
+        self.writer.write_u8(DISTRIBUTION_HEADER)?;
+        self.writer.write_u8(refs.len() as u8)?;
+        if !refs.is_empty() {
+            let long_atoms = refs
+                .iter()
+                .any(|&(_, is_new, atom)| is_new && atom.name.len() > usize::from(u8::MAX));
+            let mut flags = vec![0; refs.len() / 2 + 1];
+            for (i, &(index, is_new, _)) in refs.iter().enumerate() {
+                let flag = (if is_new { 0x08 } else { 0 }) | (index >> 8) as u8;
+                flags[i / 2] |= flag << ((i % 2) * 4);
+            }
+            if long_atoms {
+                flags[refs.len() / 2] |= 0x01 << ((refs.len() % 2) * 4);
+            }
+            self.writer.write_all(&flags)?;
+            for &(index, is_new, atom) in &refs {
+                self.writer.write_u8(index as u8)?;
+                if is_new {
+                    if long_atoms {
+                        self.writer.write_u16(atom.name.len() as u16)?;
+                    } else {
+                        self.writer.write_u8(atom.name.len() as u8)?;
+                    }
+                    self.writer.write_all(atom.name.as_bytes())?;
+                }
+            }
+        }
+
+        self.encode_term(&message.control)?;
+        if let Some(payload) = &message.payload {
+            self.encode_term(payload)?;
+        }
+        Ok(())
+    }
+    fn encode_compressed_term(&mut self, term: &Term, level: u8) -> EncodeResult {
+        let mut body = Vec::new();
+        Encoder::with_options(&mut body, self.options.clone()).encode_term(term)?;
+        let uncompressed_size = u32::try_from(body.len()).ok();
+        let compressed = aux::zlib_compress(&body, level)?;
+        match uncompressed_size {
+            Some(size) if self.options.always_compress || 5 + compressed.len() < body.len() => {
+                self.writer.write_u8(COMPRESSED_TERM)?;
+                self.writer.write_u32(size)?;
+                self.writer.write_all(&compressed)?;
+            }
+            _ => {
+                self.writer.write_all(&body)?;
+            }
+        }
+        Ok(())
     }
     fn encode_term(&mut self, term: &Term) -> EncodeResult {
         match *term {
@@ -575,6 +1807,28 @@ impl<W: io::Write> Encoder<W> {
             Term::ByteList(ref x) => self.encode_byte_list(x.bytes.as_slice()),
         }
     }
+    fn require<F>(&self, flags: DistributionFlags, term: F) -> EncodeResult
+    where
+        F: FnOnce() -> Term,
+    {
+        if self.options.has_flags(flags) {
+            Ok(())
+        } else {
+            Err(EncodeError::UnsupportedByPeer {
+                term: term(),
+                required: flags,
+            })
+        }
+    }
+    fn small_creation<F>(&self, creation: u32, term: F) -> Result<u8, EncodeError>
+    where
+        F: FnOnce() -> Term,
+    {
+        u8::try_from(creation).map_err(|_| EncodeError::UnsupportedByPeer {
+            term: term(),
+            required: DistributionFlags::BIG_CREATION,
+        })
+    }
     fn encode_nil(&mut self) -> EncodeResult {
         self.writer.write_u8(NIL_EXT)?;
         Ok(())
@@ -582,7 +1836,7 @@ impl<W: io::Write> Encoder<W> {
     fn encode_list(&mut self, x: &List) -> EncodeResult {
         let to_byte = |e: &Term| {
             e.try_as_ref()
-                .and_then(|&FixInteger { value: i }| if i < 0x100 { Some(i as u8) } else { None })
+                .and_then(|&FixInteger { value: i }| u8::try_from(i).ok())
         };
         if !x.elements.is_empty()
             && x.elements.len() <= u16::MAX as usize
@@ -590,13 +1844,14 @@ impl<W: io::Write> Encoder<W> {
         {
             self.writer.write_u8(STRING_EXT)?;
             self.writer.write_u16(x.elements.len() as u16)?;
-            for b in x.elements.iter().map(|e| to_byte(e).unwrap()) {
+            for b in x.elements.iter().filter_map(to_byte) {
                 self.writer.write_u8(b)?;
             }
         } else {
             if !x.is_nil() {
                 self.writer.write_u8(LIST_EXT)?;
-                self.writer.write_u32(x.elements.len() as u32)?;
+                self.writer
+                    .write_u32(aux::element_count(x.elements.len())?)?;
                 for e in &x.elements {
                     self.encode_term(e)?;
                 }
@@ -607,7 +1862,8 @@ impl<W: io::Write> Encoder<W> {
     }
     fn encode_improper_list(&mut self, x: &ImproperList) -> EncodeResult {
         self.writer.write_u8(LIST_EXT)?;
-        self.writer.write_u32(x.elements.len() as u32)?;
+        self.writer
+            .write_u32(aux::element_count(x.elements.len())?)?;
         for e in &x.elements {
             self.encode_term(e)?;
         }
@@ -620,7 +1876,8 @@ impl<W: io::Write> Encoder<W> {
             self.writer.write_u8(x.elements.len() as u8)?;
         } else {
             self.writer.write_u8(LARGE_TUPLE_EXT)?;
-            self.writer.write_u32(x.elements.len() as u32)?;
+            self.writer
+                .write_u32(aux::element_count(x.elements.len())?)?;
         }
         for e in &x.elements {
             self.encode_term(e)?;
@@ -628,56 +1885,135 @@ impl<W: io::Write> Encoder<W> {
         Ok(())
     }
     fn encode_map(&mut self, x: &Map) -> EncodeResult {
+        self.require(DistributionFlags::MAP_TAG, || Term::from(x.clone()))?;
         self.writer.write_u8(MAP_EXT)?;
-        self.writer.write_u32(x.map.len() as u32)?;
-        for (k, v) in x.map.iter() {
-            self.encode_term(k)?;
-            self.encode_term(v)?;
+        self.writer.write_u32(aux::element_count(x.map.len())?)?;
+        if self.options.deterministic {
+            for (k, v) in crate::order::sorted_entries(x) {
+                self.encode_term(k)?;
+                self.encode_term(v)?;
+            }
+        } else {
+            for (k, v) in x.map.iter() {
+                self.encode_term(k)?;
+                self.encode_term(v)?;
+            }
         }
         Ok(())
     }
     fn encode_byte_list(&mut self, x: &[u8]) -> EncodeResult {
+        let Ok(len) = u16::try_from(x.len()) else {
+            // Too long for `STRING_EXT`, so sent as a list of small integers.
+            self.writer.write_u8(LIST_EXT)?;
+            self.writer.write_u32(aux::element_count(x.len())?)?;
+            for &b in x {
+                self.writer.write_u8(SMALL_INTEGER_EXT)?;
+                self.writer.write_u8(b)?;
+            }
+            return self.encode_nil();
+        };
         self.writer.write_u8(STRING_EXT)?;
-        self.writer.write_u16(x.len() as u16)?;
+        self.writer.write_u16(len)?;
         self.writer.write_all(x)?;
 
         Ok(())
     }
     fn encode_binary(&mut self, x: &Binary) -> EncodeResult {
         self.writer.write_u8(BINARY_EXT)?;
-        self.writer.write_u32(x.bytes.len() as u32)?;
+        self.writer.write_u32(aux::binary_size(x.bytes.len())?)?;
         self.writer.write_all(&x.bytes)?;
         Ok(())
     }
     fn encode_bit_binary(&mut self, x: &BitBinary) -> EncodeResult {
+        // The bit count of an empty bitstring must be zero.
+        let tail_bits_size = if x.bytes.is_empty() {
+            0
+        } else if (1..=8).contains(&x.tail_bits_size) {
+            x.tail_bits_size
+        } else {
+            return Err(EncodeError::InvalidBitCount {
+                size: x.bytes.len(),
+                tail_bits_size: x.tail_bits_size,
+            });
+        };
+        if !self.options.has_flags(DistributionFlags::BIT_BINARIES) {
+            // Sent as `{Binary, BitsInLastByte}`, the last byte being padded with zeros.
+            let mut bytes = x.bytes.clone();
+            if let Some(last) = bytes.last_mut() {
+                *last <<= 8 - tail_bits_size;
+            }
+            self.writer.write_u8(SMALL_TUPLE_EXT)?;
+            self.writer.write_u8(2)?;
+            self.encode_binary(&Binary::from(bytes))?;
+            return self.encode_fix_integer(&FixInteger::from(i32::from(tail_bits_size)));
+        }
         self.writer.write_u8(BIT_BINARY_EXT)?;
-        self.writer.write_u32(x.bytes.len() as u32)?;
-        self.writer.write_u8(x.tail_bits_size)?;
-        if !x.bytes.is_empty() {
-            self.writer.write_all(&x.bytes[0..x.bytes.len() - 1])?;
-            self.writer
-                .write_u8(x.bytes[x.bytes.len() - 1] << (8 - x.tail_bits_size))?;
+        self.writer.write_u32(aux::binary_size(x.bytes.len())?)?;
+        self.writer.write_u8(tail_bits_size)?;
+        if let Some((last, rest)) = x.bytes.split_last() {
+            self.writer.write_all(rest)?;
+            self.writer.write_u8(last << (8 - tail_bits_size))?;
         }
         Ok(())
     }
     fn encode_float(&mut self, x: &Float) -> EncodeResult {
+        if !self.options.has_flags(DistributionFlags::NEW_FLOATS) {
+            self.writer.write_u8(FLOAT_EXT)?;
+            self.writer.write_all(&aux::float_ext_bytes(x.value))?;
+            return Ok(());
+        }
         self.writer.write_u8(NEW_FLOAT_EXT)?;
         self.writer.write_f64(x.value)?;
         Ok(())
     }
     fn encode_atom(&mut self, x: &Atom) -> EncodeResult {
+        if let Some(&index) = self.atom_cache_refs.get(x) {
+            self.writer.write_u8(ATOM_CACHE_REF)?;
+            self.writer.write_u8(index)?;
+            return Ok(());
+        }
+        if x.name.chars().count() > MAX_ATOM_CHARACTERS {
+            return Err(EncodeError::TooLongAtomName(x.clone()));
+        }
+        if !self.options.has_flags(DistributionFlags::UTF8_ATOMS) {
+            return self.encode_latin1_atom(x);
+        }
+        // At most 255 characters take at most 1020 bytes.
         if let Ok(len) = u8::try_from(x.name.len()) {
             self.writer.write_u8(SMALL_ATOM_UTF8_EXT)?;
             self.writer.write_u8(len)?;
-        } else if let Ok(len) = u16::try_from(x.name.len()) {
-            self.writer.write_u8(ATOM_UTF8_EXT)?;
-            self.writer.write_u16(len)?;
         } else {
-            return Err(EncodeError::TooLongAtomName(x.clone()));
+            self.writer.write_u8(ATOM_UTF8_EXT)?;
+            self.writer.write_u16(x.name.len() as u16)?;
         }
         self.writer.write_all(x.name.as_bytes())?;
         Ok(())
     }
+    fn encode_latin1_atom(&mut self, x: &Atom) -> EncodeResult {
+        let bytes = x
+            .name
+            .chars()
+            .map(|c| u8::try_from(c).ok())
+            .collect::<Option<Vec<_>>>();
+        let Some(bytes) = bytes else {
+            return Err(EncodeError::UnsupportedByPeer {
+                term: Term::from(x.clone()),
+                required: DistributionFlags::UTF8_ATOMS,
+            });
+        };
+        match u8::try_from(bytes.len()) {
+            Ok(len) if self.options.has_flags(DistributionFlags::SMALL_ATOM_TAGS) => {
+                self.writer.write_u8(SMALL_ATOM_EXT)?;
+                self.writer.write_u8(len)?;
+            }
+            _ => {
+                self.writer.write_u8(ATOM_EXT)?;
+                self.writer.write_u16(bytes.len() as u16)?;
+            }
+        }
+        self.writer.write_all(&bytes)?;
+        Ok(())
+    }
     fn encode_fix_integer(&mut self, x: &FixInteger) -> EncodeResult {
         if 0 <= x.value && x.value <= i32::from(u8::MAX) {
             self.writer.write_u8(SMALL_INTEGER_EXT)?;
@@ -704,6 +2040,15 @@ impl<W: io::Write> Encoder<W> {
         Ok(())
     }
     fn encode_pid(&mut self, x: &Pid) -> EncodeResult {
+        if !self.options.has_flags(DistributionFlags::BIG_CREATION) {
+            let creation = self.small_creation(x.creation, || Term::from(x.clone()))?;
+            self.writer.write_u8(PID_EXT)?;
+            self.encode_atom(&x.node)?;
+            self.writer.write_u32(x.id)?;
+            self.writer.write_u32(x.serial)?;
+            self.writer.write_u8(creation)?;
+            return Ok(());
+        }
         self.writer.write_u8(NEW_PID_EXT)?;
         self.encode_atom(&x.node)?;
         self.writer.write_u32(x.id)?;
@@ -712,6 +2057,17 @@ impl<W: io::Write> Encoder<W> {
         Ok(())
     }
     fn encode_port(&mut self, x: &Port) -> EncodeResult {
+        if x.id > u64::from(u32::MAX) {
+            self.require(DistributionFlags::V4_NC, || Term::from(x.clone()))?;
+        }
+        if !self.options.has_flags(DistributionFlags::BIG_CREATION) {
+            let creation = self.small_creation(x.creation, || Term::from(x.clone()))?;
+            self.writer.write_u8(PORT_EXT)?;
+            self.encode_atom(&x.node)?;
+            self.writer.write_u32(x.id as u32)?;
+            self.writer.write_u8(creation)?;
+            return Ok(());
+        }
         if (x.id >> 32) & 0xFFFFFFFF == 0 {
             self.writer.write_u8(NEW_PORT_EXT)?;
             self.encode_atom(&x.node)?;
@@ -726,10 +2082,44 @@ impl<W: io::Write> Encoder<W> {
         Ok(())
     }
     fn encode_reference(&mut self, x: &Reference) -> EncodeResult {
-        self.writer.write_u8(NEWER_REFERENCE_EXT)?;
-        if x.id.len() > u16::MAX as usize {
+        if x.id.len() > MAX_REFERENCE_ID_WORDS {
             return Err(EncodeError::TooLargeReferenceId(x.clone()));
         }
+        if x.id.len() > 3 {
+            self.require(DistributionFlags::V4_NC, || Term::from(x.clone()))?;
+        }
+        if !self
+            .options
+            .has_flags(DistributionFlags::EXTENDED_REFERENCES)
+        {
+            let id = match x.id[..] {
+                [id] => id,
+                _ => {
+                    return Err(EncodeError::UnsupportedByPeer {
+                        term: Term::from(x.clone()),
+                        required: DistributionFlags::EXTENDED_REFERENCES,
+                    });
+                }
+            };
+            let creation = self.small_creation(x.creation, || Term::from(x.clone()))?;
+            self.writer.write_u8(REFERENCE_EXT)?;
+            self.encode_atom(&x.node)?;
+            self.writer.write_u32(id)?;
+            self.writer.write_u8(creation)?;
+            return Ok(());
+        }
+        if !self.options.has_flags(DistributionFlags::BIG_CREATION) {
+            let creation = self.small_creation(x.creation, || Term::from(x.clone()))?;
+            self.writer.write_u8(NEW_REFERENCE_EXT)?;
+            self.writer.write_u16(x.id.len() as u16)?;
+            self.encode_atom(&x.node)?;
+            self.writer.write_u8(creation)?;
+            for n in &x.id {
+                self.writer.write_u32(*n)?;
+            }
+            return Ok(());
+        }
+        self.writer.write_u8(NEWER_REFERENCE_EXT)?;
         self.writer.write_u16(x.id.len() as u16)?;
         self.encode_atom(&x.node)?;
         self.writer.write_u32(x.creation)?;
@@ -739,6 +2129,12 @@ impl<W: io::Write> Encoder<W> {
         Ok(())
     }
     fn encode_external_fun(&mut self, x: &ExternalFun) -> EncodeResult {
+        if !self.options.has_flags(DistributionFlags::EXPORT_PTR_TAG) {
+            self.writer.write_u8(SMALL_TUPLE_EXT)?;
+            self.writer.write_u8(2)?;
+            self.encode_atom(&x.module)?;
+            return self.encode_atom(&x.function);
+        }
         self.writer.write_u8(EXPORT_EXT)?;
         self.encode_atom(&x.module)?;
         self.encode_atom(&x.function)?;
@@ -755,7 +2151,8 @@ impl<W: io::Write> Encoder<W> {
                 uniq,
             } => {
                 self.writer.write_u8(FUN_EXT)?;
-                self.writer.write_u32(free_vars.len() as u32)?;
+                self.writer
+                    .write_u32(aux::element_count(free_vars.len())?)?;
                 self.encode_pid(pid)?;
                 self.encode_atom(module)?;
                 self.encode_fix_integer(&FixInteger::from(index))?;
@@ -778,11 +2175,12 @@ impl<W: io::Write> Encoder<W> {
 
                 let mut buf = Vec::new();
                 {
-                    let mut tmp = Encoder::new(&mut buf);
+                    let mut tmp = Encoder::with_options(&mut buf, self.options.clone());
+                    tmp.atom_cache_refs = self.atom_cache_refs.clone();
                     tmp.writer.write_u8(arity)?;
                     tmp.writer.write_all(uniq)?;
                     tmp.writer.write_u32(index)?;
-                    tmp.writer.write_u32(free_vars.len() as u32)?;
+                    tmp.writer.write_u32(aux::element_count(free_vars.len())?)?;
                     tmp.encode_atom(module)?;
                     tmp.encode_fix_integer(&FixInteger::from(old_index))?;
                     tmp.encode_fix_integer(&FixInteger::from(old_uniq))?;
@@ -791,7 +2189,9 @@ impl<W: io::Write> Encoder<W> {
                         tmp.encode_term(v)?;
                     }
                 }
-                self.writer.write_u32(4 + buf.len() as u32)?;
+                let size = 4 + buf.len();
+                let size = u32::try_from(size).map_err(|_| EncodeError::TooLargeFun { size })?;
+                self.writer.write_u32(size)?;
                 self.writer.write_all(&buf)?;
             }
         }
@@ -800,6 +2200,7 @@ impl<W: io::Write> Encoder<W> {
 }
 
 trait ReadExt {
+    fn read_u8_or_eof(&mut self) -> io::Result<Option<u8>>;
     fn read_u8(&mut self) -> io::Result<u8>;
     fn read_u16(&mut self) -> io::Result<u16>;
     fn read_i32(&mut self) -> io::Result<i32>;
@@ -809,6 +2210,18 @@ trait ReadExt {
 }
 
 impl<R: io::Read> ReadExt for R {
+    fn read_u8_or_eof(&mut self) -> io::Result<Option<u8>> {
+        let mut buf = [0; 1];
+        loop {
+            match self.read(&mut buf) {
+                Ok(0) => return Ok(None),
+                Ok(_) => return Ok(Some(buf[0])),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
     fn read_u8(&mut self) -> io::Result<u8> {
         let mut buf = [0; 1];
         self.read_exact(&mut buf)?;
@@ -846,6 +2259,13 @@ impl<R: io::Read> ReadExt for R {
     }
 }
 
+/// Returns the number of bytes [`Encoder::encode`] would write for `term`.
+pub(crate) fn encoded_len(term: &Term, options: EncodeOptions) -> Result<usize, EncodeError> {
+    let mut counter = aux::ByteCounter::default();
+    Encoder::with_options(&mut counter, options).encode(term)?;
+    Ok(counter.0)
+}
+
 trait WriteExt {
     fn write_u8(&mut self, value: u8) -> io::Result<()>;
     fn write_u16(&mut self, value: u16) -> io::Result<()>;
@@ -885,28 +2305,58 @@ mod aux {
     use num_bigint::Sign;
     use std::io;
     use std::ops::Range;
-    use std::str;
 
+    /// Writer which discards the data but counts its length.
+    #[derive(Default)]
+    pub struct ByteCounter(pub usize);
+    impl io::Write for ByteCounter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    pub fn check_limit(
+        limit: super::DecodeLimit,
+        max: Option<usize>,
+        value: usize,
+    ) -> Result<(), super::DecodeError> {
+        match max {
+            Some(max) if value > max => {
+                Err(super::DecodeErrorKind::LimitExceeded { limit, max }.into())
+            }
+            _ => Ok(()),
+        }
+    }
     pub fn term_into_atom(t: crate::Term) -> Result<crate::Atom, super::DecodeError> {
-        t.try_into()
-            .map_err(|t| super::DecodeError::UnexpectedType {
+        t.try_into().map_err(|t| {
+            super::DecodeErrorKind::UnexpectedType {
                 value: t,
                 expected: "Atom".to_string(),
-            })
+            }
+            .into()
+        })
     }
     pub fn term_into_pid(t: crate::Term) -> Result<crate::Pid, super::DecodeError> {
-        t.try_into()
-            .map_err(|t| super::DecodeError::UnexpectedType {
+        t.try_into().map_err(|t| {
+            super::DecodeErrorKind::UnexpectedType {
                 value: t,
                 expected: "Pid".to_string(),
-            })
+            }
+            .into()
+        })
     }
     pub fn term_into_fix_integer(t: crate::Term) -> Result<crate::FixInteger, super::DecodeError> {
-        t.try_into()
-            .map_err(|t| super::DecodeError::UnexpectedType {
+        t.try_into().map_err(|t| {
+            super::DecodeErrorKind::UnexpectedType {
                 value: t,
                 expected: "FixInteger".to_string(),
-            })
+            }
+            .into()
+        })
     }
     pub fn term_into_ranged_integer(
         t: crate::Term,
@@ -917,30 +2367,125 @@ mod aux {
             if range.start <= n && n <= range.end {
                 Ok(n)
             } else {
-                Err(super::DecodeError::OutOfRange { value: n, range })
+                Err(super::DecodeErrorKind::OutOfRange { value: n, range }.into())
             }
         })
     }
-    pub fn invalid_data_error<T>(message: String) -> io::Result<T> {
-        Err(io::Error::new(io::ErrorKind::InvalidData, message))
-    }
-    pub fn other_error<T>(message: String) -> io::Result<T> {
-        Err(io::Error::other(message))
+    pub fn latin1_bytes_to_string(buf: &[u8]) -> String {
+        buf.iter().copied().map(char::from).collect()
     }
-    pub fn latin1_bytes_to_string(buf: &[u8]) -> io::Result<String> {
-        // FIXME: Supports Latin1 characters
-        str::from_utf8(buf)
-            .or_else(|e| other_error(e.to_string()))
-            .map(ToString::to_string)
+    pub fn utf8_atom_name(buf: &[u8]) -> Result<&str, super::DecodeError> {
+        std::str::from_utf8(buf)
+            .map_err(|error| super::DecodeErrorKind::InvalidUtf8Atom { error }.into())
     }
-    pub fn byte_to_sign(b: u8) -> io::Result<Sign> {
+    pub fn byte_to_sign(b: u8) -> Result<Sign, super::DecodeError> {
         match b {
             0 => Ok(Sign::Plus),
             1 => Ok(Sign::Minus),
-            _ => invalid_data_error(format!("A sign value must be 0 or 1: value={}", b)),
+            _ => Err(super::DecodeErrorKind::InvalidSign { sign: b }.into()),
         }
     }
+    pub fn check_bit_count(size: usize, tail_bits_size: u8) -> Result<(), super::DecodeError> {
+        if (size == 0) == (tail_bits_size == 0) && tail_bits_size <= 8 {
+            Ok(())
+        } else {
+            Err(super::DecodeErrorKind::InvalidBitCount {
+                size,
+                tail_bits_size,
+            }
+            .into())
+        }
+    }
+    pub fn element_count(len: usize) -> Result<u32, super::EncodeError> {
+        u32::try_from(len).map_err(|_| super::EncodeError::TooManyElements { len })
+    }
+    pub fn binary_size(len: usize) -> Result<u32, super::EncodeError> {
+        u32::try_from(len).map_err(|_| super::EncodeError::TooLargeBinary { len })
+    }
     pub fn sign_to_byte(sign: Sign) -> u8 {
         if sign == Sign::Minus { 1 } else { 0 }
     }
+    pub fn atom_cache_index(atom: &crate::Atom) -> u16 {
+        // FNV-1a
+        let hash = atom.name.bytes().fold(0x811c9dc5u32, |hash, b| {
+            (hash ^ u32::from(b)).wrapping_mul(0x01000193)
+        });
+        (hash % super::AtomCache::SIZE as u32) as u16
+    }
+    pub fn collect_atoms<'a>(term: &'a crate::Term, atoms: &mut Vec<&'a crate::Atom>) {
+        use crate::{InternalFun, Term};
+        match term {
+            Term::Atom(x) => atoms.push(x),
+            Term::Pid(x) => atoms.push(&x.node),
+            Term::Port(x) => atoms.push(&x.node),
+            Term::Reference(x) => atoms.push(&x.node),
+            Term::ExternalFun(x) => {
+                atoms.push(&x.module);
+                atoms.push(&x.function);
+            }
+            Term::InternalFun(x) => {
+                let (module, pid, free_vars) = match &**x {
+                    InternalFun::Old {
+                        module,
+                        pid,
+                        free_vars,
+                        ..
+                    }
+                    | InternalFun::New {
+                        module,
+                        pid,
+                        free_vars,
+                        ..
+                    } => (module, pid, free_vars),
+                };
+                atoms.push(module);
+                atoms.push(&pid.node);
+                for v in free_vars {
+                    collect_atoms(v, atoms);
+                }
+            }
+            Term::List(x) => x.elements.iter().for_each(|e| collect_atoms(e, atoms)),
+            Term::ImproperList(x) => {
+                x.elements.iter().for_each(|e| collect_atoms(e, atoms));
+                collect_atoms(&x.last, atoms);
+            }
+            Term::Tuple(x) => x.elements.iter().for_each(|e| collect_atoms(e, atoms)),
+            Term::Map(x) => {
+                for (k, v) in &x.map {
+                    collect_atoms(k, atoms);
+                    collect_atoms(v, atoms);
+                }
+            }
+            Term::FixInteger(_)
+            | Term::BigInteger(_)
+            | Term::Float(_)
+            | Term::Binary(_)
+            | Term::BitBinary(_)
+            | Term::ByteList(_) => {}
+        }
+    }
+    /// Formats a float for `FLOAT_EXT` like `printf("%.20e")`, padded with zeros to 31 bytes.
+    pub fn float_ext_bytes(value: f64) -> [u8; 31] {
+        let s = format!("{value:.20e}");
+        let (mantissa, exponent) = s.split_once('e').expect("unreachable");
+        let exponent: i32 = exponent.parse().expect("unreachable");
+        let sign = if exponent < 0 { '-' } else { '+' };
+        let s = format!("{mantissa}e{sign}{:02}", exponent.abs());
+        let mut buf = [0; 31];
+        buf[..s.len()].copy_from_slice(s.as_bytes());
+        buf
+    }
+    pub fn zlib_compress(data: &[u8], level: u8) -> io::Result<Vec<u8>> {
+        let options = if level == 0 {
+            noflate::deflate::EncodeOptions::new().stored()
+        } else {
+            noflate::deflate::EncodeOptions::new()
+        };
+        let mut encoder = noflate::zlib::Encoder::with_options(options.buffer_all_input());
+        encoder
+            .feed(data)
+            .and_then(|()| encoder.finish())
+            .map_err(io::Error::other)?;
+        Ok(encoder.output().to_vec())
+    }
 }
@@ -0,0 +1,46 @@
+use super::*;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Upper bound of the bytes read at once, as hinted by the incremental decoder.
+const MAX_READ_SIZE: usize = 8 * 1024;
+
+/// Reads exactly one term from `reader`.
+///
+/// No more bytes than [`DecodeStatus::NeedMore`] asks for are read,
+/// so the bytes following the term are left in the reader.
+pub(crate) async fn decode<R>(mut reader: R, options: DecodeOptions) -> DecodeResult
+where
+    R: AsyncRead + Unpin,
+{
+    let mut decoder = IncrementalDecoder::with_options(options);
+    let mut buf = Vec::new();
+
+    // The version and the tag.
+    let mut need = 2;
+    loop {
+        buf.resize(need.min(MAX_READ_SIZE), 0);
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "stream ended in the middle of a term",
+            )
+            .into());
+        }
+        match decoder.feed(&buf[..n])? {
+            DecodeStatus::NeedMore(n) => need = n.max(1),
+            DecodeStatus::Complete(term, _) => return Ok(term),
+        }
+    }
+}
+
+/// Encodes `term` into a buffer, then writes it to `writer` without flushing.
+pub(crate) async fn encode<W>(term: &Term, mut writer: W, options: EncodeOptions) -> EncodeResult
+where
+    W: AsyncWrite + Unpin,
+{
+    let mut buf = Vec::new();
+    Encoder::with_options(&mut buf, options).encode(term)?;
+    writer.write_all(&buf).await?;
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use std::fmt;
+use std::ops::{BitAnd, BitOr, BitOrAssign};
+
+/// Capability flags (`DFLAG_*`) advertised by a peer node in the distribution handshake.
+///
+/// [`EncodeOptions::distribution_flags`](super::EncodeOptions::distribution_flags)
+/// makes the encoder emit only the tags the peer understands, falling back to older tags as ERTS does.
+///
+/// See: https://www.erlang.org/doc/apps/erts/erl_dist_protocol.html#dflags
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DistributionFlags(u64);
+impl DistributionFlags {
+    /// Extended references (`NEW_REFERENCE_EXT` instead of `REFERENCE_EXT`).
+    pub const EXTENDED_REFERENCES: Self = Self(0x04);
+
+    /// `EXPORT_EXT` for external funs.
+    pub const EXPORT_PTR_TAG: Self = Self(0x200);
+
+    /// `BIT_BINARY_EXT` for bitstrings.
+    pub const BIT_BINARIES: Self = Self(0x400);
+
+    /// `NEW_FLOAT_EXT` for floats.
+    pub const NEW_FLOATS: Self = Self(0x800);
+
+    /// `SMALL_ATOM_EXT` for short latin-1 atoms.
+    pub const SMALL_ATOM_TAGS: Self = Self(0x4000);
+
+    /// `ATOM_UTF8_EXT` and `SMALL_ATOM_UTF8_EXT` for atoms.
+    pub const UTF8_ATOMS: Self = Self(0x10000);
+
+    /// `MAP_EXT` for maps.
+    pub const MAP_TAG: Self = Self(0x20000);
+
+    /// 32-bit creations (`NEW_PID_EXT`, `NEW_PORT_EXT` and `NEWER_REFERENCE_EXT`).
+    pub const BIG_CREATION: Self = Self(0x40000);
+
+    /// 64-bit port IDs (`V4_PORT_EXT`) and references with up to 5 ID words.
+    pub const V4_NC: Self = Self(1 << 34);
+
+    const NAMES: [(Self, &'static str); 9] = [
+        (Self::EXTENDED_REFERENCES, "EXTENDED_REFERENCES"),
+        (Self::EXPORT_PTR_TAG, "EXPORT_PTR_TAG"),
+        (Self::BIT_BINARIES, "BIT_BINARIES"),
+        (Self::NEW_FLOATS, "NEW_FLOATS"),
+        (Self::SMALL_ATOM_TAGS, "SMALL_ATOM_TAGS"),
+        (Self::UTF8_ATOMS, "UTF8_ATOMS"),
+        (Self::MAP_TAG, "MAP_TAG"),
+        (Self::BIG_CREATION, "BIG_CREATION"),
+        (Self::V4_NC, "V4_NC"),
+    ];
+
+    /// Makes flags from the raw bits exchanged in the handshake.
+    ///
+    /// Bits of flags not defined by this type are kept, but have no effect on encoding.
+    pub const fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    pub const fn bits(self) -> u64 {
+        self.0
+    }
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// All the flags defined by this type, i.e., the capabilities of a modern node.
+    pub const fn all() -> Self {
+        let mut bits = 0;
+        let mut i = 0;
+        while i < Self::NAMES.len() {
+            bits |= Self::NAMES[i].0.0;
+            i += 1;
+        }
+        Self(bits)
+    }
+
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Flags in `self` but not in `other`.
+    pub const fn difference(self, other: Self) -> Self {
+        Self(self.0 & !other.0)
+    }
+}
+impl BitOr for DistributionFlags {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+impl BitOrAssign for DistributionFlags {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+impl BitAnd for DistributionFlags {
+    type Output = Self;
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+impl fmt::Debug for DistributionFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "DistributionFlags({self})")
+    }
+}
+impl fmt::Display for DistributionFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut rest = self.0;
+        let mut first = true;
+        for (flag, name) in Self::NAMES {
+            if self.contains(flag) {
+                if !first {
+                    write!(f, " | ")?;
+                }
+                write!(f, "{name}")?;
+                rest &= !flag.0;
+                first = false;
+            }
+        }
+        if rest != 0 || first {
+            if !first {
+                write!(f, " | ")?;
+            }
+            write!(f, "{rest:#x}")?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+use super::*;
+use bytes::{Buf, BufMut, BytesMut};
+
+/// [`tokio_util::codec`] implementation for terms framed by a big-endian length header,
+/// as with the `{packet, N}` option of Erlang ports and sockets.
+///
+/// Each frame must contain exactly one term (the output of `term_to_binary/1`).
+///
+/// # Examples
+///
+/// ```
+/// use bytes::BytesMut;
+/// use eetf::{Atom, EtfFramedCodec, Term};
+/// use tokio_util::codec::{Decoder, Encoder};
+///
+/// let mut codec = EtfFramedCodec::new(2).max_frame(1024);
+/// let mut buf = BytesMut::new();
+/// codec.encode(Term::from(Atom::from("foo")), &mut buf).unwrap();
+/// assert_eq!(&buf[..], [0, 6, 131, 119, 3, 102, 111, 111]);
+/// assert_eq!(
+///     codec.decode(&mut buf).unwrap(),
+///     Some(Term::from(Atom::from("foo")))
+/// );
+/// ```
+#[derive(Debug, Clone)]
+pub struct EtfFramedCodec {
+    packet: usize,
+    max_frame: usize,
+    decode_options: DecodeOptions,
+    encode_options: EncodeOptions,
+}
+impl EtfFramedCodec {
+    /// Makes a codec for frames with a `packet`-byte length header.
+    ///
+    /// The maximum frame size is initially the largest one the header can represent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `packet` is not `1`, `2` or `4`.
+    pub fn new(packet: u8) -> Self {
+        assert!(
+            matches!(packet, 1 | 2 | 4),
+            "packet must be 1, 2 or 4, but is {packet}"
+        );
+        let packet = usize::from(packet);
+        EtfFramedCodec {
+            packet,
+            max_frame: Self::header_max(packet),
+            decode_options: DecodeOptions::new(),
+            encode_options: EncodeOptions::new(),
+        }
+    }
+
+    /// Sets the maximum size in bytes of a frame, excluding its length header.
+    ///
+    /// Larger frames result in [`FramedCodecError::FrameTooLarge`] in both directions.
+    /// On decoding, the error is returned as soon as the header is read, before buffering the frame.
+    /// The size is capped by the largest one the header can represent.
+    #[must_use]
+    pub fn max_frame(mut self, n: usize) -> Self {
+        self.max_frame = n.min(Self::header_max(self.packet));
+        self
+    }
+
+    /// Sets the options used to decode the terms.
+    #[must_use]
+    pub fn decode_options(mut self, options: DecodeOptions) -> Self {
+        self.decode_options = options;
+        self
+    }
+
+    /// Sets the options used to encode the terms.
+    #[must_use]
+    pub fn encode_options(mut self, options: EncodeOptions) -> Self {
+        self.encode_options = options;
+        self
+    }
+
+    fn header_max(packet: usize) -> usize {
+        usize::try_from(u64::MAX >> (64 - 8 * packet)).unwrap_or(usize::MAX)
+    }
+}
+
+impl tokio_util::codec::Decoder for EtfFramedCodec {
+    type Item = Term;
+    type Error = FramedCodecError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Term>, FramedCodecError> {
+        let Some(header) = src.get(..self.packet) else {
+            return Ok(None);
+        };
+        let size = header
+            .iter()
+            .fold(0u64, |acc, &b| (acc << 8) | u64::from(b));
+        let size = usize::try_from(size).unwrap_or(usize::MAX);
+        if size > self.max_frame {
+            return Err(FramedCodecError::FrameTooLarge {
+                size,
+                max: self.max_frame,
+            });
+        }
+        if src.len() - self.packet < size {
+            src.reserve(self.packet + size - src.len());
+            return Ok(None);
+        }
+        src.advance(self.packet);
+        let frame = src.split_to(size);
+        Decoder::with_options(io::Cursor::new(&frame[..]), self.decode_options.clone())
+            .decode_exact()
+            .map(Some)
+            .map_err(FramedCodecError::Decode)
+    }
+}
+
+impl tokio_util::codec::Encoder<&Term> for EtfFramedCodec {
+    type Error = FramedCodecError;
+
+    fn encode(&mut self, term: &Term, dst: &mut BytesMut) -> Result<(), FramedCodecError> {
+        let start = dst.len();
+        dst.put_bytes(0, self.packet);
+        let result = Encoder::with_options(dst.writer(), self.encode_options.clone()).encode(term);
+        if let Err(e) = result {
+            dst.truncate(start);
+            return Err(FramedCodecError::Encode(e));
+        }
+        let size = dst.len() - start - self.packet;
+        if size > self.max_frame {
+            dst.truncate(start);
+            return Err(FramedCodecError::FrameTooLarge {
+                size,
+                max: self.max_frame,
+            });
+        }
+        let header = (size as u64).to_be_bytes();
+        dst[start..start + self.packet].copy_from_slice(&header[8 - self.packet..]);
+        Ok(())
+    }
+}
+
+impl tokio_util::codec::Encoder<Term> for EtfFramedCodec {
+    type Error = FramedCodecError;
+
+    fn encode(&mut self, term: Term, dst: &mut BytesMut) -> Result<(), FramedCodecError> {
+        self.encode(&term, dst)
+    }
+}
+
+/// Errors of [`EtfFramedCodec`].
+#[derive(Debug)]
+pub enum FramedCodecError {
+    /// I/O error of the underlying stream.
+    Io(io::Error),
+
+    /// The frame exceeds the maximum size.
+    FrameTooLarge { size: usize, max: usize },
+
+    /// The frame is not a valid term.
+    Decode(DecodeError),
+
+    /// The term cannot be encoded.
+    Encode(EncodeError),
+}
+
+impl std::fmt::Display for FramedCodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "I/O error: {error}"),
+            Self::FrameTooLarge { size, max } => {
+                write!(f, "frame of {size} bytes exceeds the limit of {max}")
+            }
+            Self::Decode(error) => write!(f, "cannot decode a frame: {error}"),
+            Self::Encode(error) => write!(f, "cannot encode a term: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for FramedCodecError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::FrameTooLarge { .. } => None,
+            Self::Decode(error) => Some(error),
+            Self::Encode(error) => Some(error),
+        }
+    }
+}
+
+impl From<io::Error> for FramedCodecError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
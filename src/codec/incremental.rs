@@ -0,0 +1,528 @@
+use super::*;
+use noflate::zlib::Adler32;
+
+/// Result of [`IncrementalDecoder::feed`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeStatus {
+    /// All of the input has been consumed and more bytes are needed.
+    ///
+    /// The value is the minimum number of additional bytes required to make progress
+    /// (the actual number may be larger).
+    NeedMore(usize),
+
+    /// A term has been decoded.
+    ///
+    /// The value is the number of bytes consumed from the input given to the last `feed` call.
+    /// The remaining bytes belong to the next term.
+    Complete(Term, usize),
+}
+
+/// Push-based decoder that accepts input in arbitrary chunks.
+///
+/// This is useful for non-blocking I/O where [`Decoder`] cannot be used
+/// because it requires a blocking reader.
+/// Bytes fed so far are kept (and, for compressed terms, inflated) between calls,
+/// so they are never parsed again.
+///
+/// # Examples
+///
+/// ```
+/// use eetf::{Atom, DecodeStatus, IncrementalDecoder, Term};
+///
+/// let mut decoder = IncrementalDecoder::new();
+/// assert_eq!(decoder.feed(&[131, 119, 3]).unwrap(), DecodeStatus::NeedMore(3));
+/// assert_eq!(
+///     decoder.feed(&[102, 111, 111, 131]).unwrap(),
+///     DecodeStatus::Complete(Term::from(Atom::from("foo")), 3)
+/// );
+/// ```
+#[derive(Debug)]
+pub struct IncrementalDecoder {
+    options: DecodeOptions,
+    state: State,
+    buf: Vec<u8>,
+    carry: Vec<u8>,
+    consumed: usize,
+}
+impl IncrementalDecoder {
+    pub fn new() -> Self {
+        Self::with_options(DecodeOptions::new())
+    }
+
+    pub fn with_options(options: DecodeOptions) -> Self {
+        IncrementalDecoder {
+            options,
+            state: State::Version,
+            buf: Vec::new(),
+            carry: Vec::new(),
+            consumed: 0,
+        }
+    }
+
+    /// Feeds the next chunk of the input.
+    ///
+    /// After [`DecodeStatus::Complete`] is returned, the decoder is ready for the next term.
+    /// After an error is returned, [`IncrementalDecoder::reset`] must be called
+    /// before decoding another term.
+    /// The [offset](DecodeError::offset) of the error is relative to the start of the term,
+    /// not to the input of this call.
+    pub fn feed(&mut self, input: &[u8]) -> Result<DecodeStatus, DecodeError> {
+        if !self.carry.is_empty() {
+            // Bytes that were fed before but turned out to be beyond the end of the previous term.
+            let carry = std::mem::take(&mut self.carry);
+            let (term, consumed) = self.process(&carry)?;
+            if let Some(term) = term {
+                self.carry.extend_from_slice(&carry[consumed..]);
+                return Ok(DecodeStatus::Complete(term, 0));
+            }
+        }
+        match self.process(input)? {
+            (Some(term), consumed) => Ok(DecodeStatus::Complete(term, consumed)),
+            (None, _) => Ok(DecodeStatus::NeedMore(self.state.hint())),
+        }
+    }
+
+    /// Discards the partially decoded term.
+    pub fn reset(&mut self) {
+        self.state = State::Version;
+        self.buf.clear();
+        self.carry.clear();
+        self.consumed = 0;
+    }
+
+    fn process(&mut self, input: &[u8]) -> Result<(Option<Term>, usize), DecodeError> {
+        let mut offset = 0;
+        match self.process_input(input, &mut offset) {
+            Ok((Some(term), consumed)) => {
+                self.consumed = 0;
+                Ok((Some(term), consumed))
+            }
+            Ok((None, consumed)) => {
+                self.consumed += consumed;
+                Ok((None, consumed))
+            }
+            Err(e) => Err(e.at(self.consumed + offset)),
+        }
+    }
+
+    fn process_input(
+        &mut self,
+        input: &[u8],
+        offset: &mut usize,
+    ) -> Result<(Option<Term>, usize), DecodeError> {
+        while *offset < input.len() {
+            let rest = &input[*offset..];
+            match &mut self.state {
+                State::Version => {
+                    if rest[0] != VERSION {
+                        return Err(DecodeErrorKind::UnsupportedVersion { version: rest[0] }.into());
+                    }
+                    self.buf.push(rest[0]);
+                    self.state = State::Tag;
+                    *offset += 1;
+                }
+                State::Tag => {
+                    self.state = match rest[0] {
+                        COMPRESSED_TERM => {
+                            // Only the decompressed body is buffered.
+                            self.buf.clear();
+                            State::CompressedSize(Header::new(4))
+                        }
+                        DISTRIBUTION_HEADER => {
+                            return Err(DecodeErrorKind::UnexpectedDistributionHeader.into());
+                        }
+                        _ => {
+                            self.buf.push(rest[0]);
+                            let mut scanner = Scanner::new();
+                            scanner.start_term(rest[0])?;
+                            State::Plain(scanner)
+                        }
+                    };
+                    *offset += 1;
+                }
+                State::Plain(scanner) => {
+                    let n = scanner.scan(rest, self.buf.len())?;
+                    self.buf.extend_from_slice(&rest[..n]);
+                    *offset += n;
+                    aux::check_limit(
+                        DecodeLimit::AllocatedBytes,
+                        self.options.max_allocated_bytes,
+                        self.buf.len(),
+                    )?;
+                }
+                State::CompressedSize(header) => {
+                    *offset += header.fill(rest);
+                    if let Some(size) = header.value() {
+                        let size = size as usize;
+                        aux::check_limit(
+                            DecodeLimit::AllocatedBytes,
+                            self.options.max_allocated_bytes,
+                            size,
+                        )?;
+                        self.state = State::Compressed(Box::new(Inflate::new(size)));
+                    }
+                }
+                State::Compressed(inflate) => {
+                    let excess = inflate.feed(rest, &mut self.buf)?;
+                    if excess.len() <= rest.len() {
+                        *offset = input.len() - excess.len();
+                    } else {
+                        // The term ended within bytes fed by earlier calls,
+                        // so none of the current input has been consumed.
+                        self.carry = excess[..excess.len() - rest.len()].to_vec();
+                    }
+                }
+            }
+
+            if self.state.is_complete() {
+                let term = if let State::Compressed(_) = self.state {
+                    super::decode_inflated_term(&self.buf, &self.options)
+                } else {
+                    Decoder::with_options(io::Cursor::new(&self.buf), self.options.clone()).decode()
+                };
+                self.state = State::Version;
+                self.buf.clear();
+                return Ok((Some(term?), *offset));
+            }
+        }
+        Ok((None, input.len()))
+    }
+}
+impl Default for IncrementalDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug)]
+enum State {
+    Version,
+    Tag,
+    Plain(Scanner),
+    CompressedSize(Header),
+    Compressed(Box<Inflate>),
+}
+impl State {
+    fn is_complete(&self) -> bool {
+        match self {
+            State::Plain(scanner) => scanner.is_complete(),
+            State::Compressed(inflate) => inflate.is_finished(),
+            _ => false,
+        }
+    }
+
+    fn hint(&self) -> usize {
+        match self {
+            State::Version | State::Tag => 1,
+            State::Plain(scanner) => scanner.hint(),
+            State::CompressedSize(header) => header.hint(),
+            State::Compressed(inflate) => inflate.hint(),
+        }
+    }
+}
+
+/// Big-endian unsigned integer that may be split across chunks.
+#[derive(Debug, Clone, Copy)]
+struct Header {
+    bytes: [u8; 4],
+    size: u8,
+    filled: u8,
+}
+impl Header {
+    fn new(size: u8) -> Self {
+        Header {
+            bytes: [0; 4],
+            size,
+            filled: 0,
+        }
+    }
+
+    fn fill(&mut self, input: &[u8]) -> usize {
+        let n = usize::from(self.size - self.filled).min(input.len());
+        let start = usize::from(self.filled);
+        self.bytes[start..start + n].copy_from_slice(&input[..n]);
+        self.filled += n as u8;
+        n
+    }
+
+    fn value(&self) -> Option<u64> {
+        (self.filled == self.size).then(|| {
+            self.bytes[..usize::from(self.size)]
+                .iter()
+                .fold(0, |acc, &b| (acc << 8) | u64::from(b))
+        })
+    }
+
+    fn hint(&self) -> usize {
+        usize::from(self.size - self.filled)
+    }
+}
+
+/// Finds the end of an uncompressed term without building it.
+#[derive(Debug)]
+struct Scanner {
+    stack: Vec<Task>,
+}
+#[derive(Debug)]
+enum Task {
+    Terms(u64),
+    Skip(u64),
+    Header(Header, Then),
+}
+#[derive(Debug, Clone, Copy)]
+enum Then {
+    /// Skips `n + extra` bytes.
+    Skip { extra: u64 },
+    /// Skips `n - 4` bytes, where `n` includes the size field itself.
+    SkipSized,
+    /// Reads `n * mult + extra` terms.
+    Terms { mult: u64, extra: u64 },
+    /// Reads a node atom, then skips the creation and `n` IDs.
+    ReferenceIds { creation: u64 },
+}
+impl Scanner {
+    fn new() -> Self {
+        Scanner { stack: Vec::new() }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    fn hint(&self) -> usize {
+        match self.stack.last() {
+            Some(Task::Skip(n)) => usize::try_from(*n).unwrap_or(usize::MAX),
+            Some(Task::Header(header, _)) => header.hint(),
+            _ => 1,
+        }
+    }
+
+    fn start_term(&mut self, tag: u8) -> Result<(), DecodeError> {
+        let header = |size, then| Task::Header(Header::new(size), then);
+        let skip = Then::Skip { extra: 0 };
+        match tag {
+            NIL_EXT => {}
+            SMALL_INTEGER_EXT | ATOM_CACHE_REF => self.stack.push(Task::Skip(1)),
+            INTEGER_EXT => self.stack.push(Task::Skip(4)),
+            NEW_FLOAT_EXT => self.stack.push(Task::Skip(8)),
+            FLOAT_EXT => self.stack.push(Task::Skip(31)),
+            ATOM_EXT | ATOM_UTF8_EXT | STRING_EXT => self.stack.push(header(2, skip)),
+            SMALL_ATOM_EXT | SMALL_ATOM_UTF8_EXT => self.stack.push(header(1, skip)),
+            BINARY_EXT => self.stack.push(header(4, skip)),
+            BIT_BINARY_EXT | LARGE_BIG_EXT => self.stack.push(header(4, Then::Skip { extra: 1 })),
+            SMALL_BIG_EXT => self.stack.push(header(1, Then::Skip { extra: 1 })),
+            PID_EXT => self.push_node_and_skip(9),
+            NEW_PID_EXT | V4_PORT_EXT => self.push_node_and_skip(12),
+            PORT_EXT | REFERENCE_EXT => self.push_node_and_skip(5),
+            NEW_PORT_EXT => self.push_node_and_skip(8),
+            NEW_REFERENCE_EXT => {
+                self.stack
+                    .push(header(2, Then::ReferenceIds { creation: 1 }));
+            }
+            NEWER_REFERENCE_EXT => {
+                self.stack
+                    .push(header(2, Then::ReferenceIds { creation: 4 }));
+            }
+            SMALL_TUPLE_EXT => self
+                .stack
+                .push(header(1, Then::Terms { mult: 1, extra: 0 })),
+            LARGE_TUPLE_EXT => self
+                .stack
+                .push(header(4, Then::Terms { mult: 1, extra: 0 })),
+            LIST_EXT => self
+                .stack
+                .push(header(4, Then::Terms { mult: 1, extra: 1 })),
+            MAP_EXT => self
+                .stack
+                .push(header(4, Then::Terms { mult: 2, extra: 0 })),
+            FUN_EXT => self
+                .stack
+                .push(header(4, Then::Terms { mult: 1, extra: 4 })),
+            NEW_FUN_EXT => self.stack.push(header(4, Then::SkipSized)),
+            EXPORT_EXT => self.stack.push(Task::Terms(3)),
+            _ => return Err(DecodeErrorKind::UnknownTag { tag }.into()),
+        }
+        Ok(())
+    }
+
+    fn push_node_and_skip(&mut self, n: u64) {
+        self.stack.push(Task::Skip(n));
+        self.stack.push(Task::Terms(1));
+    }
+
+    /// Scans the input until the end of the term and returns the number of consumed bytes.
+    ///
+    /// `base` is the offset of the input from the start of the term.
+    fn scan(&mut self, input: &[u8], base: usize) -> Result<usize, DecodeError> {
+        let mut offset = 0;
+        while offset < input.len() {
+            let Some(task) = self.stack.last_mut() else {
+                break;
+            };
+            match task {
+                Task::Terms(n) => {
+                    *n -= 1;
+                    if *n == 0 {
+                        self.stack.pop();
+                    }
+                    self.start_term(input[offset])
+                        .map_err(|e| e.at(base + offset))?;
+                    offset += 1;
+                }
+                Task::Skip(n) => {
+                    let m = (*n).min((input.len() - offset) as u64);
+                    *n -= m;
+                    offset += m as usize;
+                    if *n == 0 {
+                        self.stack.pop();
+                    }
+                }
+                Task::Header(header, then) => {
+                    offset += header.fill(&input[offset..]);
+                    let Some(n) = header.value() else {
+                        continue;
+                    };
+                    let then = *then;
+                    self.stack.pop();
+                    match then {
+                        Then::Skip { extra } => self.push_skip(n + extra),
+                        Then::SkipSized => {
+                            let n = n.checked_sub(4).ok_or_else(|| {
+                                io::Error::new(io::ErrorKind::InvalidData, "too small fun size")
+                            })?;
+                            self.push_skip(n);
+                        }
+                        Then::Terms { mult, extra } => {
+                            let n = n * mult + extra;
+                            if n > 0 {
+                                self.stack.push(Task::Terms(n));
+                            }
+                        }
+                        Then::ReferenceIds { creation } => {
+                            self.push_node_and_skip(creation + n * 4);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(offset)
+    }
+
+    fn push_skip(&mut self, n: u64) {
+        if n > 0 {
+            self.stack.push(Task::Skip(n));
+        }
+    }
+}
+
+/// Zlib stream of a `COMPRESSED_TERM` that may be split across chunks.
+#[derive(Debug)]
+pub(super) struct Inflate {
+    uncompressed_size: usize,
+    zlib_header: Header,
+    deflate: noflate::deflate::Decoder,
+    adler: Adler32,
+    trailer: Header,
+}
+impl Inflate {
+    pub(super) fn new(uncompressed_size: usize) -> Self {
+        Inflate {
+            uncompressed_size,
+            zlib_header: Header::new(2),
+            deflate: noflate::deflate::Decoder::new(),
+            adler: Adler32::new(),
+            trailer: Header::new(4),
+        }
+    }
+
+    pub(super) fn is_finished(&self) -> bool {
+        self.trailer.value().is_some()
+    }
+
+    fn hint(&self) -> usize {
+        if self.zlib_header.value().is_none() {
+            self.zlib_header.hint()
+        } else if self.deflate.is_finished() {
+            self.trailer.hint()
+        } else {
+            1
+        }
+    }
+
+    /// Feeds the compressed bytes and returns the bytes that follow the zlib stream, if any.
+    pub(super) fn feed(
+        &mut self,
+        mut input: &[u8],
+        output: &mut Vec<u8>,
+    ) -> Result<Vec<u8>, DecodeError> {
+        let invalid_data = |e: noflate::Error| io::Error::new(io::ErrorKind::InvalidData, e);
+        if self.zlib_header.value().is_none() {
+            input = &input[self.zlib_header.fill(input)..];
+            let Some(header) = self.zlib_header.value() else {
+                return Ok(Vec::new());
+            };
+            if header % 31 != 0 || (header >> 8) & 0x0F != 8 || header & 0x20 != 0 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "unsupported or invalid zlib header",
+                )
+                .into());
+            }
+        }
+
+        let mut rest = Vec::new();
+        if !self.deflate.is_finished() {
+            self.deflate.feed(input).map_err(invalid_data)?;
+            input = &[];
+            loop {
+                let out = self.deflate.output();
+                if output.len() + out.len() > self.uncompressed_size {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "decompressed data exceeds the uncompressed size",
+                    )
+                    .into());
+                }
+                self.adler.update(out);
+                output.extend_from_slice(out);
+                let n = out.len();
+                self.deflate.advance(n);
+                if self.deflate.is_finished() {
+                    break;
+                }
+                let before = self.deflate.remaining_input().len();
+                self.deflate.feed(&[]).map_err(invalid_data)?;
+                if self.deflate.output().is_empty()
+                    && self.deflate.remaining_input().len() == before
+                    && !self.deflate.is_finished()
+                {
+                    break;
+                }
+            }
+            if !self.deflate.is_finished() {
+                return Ok(Vec::new());
+            }
+            if output.len() != self.uncompressed_size {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "decompressed data is shorter than the uncompressed size",
+                )
+                .into());
+            }
+            rest = self.deflate.remaining_input().to_vec();
+        }
+        rest.extend_from_slice(input);
+
+        let n = self.trailer.fill(&rest);
+        if let Some(checksum) = self.trailer.value()
+            && checksum != u64::from(self.adler.value())
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "adler-32 checksum mismatch in zlib trailer",
+            )
+            .into());
+        }
+        Ok(rest.split_off(n))
+    }
+}
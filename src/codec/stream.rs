@@ -0,0 +1,142 @@
+use super::*;
+
+const READ_BUF_SIZE: usize = 8 * 1024;
+
+/// Iterator over terms concatenated back-to-back in a byte stream,
+/// such as a file of `term_to_binary/1` outputs.
+///
+/// The iterator ends when the stream ends at a term boundary.
+/// If the stream ends in the middle of a term, an error of kind [`io::ErrorKind::UnexpectedEof`] is returned instead.
+/// After an error is returned, the iterator yields no more items.
+///
+/// The reader is read in chunks, so bytes after the last decoded term may already have been read from it.
+///
+/// # Examples
+///
+/// ```
+/// use eetf::{Atom, Term, TermReader, TermWriter};
+///
+/// let mut writer = TermWriter::new(Vec::new());
+/// writer.write_term(&Term::from(Atom::from("foo"))).unwrap();
+/// writer.write_term(&Term::from(Atom::from("bar"))).unwrap();
+/// let bytes = writer.into_inner();
+///
+/// let terms = TermReader::new(&bytes[..]).collect::<Result<Vec<_>, _>>().unwrap();
+/// assert_eq!(terms, [Term::from(Atom::from("foo")), Term::from(Atom::from("bar"))]);
+/// ```
+#[derive(Debug)]
+pub struct TermReader<R> {
+    reader: R,
+    decoder: IncrementalDecoder,
+    buf: Vec<u8>,
+    start: usize,
+    end: usize,
+    in_term: bool,
+    failed: bool,
+}
+impl<R: io::Read> TermReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, DecodeOptions::new())
+    }
+    pub fn with_options(reader: R, options: DecodeOptions) -> Self {
+        TermReader {
+            reader,
+            decoder: IncrementalDecoder::with_options(options),
+            buf: vec![0; READ_BUF_SIZE],
+            start: 0,
+            end: 0,
+            in_term: false,
+            failed: false,
+        }
+    }
+    pub fn get_ref(&self) -> &R {
+        &self.reader
+    }
+    fn next_term(&mut self) -> Option<DecodeResult> {
+        loop {
+            if self.start == self.end {
+                let n = match self.reader.read(&mut self.buf) {
+                    Ok(n) => n,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Some(Err(e.into())),
+                };
+                if n == 0 {
+                    if !self.in_term {
+                        return None;
+                    }
+                    return Some(Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "stream ended in the middle of a term",
+                    )
+                    .into()));
+                }
+                self.start = 0;
+                self.end = n;
+            }
+            self.in_term = true;
+            match self.decoder.feed(&self.buf[self.start..self.end]) {
+                Err(e) => return Some(Err(e)),
+                Ok(DecodeStatus::NeedMore(_)) => {
+                    self.start = self.end;
+                }
+                Ok(DecodeStatus::Complete(term, consumed)) => {
+                    self.start += consumed;
+                    self.in_term = false;
+                    return Some(Ok(term));
+                }
+            }
+        }
+    }
+}
+impl<R: io::Read> Iterator for TermReader<R> {
+    type Item = DecodeResult;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let item = self.next_term();
+        self.failed = matches!(item, Some(Err(_)));
+        item
+    }
+}
+
+/// Writer of terms concatenated back-to-back.
+///
+/// Each term is encoded into an internal buffer, which is reused across calls,
+/// and then written to the underlying writer with a single `write_all`.
+#[derive(Debug)]
+pub struct TermWriter<W> {
+    writer: W,
+    options: EncodeOptions,
+    buf: Vec<u8>,
+}
+impl<W: io::Write> TermWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self::with_options(writer, EncodeOptions::new())
+    }
+    pub fn with_options(writer: W, options: EncodeOptions) -> Self {
+        TermWriter {
+            writer,
+            options,
+            buf: Vec::new(),
+        }
+    }
+    pub fn write_term(&mut self, term: &Term) -> EncodeResult {
+        self.buf.clear();
+        Encoder::with_options(&mut self.buf, self.options.clone()).encode(term)?;
+        self.writer.write_all(&self.buf)?;
+        Ok(())
+    }
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+    pub fn get_ref(&self) -> &W {
+        &self.writer
+    }
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
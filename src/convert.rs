@@ -1,4 +1,5 @@
 use super::*;
+use std::collections::BTreeMap;
 use std::convert::TryInto;
 
 pub trait TryAsRef<T> {
@@ -197,3 +198,731 @@ impl num_bigint::ToBigUint for Term {
         }
     }
 }
+
+/// Conversion of a Rust value into a [`Term`].
+///
+/// This is implemented for the term types themselves and for common standard types:
+///
+/// | Rust                                       | Term                                   |
+/// | ------------------------------------------ | -------------------------------------- |
+/// | integers (including `i128` and `u128`)     | [`FixInteger`] if it fits, otherwise [`BigInteger`] |
+/// | `f32`, `f64`                               | [`Float`] (non-finite values are rejected) |
+/// | `bool`                                     | `true` or `false`                      |
+/// | `String`, `str`                            | [`Binary`]                             |
+/// | `Vec<T>`, `[T]`                            | [`List`]                               |
+/// | `()` and tuples of up to 12 elements       | [`Tuple`]                              |
+/// | `HashMap<K, V>`, `BTreeMap<K, V>`          | [`Map`]                                |
+/// | `Option<T>`                                | `undefined` or the value itself        |
+/// | `Result<T, E>`                             | `{ok, T}` or `{error, E}`              |
+///
+/// # Deriving
+///
+/// With the `derive` feature, `#[derive(ToTerm, FromTerm)]` is available for structs and enums.
+/// Atoms default to the snake case of the Rust name.
+///
+/// | Rust                                       | Term                                   |
+/// | ------------------------------------------ | -------------------------------------- |
+/// | `struct S { a, b }`                        | `#{a => .., b => ..}`                  |
+/// | `#[eetf(record)] struct S { a, b }`        | `{s, .., ..}`                          |
+/// | `#[eetf(tuple)] struct S { a, b }`         | `{.., ..}`                             |
+/// | `#[eetf(elixir_struct = "M")] struct S { a }` | `%M{a: ..}`                         |
+/// | `struct S(a)`                              | `a` itself                             |
+/// | `struct S(a, b)`                           | `{.., ..}` (or `{s, .., ..}` with `record`) |
+/// | `struct S`                                 | `s`                                    |
+/// | enum variant `V`                           | `v`                                    |
+/// | enum variant `V(a, b)` or `V { a, b }`     | `{v, .., ..}`                          |
+///
+/// `#[eetf(rename = "...")]` on a struct, variant or map field overrides its atom,
+/// and `#[eetf(default)]` on a map field uses [`Default`] when the key is missing.
+///
+/// ```
+/// # #[cfg(feature = "derive")]
+/// # {
+/// use eetf::{FromTerm, ToTerm};
+///
+/// #[derive(Debug, PartialEq, ToTerm, FromTerm)]
+/// #[eetf(record)]
+/// struct Person {
+///     name: String,
+///     age: u8,
+/// }
+///
+/// #[derive(Debug, PartialEq, ToTerm, FromTerm)]
+/// enum Event {
+///     Joined(Person),
+///     #[eetf(rename = "bye")]
+///     Left { name: String },
+///     Shutdown,
+/// }
+///
+/// let event = Event::Joined(Person { name: "alice".to_owned(), age: 30 });
+/// let term = event.to_term().unwrap();
+/// assert_eq!(Event::from_term(term).unwrap(), event);
+///
+/// let term = Event::Left { name: "bob".to_owned() }.to_term().unwrap();
+/// assert_eq!(term, eetf::Term::from(eetf::Tuple::from(vec![
+///     eetf::Atom::from("bye").into(),
+///     "bob".to_term().unwrap(),
+/// ])));
+/// assert_eq!(Event::Shutdown.to_term().unwrap().to_string(), "shutdown");
+/// # }
+/// ```
+///
+/// # Examples
+///
+/// ```
+/// use eetf::convert::ToTerm;
+/// use eetf::{Atom, Binary, FixInteger, Term, Tuple};
+///
+/// let result: Result<(&str, u8), ()> = Ok(("foo", 1));
+/// assert_eq!(
+///     result.to_term().unwrap(),
+///     Term::from(Tuple::from(vec![
+///         Term::from(Atom::from("ok")),
+///         Term::from(Tuple::from(vec![
+///             Term::from(Binary::from(&b"foo"[..])),
+///             Term::from(FixInteger::from(1)),
+///         ])),
+///     ]))
+/// );
+/// ```
+pub trait ToTerm {
+    fn to_term(&self) -> Result<Term, ConvertError>;
+}
+
+/// Conversion of a [`Term`] into a Rust value.
+///
+/// This is the inverse of [`ToTerm`], implemented for the same types.
+/// In addition, strings also accept byte lists (`STRING_EXT`) and nil,
+/// and vectors also accept byte lists.
+///
+/// # Examples
+///
+/// ```
+/// use eetf::convert::{FromTerm, ToTerm};
+/// use eetf::PathSegment;
+///
+/// let term = vec![("a", 1), ("b", 300)].to_term().unwrap();
+/// let pairs = Vec::<(String, u8)>::from_term(term).unwrap_err();
+/// assert_eq!(
+///     pairs.path(),
+///     [PathSegment::ListElement(1), PathSegment::TupleElement(1)]
+/// );
+/// assert_eq!(pairs.to_string(), "300 is out of range of u8 in list[1] -> tuple[1]");
+/// ```
+pub trait FromTerm: Sized {
+    fn from_term(term: Term) -> Result<Self, ConvertError>;
+}
+
+/// Error which can occur when converting between a [`Term`] and a Rust value.
+///
+/// Besides its [`ConvertErrorKind`], the error tells where the conversion failed
+/// as the path from the root term to the offending term.
+pub struct ConvertError {
+    inner: Box<ConvertErrorInner>,
+}
+#[derive(Debug)]
+struct ConvertErrorInner {
+    kind: ConvertErrorKind,
+    path: Vec<PathSegment>,
+}
+impl ConvertError {
+    pub fn kind(&self) -> &ConvertErrorKind {
+        &self.inner.kind
+    }
+
+    pub fn into_kind(self) -> ConvertErrorKind {
+        self.inner.kind
+    }
+
+    /// Path from the root term to the term which failed to convert.
+    pub fn path(&self) -> &[PathSegment] {
+        &self.inner.path
+    }
+
+    /// Prepends a segment to the path, as the error propagates to the parent term.
+    ///
+    /// This is intended for [`ToTerm`] and [`FromTerm`] implementations of container types.
+    #[must_use]
+    pub fn within(mut self, segment: PathSegment) -> Self {
+        self.inner.path.insert(0, segment);
+        self
+    }
+
+    /// Makes an [`ConvertErrorKind::UnexpectedType`] error.
+    pub fn unexpected_type(value: Term, expected: impl Into<String>) -> Self {
+        ConvertErrorKind::UnexpectedType {
+            value,
+            expected: expected.into(),
+        }
+        .into()
+    }
+
+    fn out_of_range(value: Term, expected: &str) -> Self {
+        ConvertErrorKind::OutOfRange {
+            value,
+            expected: expected.to_owned(),
+        }
+        .into()
+    }
+}
+
+impl fmt::Debug for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConvertError")
+            .field("kind", &self.inner.kind)
+            .field("path", &self.inner.path)
+            .finish()
+    }
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.inner.kind)?;
+        for (i, segment) in self.inner.path.iter().enumerate() {
+            let separator = if i == 0 { " in " } else { " -> " };
+            write!(f, "{separator}{segment}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConvertError {}
+
+impl From<ConvertErrorKind> for ConvertError {
+    fn from(kind: ConvertErrorKind) -> Self {
+        ConvertError {
+            inner: Box::new(ConvertErrorInner {
+                kind,
+                path: Vec::new(),
+            }),
+        }
+    }
+}
+
+/// Kinds of errors which can occur when converting between a [`Term`] and a Rust value.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConvertErrorKind {
+    /// The term is not of the expected type (e.g. `"an integer"` or `"a tuple of 2 elements"`).
+    UnexpectedType { value: Term, expected: String },
+
+    /// The integer does not fit in the expected Rust type (e.g. `"u8"`).
+    OutOfRange { value: Term, expected: String },
+
+    /// A float to convert into a term is NaN or infinite.
+    NonFiniteFloat { value: f64 },
+
+    /// A map does not have the required key.
+    MissingKey { key: Term },
+
+    /// Error reported by a [`ToTerm`] or [`FromTerm`] implementation.
+    Custom { message: String },
+}
+
+impl fmt::Display for ConvertErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedType { value, expected } => write!(f, "{value} is not {expected}"),
+            Self::OutOfRange { value, expected } => {
+                write!(f, "{value} is out of range of {expected}")
+            }
+            Self::NonFiniteFloat { value } => {
+                write!(f, "non-finite float {value} cannot be a term")
+            }
+            Self::MissingKey { key } => write!(f, "missing key {key}"),
+            Self::Custom { message } => write!(f, "{message}"),
+        }
+    }
+}
+
+macro_rules! impl_term_conversion {
+    ($($to:ident),*) => {
+        $(
+            impl ToTerm for $to {
+                fn to_term(&self) -> Result<Term, ConvertError> {
+                    Ok(Term::from(self.clone()))
+                }
+            }
+            impl FromTerm for $to {
+                fn from_term(term: Term) -> Result<Self, ConvertError> {
+                    term.try_into().map_err(|term| {
+                        ConvertError::unexpected_type(term, concat!("a term of type ", stringify!($to)))
+                    })
+                }
+            }
+        )*
+    };
+}
+impl_term_conversion!(
+    Atom,
+    FixInteger,
+    BigInteger,
+    Float,
+    Pid,
+    Port,
+    Reference,
+    ExternalFun,
+    InternalFun,
+    Binary,
+    BitBinary,
+    ByteList,
+    List,
+    ImproperList,
+    Tuple,
+    Map
+);
+
+impl ToTerm for Term {
+    fn to_term(&self) -> Result<Term, ConvertError> {
+        Ok(self.clone())
+    }
+}
+impl FromTerm for Term {
+    fn from_term(term: Term) -> Result<Self, ConvertError> {
+        Ok(term)
+    }
+}
+
+impl<T: ToTerm + ?Sized> ToTerm for &T {
+    fn to_term(&self) -> Result<Term, ConvertError> {
+        (**self).to_term()
+    }
+}
+
+impl<T: ToTerm + ?Sized> ToTerm for Box<T> {
+    fn to_term(&self) -> Result<Term, ConvertError> {
+        (**self).to_term()
+    }
+}
+impl<T: FromTerm> FromTerm for Box<T> {
+    fn from_term(term: Term) -> Result<Self, ConvertError> {
+        T::from_term(term).map(Box::new)
+    }
+}
+
+macro_rules! impl_integer_conversion {
+    ($($ty:ty),*) => {
+        $(
+            impl ToTerm for $ty {
+                fn to_term(&self) -> Result<Term, ConvertError> {
+                    Ok(match i32::try_from(*self) {
+                        Ok(value) => Term::from(FixInteger { value }),
+                        Err(_) => Term::from(BigInteger {
+                            value: BigInt::from(*self),
+                        }),
+                    })
+                }
+            }
+            impl FromTerm for $ty {
+                fn from_term(term: Term) -> Result<Self, ConvertError> {
+                    let value = match &term {
+                        Term::FixInteger(x) => <$ty>::try_from(x.value).ok(),
+                        Term::BigInteger(x) => <$ty>::try_from(&x.value).ok(),
+                        _ => return Err(ConvertError::unexpected_type(term, "an integer")),
+                    };
+                    value.ok_or_else(|| ConvertError::out_of_range(term, stringify!($ty)))
+                }
+            }
+        )*
+    };
+}
+impl_integer_conversion!(
+    i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize
+);
+
+impl ToTerm for f64 {
+    fn to_term(&self) -> Result<Term, ConvertError> {
+        Float::try_from(*self)
+            .map(Term::from)
+            .map_err(|_| ConvertErrorKind::NonFiniteFloat { value: *self }.into())
+    }
+}
+impl FromTerm for f64 {
+    fn from_term(term: Term) -> Result<Self, ConvertError> {
+        match term {
+            Term::Float(x) => Ok(x.value),
+            _ => Err(ConvertError::unexpected_type(term, "a float")),
+        }
+    }
+}
+
+impl ToTerm for f32 {
+    fn to_term(&self) -> Result<Term, ConvertError> {
+        f64::from(*self).to_term()
+    }
+}
+impl FromTerm for f32 {
+    fn from_term(term: Term) -> Result<Self, ConvertError> {
+        f64::from_term(term).map(|x| x as f32)
+    }
+}
+
+impl ToTerm for bool {
+    fn to_term(&self) -> Result<Term, ConvertError> {
+        Ok(Term::from(Atom::from(if *self { "true" } else { "false" })))
+    }
+}
+impl FromTerm for bool {
+    fn from_term(term: Term) -> Result<Self, ConvertError> {
+        match &term {
+            Term::Atom(x) if x.name == "true" => Ok(true),
+            Term::Atom(x) if x.name == "false" => Ok(false),
+            _ => Err(ConvertError::unexpected_type(term, "a boolean")),
+        }
+    }
+}
+
+impl ToTerm for str {
+    fn to_term(&self) -> Result<Term, ConvertError> {
+        Ok(Term::from(Binary::from(self.as_bytes())))
+    }
+}
+impl ToTerm for String {
+    fn to_term(&self) -> Result<Term, ConvertError> {
+        self.as_str().to_term()
+    }
+}
+impl FromTerm for String {
+    fn from_term(term: Term) -> Result<Self, ConvertError> {
+        match term {
+            Term::Binary(x) => String::from_utf8(x.bytes).map_err(|e| {
+                ConvertError::unexpected_type(
+                    Term::from(Binary::from(e.into_bytes())),
+                    "a UTF-8 binary",
+                )
+            }),
+            Term::ByteList(x) => Ok(x.bytes.iter().map(|&b| char::from(b)).collect()),
+            Term::List(x) if x.is_nil() => Ok(String::new()),
+            _ => Err(ConvertError::unexpected_type(term, "a string")),
+        }
+    }
+}
+
+impl<T: ToTerm> ToTerm for [T] {
+    fn to_term(&self) -> Result<Term, ConvertError> {
+        let elements = self
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                x.to_term()
+                    .map_err(|e| e.within(PathSegment::ListElement(i)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Term::from(List::from(elements)))
+    }
+}
+impl<T: ToTerm> ToTerm for Vec<T> {
+    fn to_term(&self) -> Result<Term, ConvertError> {
+        self.as_slice().to_term()
+    }
+}
+impl<T: FromTerm> FromTerm for Vec<T> {
+    fn from_term(term: Term) -> Result<Self, ConvertError> {
+        let elements = match term {
+            Term::List(x) => x.elements,
+            Term::ByteList(x) => List::from(x).elements,
+            _ => return Err(ConvertError::unexpected_type(term, "a list")),
+        };
+        elements
+            .into_iter()
+            .enumerate()
+            .map(|(i, x)| T::from_term(x).map_err(|e| e.within(PathSegment::ListElement(i))))
+            .collect()
+    }
+}
+
+macro_rules! impl_tuple_conversion {
+    ($len:expr; $($name:ident $index:tt),*) => {
+        impl<$($name: ToTerm),*> ToTerm for ($($name,)*) {
+            fn to_term(&self) -> Result<Term, ConvertError> {
+                Ok(Term::from(Tuple::from(vec![
+                    $(
+                        self.$index
+                            .to_term()
+                            .map_err(|e| e.within(PathSegment::TupleElement($index)))?,
+                    )*
+                ])))
+            }
+        }
+        impl<$($name: FromTerm),*> FromTerm for ($($name,)*) {
+            fn from_term(term: Term) -> Result<Self, ConvertError> {
+                let elements = match term {
+                    Term::Tuple(x) if x.elements.len() == $len => x.elements,
+                    _ => {
+                        return Err(ConvertError::unexpected_type(
+                            term,
+                            concat!("a tuple of ", $len, " elements"),
+                        ));
+                    }
+                };
+                let mut elements = elements.into_iter();
+                Ok(($(
+                    $name::from_term(elements.next().expect("the length is checked"))
+                        .map_err(|e| e.within(PathSegment::TupleElement($index)))?,
+                )*))
+            }
+        }
+    };
+}
+impl ToTerm for () {
+    fn to_term(&self) -> Result<Term, ConvertError> {
+        Ok(Term::from(Tuple::from(Vec::new())))
+    }
+}
+impl FromTerm for () {
+    fn from_term(term: Term) -> Result<Self, ConvertError> {
+        match term {
+            Term::Tuple(x) if x.elements.is_empty() => Ok(()),
+            _ => Err(ConvertError::unexpected_type(term, "an empty tuple")),
+        }
+    }
+}
+impl_tuple_conversion!(1; A 0);
+impl_tuple_conversion!(2; A 0, B 1);
+impl_tuple_conversion!(3; A 0, B 1, C 2);
+impl_tuple_conversion!(4; A 0, B 1, C 2, D 3);
+impl_tuple_conversion!(5; A 0, B 1, C 2, D 3, E 4);
+impl_tuple_conversion!(6; A 0, B 1, C 2, D 3, E 4, F 5);
+impl_tuple_conversion!(7; A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_tuple_conversion!(8; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+impl_tuple_conversion!(9; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8);
+impl_tuple_conversion!(10; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9);
+impl_tuple_conversion!(11; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10);
+impl_tuple_conversion!(12; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
+
+fn map_to_term<'a, K, V, I>(entries: I) -> Result<Term, ConvertError>
+where
+    K: ToTerm + 'a,
+    V: ToTerm + 'a,
+    I: Iterator<Item = (&'a K, &'a V)>,
+{
+    let mut map = HashMap::new();
+    for (i, (k, v)) in entries.enumerate() {
+        let k = k.to_term().map_err(|e| e.within(PathSegment::MapKey(i)))?;
+        let v = v
+            .to_term()
+            .map_err(|e| e.within(PathSegment::MapValue(k.clone())))?;
+        map.insert(k, v);
+    }
+    Ok(Term::from(Map::from(map)))
+}
+
+fn map_from_term<K, V, C>(term: Term) -> Result<C, ConvertError>
+where
+    K: FromTerm,
+    V: FromTerm,
+    C: FromIterator<(K, V)>,
+{
+    let map = match term {
+        Term::Map(x) => x.map,
+        _ => return Err(ConvertError::unexpected_type(term, "a map")),
+    };
+    map.into_iter()
+        .enumerate()
+        .map(|(i, (k, v))| {
+            let segment = PathSegment::MapValue(k.clone());
+            let k = K::from_term(k).map_err(|e| e.within(PathSegment::MapKey(i)))?;
+            let v = V::from_term(v).map_err(|e| e.within(segment))?;
+            Ok((k, v))
+        })
+        .collect()
+}
+
+impl<K: ToTerm, V: ToTerm, S> ToTerm for HashMap<K, V, S> {
+    fn to_term(&self) -> Result<Term, ConvertError> {
+        map_to_term(self.iter())
+    }
+}
+impl<K, V, S> FromTerm for HashMap<K, V, S>
+where
+    K: FromTerm + Eq + Hash,
+    V: FromTerm,
+    S: std::hash::BuildHasher + Default,
+{
+    fn from_term(term: Term) -> Result<Self, ConvertError> {
+        map_from_term(term)
+    }
+}
+
+impl<K: ToTerm, V: ToTerm> ToTerm for BTreeMap<K, V> {
+    fn to_term(&self) -> Result<Term, ConvertError> {
+        map_to_term(self.iter())
+    }
+}
+impl<K: FromTerm + Ord, V: FromTerm> FromTerm for BTreeMap<K, V> {
+    fn from_term(term: Term) -> Result<Self, ConvertError> {
+        map_from_term(term)
+    }
+}
+
+/// `None` is the atom `undefined`, as is the convention of Erlang.
+impl<T: ToTerm> ToTerm for Option<T> {
+    fn to_term(&self) -> Result<Term, ConvertError> {
+        match self {
+            None => Ok(Term::from(Atom::from("undefined"))),
+            Some(x) => x.to_term(),
+        }
+    }
+}
+impl<T: FromTerm> FromTerm for Option<T> {
+    fn from_term(term: Term) -> Result<Self, ConvertError> {
+        match &term {
+            Term::Atom(x) if x.name == "undefined" => Ok(None),
+            _ => T::from_term(term).map(Some),
+        }
+    }
+}
+
+/// `Ok(x)` is `{ok, x}` and `Err(x)` is `{error, x}`.
+impl<T: ToTerm, E: ToTerm> ToTerm for Result<T, E> {
+    fn to_term(&self) -> Result<Term, ConvertError> {
+        let (tag, value) = match self {
+            Ok(x) => ("ok", x.to_term()),
+            Err(x) => ("error", x.to_term()),
+        };
+        let value = value.map_err(|e| e.within(PathSegment::TupleElement(1)))?;
+        Ok(Term::from(Tuple::from(vec![
+            Term::from(Atom::from(tag)),
+            value,
+        ])))
+    }
+}
+impl<T: FromTerm, E: FromTerm> FromTerm for Result<T, E> {
+    fn from_term(term: Term) -> Result<Self, ConvertError> {
+        let (tag, value) = match term {
+            Term::Tuple(mut x) if x.elements.len() == 2 => {
+                let value = x.elements.pop().expect("the length is checked");
+                match x.elements.pop().expect("the length is checked") {
+                    Term::Atom(tag) if tag.name == "ok" || tag.name == "error" => (tag, value),
+                    tag => {
+                        x.elements.extend([tag, value]);
+                        return Err(ConvertError::unexpected_type(
+                            Term::from(x),
+                            "{ok, _} or {error, _}",
+                        ));
+                    }
+                }
+            }
+            _ => {
+                return Err(ConvertError::unexpected_type(term, "{ok, _} or {error, _}"));
+            }
+        };
+        let result = if tag.name == "ok" {
+            T::from_term(value).map(Ok)
+        } else {
+            E::from_term(value).map(Err)
+        };
+        result.map_err(|e| e.within(PathSegment::TupleElement(1)))
+    }
+}
+
+/// Helpers for the code generated by `#[derive(ToTerm, FromTerm)]`. Not public API.
+#[doc(hidden)]
+pub mod __private {
+    use super::*;
+    use std::vec::IntoIter;
+
+    pub use std::collections::HashMap;
+
+    pub fn atom(name: &str) -> Term {
+        Term::from(Atom::from(name))
+    }
+
+    /// Returns the name of an atom, or the tag of a tuple along with the number of the other elements.
+    pub fn variant(term: &Term) -> Option<(&str, Option<usize>)> {
+        match term {
+            Term::Atom(x) => Some((&x.name, None)),
+            Term::Tuple(x) => match x.elements.first() {
+                Some(Term::Atom(tag)) => Some((&tag.name, Some(x.elements.len() - 1))),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn tuple_len(term: &Term) -> Option<usize> {
+        match term {
+            Term::Tuple(x) => Some(x.elements.len()),
+            _ => None,
+        }
+    }
+
+    pub fn into_elements(term: Term) -> IntoIter<Term> {
+        match term {
+            Term::Tuple(x) => x.elements.into_iter(),
+            _ => Vec::new().into_iter(),
+        }
+    }
+
+    pub fn element<T: FromTerm>(
+        elements: &mut IntoIter<Term>,
+        index: usize,
+    ) -> Result<T, ConvertError> {
+        let term = elements.next().ok_or_else(|| ConvertErrorKind::Custom {
+            message: format!("missing tuple element {index}"),
+        })?;
+        T::from_term(term).map_err(|e| e.within(PathSegment::TupleElement(index)))
+    }
+
+    pub fn to_element<T: ToTerm + ?Sized>(value: &T, index: usize) -> Result<Term, ConvertError> {
+        value
+            .to_term()
+            .map_err(|e| e.within(PathSegment::TupleElement(index)))
+    }
+
+    pub fn tuple(elements: Vec<Term>) -> Term {
+        Term::from(Tuple::from(elements))
+    }
+
+    /// Returns the entries of a map, which must have the key `__struct__` if `elixir_struct` is specified.
+    pub fn into_map(
+        term: Term,
+        elixir_struct: Option<&str>,
+        expected: &str,
+    ) -> Result<HashMap<Term, Term>, ConvertError> {
+        match term {
+            Term::Map(x)
+                if elixir_struct
+                    .is_none_or(|module| x.map.get(&atom("__struct__")) == Some(&atom(module))) =>
+            {
+                Ok(x.map)
+            }
+            _ => Err(ConvertError::unexpected_type(term, expected)),
+        }
+    }
+
+    /// Removes the entry with the atom `key` from `map`, and converts its value.
+    pub fn field<T: FromTerm>(
+        map: &mut HashMap<Term, Term>,
+        key: &str,
+    ) -> Result<Option<T>, ConvertError> {
+        let key = atom(key);
+        match map.remove(&key) {
+            None => Ok(None),
+            Some(value) => T::from_term(value)
+                .map(Some)
+                .map_err(|e| e.within(PathSegment::MapValue(key))),
+        }
+    }
+
+    pub fn missing_field(key: &str) -> ConvertError {
+        ConvertErrorKind::MissingKey { key: atom(key) }.into()
+    }
+
+    pub fn insert_field<T: ToTerm + ?Sized>(
+        map: &mut HashMap<Term, Term>,
+        key: &str,
+        value: &T,
+    ) -> Result<(), ConvertError> {
+        let key = atom(key);
+        let value = value
+            .to_term()
+            .map_err(|e| e.within(PathSegment::MapValue(key.clone())))?;
+        map.insert(key, value);
+        Ok(())
+    }
+
+    pub fn map(map: HashMap<Term, Term>) -> Term {
+        Term::from(Map::from(map))
+    }
+}
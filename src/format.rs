@@ -0,0 +1,248 @@
+//! [serde] data format on top of the codec.
+//!
+//! Rust values are mapped to terms as follows:
+//!
+//! | Rust                                  | Term                                      |
+//! | ------------------------------------- | ----------------------------------------- |
+//! | `bool`                                | `true` or `false`                         |
+//! | integers, `char`                      | integer                                   |
+//! | `f32`, `f64`                          | float (NaN and infinities are rejected)   |
+//! | `String`, `&str`                      | binary                                    |
+//! | bytes (e.g. `serde_bytes::ByteBuf`)   | binary                                    |
+//! | `None`                                | `undefined` (see [`SerdeOptions::none_atom`]) |
+//! | `Some(x)`, newtype struct             | `x`                                       |
+//! | `()`, unit struct                     | `{}`                                      |
+//! | sequence                              | list                                      |
+//! | tuple, tuple struct                   | tuple                                     |
+//! | map                                   | map                                       |
+//! | struct                                | map with atom keys                        |
+//! | unit variant `V`                      | `'V'`                                     |
+//! | newtype variant `V(x)`                | `{'V', x}`                                |
+//! | tuple variant `V(x, y)`               | `{'V', x, y}`                             |
+//! | struct variant `V { a: x }`           | `{'V', #{a => x}}`                        |
+//!
+//! On deserialization, the mapping is more lenient:
+//! strings and struct fields also accept atoms and byte lists (`STRING_EXT`),
+//! and sequences also accept tuples.
+use crate::codec::{DecodeError, DecodeErrorKind, EncodeError, EncodeOptions};
+use crate::{Atom, Term};
+use std::fmt;
+
+mod de;
+mod ser;
+
+/// Serializes `value` into the external term format.
+///
+/// # Examples
+///
+/// ```
+/// use eetf::{Atom, FixInteger, Map, Term};
+///
+/// #[derive(serde::Serialize)]
+/// struct Point {
+///     x: i32,
+///     y: i32,
+/// }
+///
+/// let bytes = eetf::to_vec(&Point { x: 1, y: 2 }).unwrap();
+/// let expected = Map::from([
+///     (Term::from(Atom::from("x")), Term::from(FixInteger::from(1))),
+///     (Term::from(Atom::from("y")), Term::from(FixInteger::from(2))),
+/// ]);
+/// assert_eq!(Term::decode(&bytes[..]).unwrap(), Term::from(expected));
+/// ```
+pub fn to_vec<T: serde::Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, SerdeError> {
+    to_vec_with_options(value, &SerdeOptions::new())
+}
+
+/// Serializes `value` into the external term format with the given options.
+pub fn to_vec_with_options<T: serde::Serialize + ?Sized>(
+    value: &T,
+    options: &SerdeOptions,
+) -> Result<Vec<u8>, SerdeError> {
+    let term = to_term_with_options(value, options)?;
+    let mut buf = Vec::new();
+    term.encode_with_options(&mut buf, options.encode_options.clone())
+        .map_err(SerdeError::Encode)?;
+    Ok(buf)
+}
+
+/// Converts `value` into a [`Term`].
+pub fn to_term<T: serde::Serialize + ?Sized>(value: &T) -> Result<Term, SerdeError> {
+    to_term_with_options(value, &SerdeOptions::new())
+}
+
+/// Converts `value` into a [`Term`] with the given options.
+pub fn to_term_with_options<T: serde::Serialize + ?Sized>(
+    value: &T,
+    options: &SerdeOptions,
+) -> Result<Term, SerdeError> {
+    value.serialize(ser::Serializer::new(options))
+}
+
+/// Deserializes a value from a term occupying the whole of `bytes`.
+///
+/// The value is deserialized straight from the bytes, without building a [`Term`].
+/// Strings and byte slices can be borrowed from `bytes` unless the term is compressed.
+///
+/// # Examples
+///
+/// ```
+/// #[derive(Debug, PartialEq, serde::Deserialize)]
+/// enum Reply<'a> {
+///     #[serde(rename = "ok")]
+///     Ok(&'a str),
+///     #[serde(rename = "error")]
+///     Error(String),
+/// }
+///
+/// // term_to_binary({ok, <<"hello">>})
+/// let bytes = [131, 104, 2, 119, 2, 111, 107, 109, 0, 0, 0, 5, 104, 101, 108, 108, 111];
+/// assert_eq!(eetf::from_slice::<Reply>(&bytes).unwrap(), Reply::Ok("hello"));
+/// ```
+pub fn from_slice<'de, T: serde::Deserialize<'de>>(bytes: &'de [u8]) -> Result<T, SerdeError> {
+    from_slice_with_options(bytes, &SerdeOptions::new())
+}
+
+/// Deserializes a value from a term occupying the whole of `bytes` with the given options.
+pub fn from_slice_with_options<'de, T: serde::Deserialize<'de>>(
+    bytes: &'de [u8],
+    options: &SerdeOptions,
+) -> Result<T, SerdeError> {
+    de::from_slice(bytes, options)
+}
+
+/// Options for the [serde] data format.
+#[derive(Debug, Clone)]
+pub struct SerdeOptions {
+    none_atom: Atom,
+    encode_options: EncodeOptions,
+}
+impl SerdeOptions {
+    /// Default options: `None` is `undefined`, and the default [`EncodeOptions`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the atom representing `None` (e.g. `"nil"` for Elixir).
+    ///
+    /// On deserialization, this atom is the only term accepted as `None`.
+    #[must_use]
+    pub fn none_atom(mut self, name: &str) -> Self {
+        self.none_atom = Atom::from(name);
+        self
+    }
+
+    /// Sets the options used to encode the serialized terms.
+    #[must_use]
+    pub fn encode_options(mut self, options: EncodeOptions) -> Self {
+        self.encode_options = options;
+        self
+    }
+}
+impl Default for SerdeOptions {
+    fn default() -> Self {
+        Self {
+            none_atom: Atom::from("undefined"),
+            encode_options: EncodeOptions::new(),
+        }
+    }
+}
+
+/// Errors of the [serde] data format.
+#[derive(Debug)]
+pub enum SerdeError {
+    /// The input is not a valid term.
+    Decode(DecodeError),
+
+    /// The serialized term cannot be encoded.
+    Encode(EncodeError),
+
+    /// The term does not match the expected type,
+    /// or a custom error reported by a `Serialize` or `Deserialize` implementation.
+    Message {
+        message: String,
+
+        /// Byte offset of the term the error refers to, if known.
+        offset: Option<usize>,
+    },
+}
+impl SerdeError {
+    fn message<T: fmt::Display>(message: T) -> Self {
+        Self::Message {
+            message: message.to_string(),
+            offset: None,
+        }
+    }
+
+    /// Sets the offset unless it is already known.
+    fn at(self, offset: usize) -> Self {
+        match self {
+            Self::Decode(error) => Self::Decode(error.at(offset)),
+            Self::Message {
+                message,
+                offset: None,
+            } => Self::Message {
+                message,
+                offset: Some(offset),
+            },
+            error => error,
+        }
+    }
+}
+
+impl fmt::Display for SerdeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Decode(error) => write!(f, "cannot decode a term: {error}"),
+            Self::Encode(error) => write!(f, "cannot encode a term: {error}"),
+            Self::Message { message, offset } => {
+                write!(f, "{message}")?;
+                if let Some(offset) = offset {
+                    write!(f, " at byte {offset}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl std::error::Error for SerdeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Decode(error) => Some(error),
+            Self::Encode(error) => Some(error),
+            Self::Message { .. } => None,
+        }
+    }
+}
+
+impl serde::ser::Error for SerdeError {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Self::message(message)
+    }
+}
+
+impl serde::de::Error for SerdeError {
+    fn custom<T: fmt::Display>(message: T) -> Self {
+        Self::message(message)
+    }
+}
+
+impl From<DecodeError> for SerdeError {
+    fn from(value: DecodeError) -> Self {
+        Self::Decode(value)
+    }
+}
+
+impl From<std::io::Error> for SerdeError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Decode(value.into())
+    }
+}
+
+impl From<DecodeErrorKind> for SerdeError {
+    fn from(value: DecodeErrorKind) -> Self {
+        Self::Decode(value.into())
+    }
+}
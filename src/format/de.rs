@@ -0,0 +1,774 @@
+use super::*;
+use crate::codec::{self, DecodeLimit, DecodeOptions};
+use crate::codec::{
+    ATOM_CACHE_REF, ATOM_EXT, ATOM_UTF8_EXT, BINARY_EXT, BIT_BINARY_EXT, COMPRESSED_TERM,
+    EXPORT_EXT, FLOAT_EXT, FUN_EXT, INTEGER_EXT, LARGE_BIG_EXT, LARGE_TUPLE_EXT, LIST_EXT, MAP_EXT,
+    NEW_FLOAT_EXT, NEW_FUN_EXT, NEW_PID_EXT, NEW_PORT_EXT, NEW_REFERENCE_EXT, NEWER_REFERENCE_EXT,
+    NIL_EXT, PID_EXT, PORT_EXT, REFERENCE_EXT, SMALL_ATOM_EXT, SMALL_ATOM_UTF8_EXT, SMALL_BIG_EXT,
+    SMALL_INTEGER_EXT, SMALL_TUPLE_EXT, STRING_EXT, V4_PORT_EXT, VERSION,
+};
+use serde::de::{self, DeserializeSeed, IntoDeserializer, Unexpected, Visitor};
+use std::io;
+use std::ops::Range;
+use std::str;
+
+/// Maximum nesting depth of a deserialized term (the root term is at depth `0`).
+const MAX_DEPTH: usize = 128;
+
+type Result<T> = std::result::Result<T, SerdeError>;
+
+pub(super) fn from_slice<'de, T: de::Deserialize<'de>>(
+    bytes: &'de [u8],
+    options: &SerdeOptions,
+) -> Result<T> {
+    match bytes {
+        [VERSION, COMPRESSED_TERM, rest @ ..] => {
+            // As with `DecodeError::offset`, offsets are counted in the decompressed data.
+            let (body, size) =
+                codec::inflate_compressed_term(rest, &DecodeOptions::new()).map_err(|e| e.at(1))?;
+            if 2 + size < bytes.len() {
+                return Err(DecodeErrorKind::TrailingBytes { offset: 2 + size }.into());
+            }
+            Deserializer::new(body, 0, options).deserialize_root()
+        }
+        [VERSION, ..] => Deserializer::new(bytes, 1, options).deserialize_root(),
+        [version, ..] => {
+            Err(SerdeError::from(DecodeErrorKind::UnsupportedVersion { version: *version }).at(0))
+        }
+        [] => Err(eof_error().at(0)),
+    }
+}
+
+fn eof_error() -> SerdeError {
+    io::Error::new(
+        io::ErrorKind::UnexpectedEof,
+        "input ended in the middle of a term",
+    )
+    .into()
+}
+
+/// Bytes read by [`Deserializer`]: either the input borrowed for `'de`,
+/// or a buffer owned by the deserializer (the decompressed body of a compressed term).
+trait Input<'de> {
+    fn bytes(&self) -> &[u8];
+    fn slice(&self, range: Range<usize>) -> Slice<'de, '_>;
+}
+impl<'de> Input<'de> for &'de [u8] {
+    fn bytes(&self) -> &[u8] {
+        self
+    }
+
+    fn slice(&self, range: Range<usize>) -> Slice<'de, '_> {
+        let bytes: &'de [u8] = self;
+        Slice::Borrowed(&bytes[range])
+    }
+}
+impl<'de> Input<'de> for Vec<u8> {
+    fn bytes(&self) -> &[u8] {
+        self
+    }
+
+    fn slice(&self, range: Range<usize>) -> Slice<'de, '_> {
+        Slice::Copied(&self[range])
+    }
+}
+
+enum Slice<'de, 's> {
+    Borrowed(&'de [u8]),
+    Copied(&'s [u8]),
+}
+impl<'de, 's> Slice<'de, 's> {
+    fn bytes(&self) -> &[u8] {
+        match *self {
+            Self::Borrowed(bytes) => bytes,
+            Self::Copied(bytes) => bytes,
+        }
+    }
+
+    /// Interprets the bytes as UTF-8, or as Latin-1 if `latin1` is `true`.
+    fn text(self, latin1: bool) -> std::result::Result<Text<'de, 's>, str::Utf8Error> {
+        if latin1 && !self.bytes().is_ascii() {
+            return Ok(Text::Owned(
+                self.bytes().iter().map(|&b| char::from(b)).collect(),
+            ));
+        }
+        match self {
+            Self::Borrowed(bytes) => str::from_utf8(bytes).map(Text::Borrowed),
+            Self::Copied(bytes) => str::from_utf8(bytes).map(Text::Copied),
+        }
+    }
+
+    fn visit_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Self::Borrowed(bytes) => visitor.visit_borrowed_bytes(bytes),
+            Self::Copied(bytes) => visitor.visit_bytes(bytes),
+        }
+    }
+
+    /// Visits the bytes as a string if they are valid UTF-8, or as bytes otherwise.
+    fn visit_str_or_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        if str::from_utf8(self.bytes()).is_ok() {
+            self.text(false)
+                .expect("already checked to be UTF-8")
+                .visit(visitor)
+        } else {
+            self.visit_bytes(visitor)
+        }
+    }
+}
+
+enum Text<'de, 's> {
+    Borrowed(&'de str),
+    Copied(&'s str),
+    Owned(String),
+}
+impl<'de> Text<'de, '_> {
+    fn as_str(&self) -> &str {
+        match self {
+            Self::Borrowed(text) => text,
+            Self::Copied(text) => text,
+            Self::Owned(text) => text,
+        }
+    }
+
+    fn visit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self {
+            Self::Borrowed(text) => visitor.visit_borrowed_str(text),
+            Self::Copied(text) => visitor.visit_str(text),
+            Self::Owned(text) => visitor.visit_string(text),
+        }
+    }
+}
+
+/// Name of an atom in the input.
+struct AtomName {
+    range: Range<usize>,
+    latin1: bool,
+}
+
+/// Deserializer reading a term straight from its encoded bytes.
+struct Deserializer<'o, I> {
+    input: I,
+    pos: usize,
+    depth: usize,
+    options: &'o SerdeOptions,
+}
+impl<'de, 'o, I: Input<'de>> Deserializer<'o, I> {
+    fn new(input: I, pos: usize, options: &'o SerdeOptions) -> Self {
+        Self {
+            input,
+            pos,
+            depth: 0,
+            options,
+        }
+    }
+
+    fn deserialize_root<T: de::Deserialize<'de>>(mut self) -> Result<T> {
+        let value = T::deserialize(&mut self)?;
+        if self.pos < self.input.bytes().len() {
+            return Err(DecodeErrorKind::TrailingBytes { offset: self.pos }.into());
+        }
+        Ok(value)
+    }
+
+    /// Runs `f` attributing its errors to the term at the current position, unless they are located already.
+    fn located<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let start = self.pos;
+        f(self).map_err(|e| e.at(start))
+    }
+
+    fn enter(&mut self) -> Result<()> {
+        if self.depth == MAX_DEPTH {
+            return Err(DecodeErrorKind::LimitExceeded {
+                limit: DecodeLimit::Depth,
+                max: MAX_DEPTH,
+            }
+            .into());
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+
+    fn take(&mut self, n: usize) -> Result<Range<usize>> {
+        let start = self.pos;
+        let end = start
+            .checked_add(n)
+            .filter(|&end| end <= self.input.bytes().len())
+            .ok_or_else(eof_error)?;
+        self.pos = end;
+        Ok(start..end)
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let range = self.take(N)?;
+        Ok(self.input.bytes()[range]
+            .try_into()
+            .expect("the range has N bytes"))
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        self.read_array().map(u8::from_be_bytes)
+    }
+
+    fn read_u16(&mut self) -> Result<u16> {
+        self.read_array().map(u16::from_be_bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<u32> {
+        self.read_array().map(u32::from_be_bytes)
+    }
+
+    fn read_i32(&mut self) -> Result<i32> {
+        self.read_array().map(i32::from_be_bytes)
+    }
+
+    fn read_len(&mut self) -> Result<usize> {
+        self.read_u32().map(|n| n as usize)
+    }
+
+    /// Reads the name of an atom if `tag` is the tag of an atom.
+    fn read_atom_name(&mut self, tag: u8) -> Result<Option<AtomName>> {
+        let (len, latin1) = match tag {
+            ATOM_EXT => (usize::from(self.read_u16()?), true),
+            ATOM_UTF8_EXT => (usize::from(self.read_u16()?), false),
+            SMALL_ATOM_EXT => (usize::from(self.read_u8()?), true),
+            SMALL_ATOM_UTF8_EXT => (usize::from(self.read_u8()?), false),
+            _ => return Ok(None),
+        };
+        let range = self.take(len)?;
+        Ok(Some(AtomName { range, latin1 }))
+    }
+
+    fn atom_text(&self, name: AtomName) -> Result<Text<'de, '_>> {
+        self.input
+            .slice(name.range)
+            .text(name.latin1)
+            .map_err(|error| DecodeErrorKind::InvalidUtf8Atom { error }.into())
+    }
+
+    /// Consumes the next term if it is the atom representing `None`.
+    fn read_none_atom(&mut self) -> Result<bool> {
+        let start = self.pos;
+        let tag = self.read_u8()?;
+        if let Some(name) = self.read_atom_name(tag)?
+            && self.atom_text(name)?.as_str() == self.options.none_atom.name
+        {
+            return Ok(true);
+        }
+        self.pos = start;
+        Ok(false)
+    }
+
+    fn read_float_text(&mut self) -> Result<f64> {
+        let range = self.take(31)?;
+        let text: String = self.input.bytes()[range]
+            .iter()
+            .map(|&b| char::from(b))
+            .collect();
+        let text = text.trim_end_matches('\0');
+        text.trim().parse::<f64>().map_err(|_| {
+            DecodeErrorKind::InvalidFloat {
+                text: text.to_owned(),
+            }
+            .into()
+        })
+    }
+
+    /// Reads the list of integers following `LIST_EXT` as a string of code points,
+    /// or returns `None` if any element is not a valid code point or the list is improper.
+    fn read_code_points(&mut self) -> Result<Option<String>> {
+        let len = self.read_len()?;
+        let mut text = String::new();
+        for _ in 0..len {
+            let c = match self.read_u8()? {
+                SMALL_INTEGER_EXT => char::from(self.read_u8()?),
+                INTEGER_EXT => match char::from_u32(self.read_i32()? as u32) {
+                    Some(c) => c,
+                    None => return Ok(None),
+                },
+                _ => return Ok(None),
+            };
+            text.push(c);
+        }
+        if self.read_u8()? != NIL_EXT {
+            return Ok(None);
+        }
+        Ok(Some(text))
+    }
+
+    fn visit_integer<V: Visitor<'de>>(&mut self, len: usize, visitor: V) -> Result<V::Value> {
+        let sign = self.read_u8()?;
+        let range = self.take(len)?;
+        let digits = &self.input.bytes()[range];
+        if digits.iter().skip(16).any(|&b| b != 0) {
+            return Err(SerdeError::message(format!(
+                "integer of {len} bytes does not fit in 128 bits"
+            )));
+        }
+        let magnitude = digits
+            .iter()
+            .take(16)
+            .rev()
+            .fold(0u128, |acc, &b| (acc << 8) | u128::from(b));
+        match sign {
+            0 => match u64::try_from(magnitude) {
+                Ok(v) => visitor.visit_u64(v),
+                Err(_) => visitor.visit_u128(magnitude),
+            },
+            1 if magnitude <= 1 << 63 => visitor.visit_i64((magnitude as i64).wrapping_neg()),
+            1 if magnitude <= 1 << 127 => visitor.visit_i128((magnitude as i128).wrapping_neg()),
+            1 => Err(SerdeError::message(
+                "negative integer does not fit in 128 bits",
+            )),
+            _ => Err(DecodeErrorKind::InvalidSign { sign }.into()),
+        }
+    }
+
+    fn visit_elements<V: Visitor<'de>>(
+        &mut self,
+        len: usize,
+        bytes: bool,
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.enter()?;
+        let mut elements = Elements {
+            de: self,
+            remaining: len,
+            bytes,
+        };
+        let value = visitor.visit_seq(&mut elements)?;
+        elements.end(len)?;
+        self.leave();
+        Ok(value)
+    }
+
+    fn visit_entries<V: Visitor<'de>>(&mut self, len: usize, visitor: V) -> Result<V::Value> {
+        self.enter()?;
+        let mut entries = Entries {
+            de: self,
+            remaining: len,
+        };
+        let value = visitor.visit_map(&mut entries)?;
+        if entries.remaining != 0 {
+            return Err(de::Error::invalid_length(len, &"fewer entries"));
+        }
+        self.leave();
+        Ok(value)
+    }
+
+    fn parse_any<V: Visitor<'de>>(&mut self, visitor: V) -> Result<V::Value> {
+        let tag = self.read_u8()?;
+        if let Some(name) = self.read_atom_name(tag)? {
+            let text = self.atom_text(name)?;
+            return match text.as_str() {
+                "true" => visitor.visit_bool(true),
+                "false" => visitor.visit_bool(false),
+                name if name == self.options.none_atom.name => visitor.visit_none(),
+                _ => text.visit(visitor),
+            };
+        }
+        match tag {
+            SMALL_INTEGER_EXT => visitor.visit_u8(self.read_u8()?),
+            INTEGER_EXT => visitor.visit_i32(self.read_i32()?),
+            SMALL_BIG_EXT => {
+                let len = usize::from(self.read_u8()?);
+                self.visit_integer(len, visitor)
+            }
+            LARGE_BIG_EXT => {
+                let len = self.read_len()?;
+                self.visit_integer(len, visitor)
+            }
+            NEW_FLOAT_EXT | FLOAT_EXT => {
+                let value = if tag == NEW_FLOAT_EXT {
+                    f64::from_be_bytes(self.read_array()?)
+                } else {
+                    self.read_float_text()?
+                };
+                if !value.is_finite() {
+                    return Err(DecodeErrorKind::NonFiniteFloat.into());
+                }
+                visitor.visit_f64(value)
+            }
+            BINARY_EXT => {
+                let len = self.read_len()?;
+                let range = self.take(len)?;
+                self.input.slice(range).visit_str_or_bytes(visitor)
+            }
+            STRING_EXT => {
+                let len = usize::from(self.read_u16()?);
+                self.visit_elements(len, true, visitor)
+            }
+            NIL_EXT => self.visit_elements(0, false, visitor),
+            LIST_EXT => {
+                let len = self.read_len()?;
+                let value = self.visit_elements(len, false, visitor)?;
+                if self.read_u8()? != NIL_EXT {
+                    return Err(SerdeError::message("improper lists cannot be deserialized"));
+                }
+                Ok(value)
+            }
+            SMALL_TUPLE_EXT | LARGE_TUPLE_EXT => {
+                let len = if tag == SMALL_TUPLE_EXT {
+                    usize::from(self.read_u8()?)
+                } else {
+                    self.read_len()?
+                };
+                if len == 0 {
+                    visitor.visit_unit()
+                } else {
+                    self.visit_elements(len, false, visitor)
+                }
+            }
+            MAP_EXT => {
+                let len = self.read_len()?;
+                self.visit_entries(len, visitor)
+            }
+            _ => Err(unsupported_tag(tag)),
+        }
+    }
+}
+
+fn unsupported_tag(tag: u8) -> SerdeError {
+    let kind = match tag {
+        PID_EXT | NEW_PID_EXT => "pids",
+        PORT_EXT | NEW_PORT_EXT | V4_PORT_EXT => "ports",
+        REFERENCE_EXT | NEW_REFERENCE_EXT | NEWER_REFERENCE_EXT => "references",
+        FUN_EXT | NEW_FUN_EXT | EXPORT_EXT => "funs",
+        BIT_BINARY_EXT => "bitstrings",
+        ATOM_CACHE_REF => "atom cache references",
+        _ => return DecodeErrorKind::UnknownTag { tag }.into(),
+    };
+    SerdeError::message(format!("{kind} cannot be deserialized"))
+}
+
+fn arity_error(actual: usize, expected: usize) -> SerdeError {
+    SerdeError::message(format!(
+        "expected a tuple of {expected} elements, found {actual}"
+    ))
+}
+
+impl<'de, I: Input<'de>> de::Deserializer<'de> for &mut Deserializer<'_, I> {
+    type Error = SerdeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.located(|de| de.parse_any(visitor))
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.located(|de| {
+            let start = de.pos;
+            let code_point = match de.read_u8()? {
+                SMALL_INTEGER_EXT => u32::from(de.read_u8()?),
+                INTEGER_EXT => de.read_i32()? as u32,
+                _ => {
+                    de.pos = start;
+                    return de.deserialize_str(visitor);
+                }
+            };
+            match char::from_u32(code_point) {
+                Some(c) => visitor.visit_char(c),
+                None => Err(de::Error::invalid_value(
+                    Unexpected::Unsigned(u64::from(code_point)),
+                    &visitor,
+                )),
+            }
+        })
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.located(|de| {
+            let start = de.pos;
+            let tag = de.read_u8()?;
+            if let Some(name) = de.read_atom_name(tag)? {
+                return de.atom_text(name)?.visit(visitor);
+            }
+            match tag {
+                STRING_EXT => {
+                    let len = usize::from(de.read_u16()?);
+                    let range = de.take(len)?;
+                    de.input
+                        .slice(range)
+                        .text(true)
+                        .expect("Latin-1 text is always valid")
+                        .visit(visitor)
+                }
+                NIL_EXT => visitor.visit_borrowed_str(""),
+                LIST_EXT => match de.read_code_points()? {
+                    Some(text) => visitor.visit_string(text),
+                    None => {
+                        de.pos = start;
+                        de.parse_any(visitor)
+                    }
+                },
+                _ => {
+                    de.pos = start;
+                    de.parse_any(visitor)
+                }
+            }
+        })
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.located(|de| {
+            let start = de.pos;
+            let len = match de.read_u8()? {
+                BINARY_EXT => de.read_len()?,
+                STRING_EXT => usize::from(de.read_u16()?),
+                NIL_EXT => return visitor.visit_borrowed_bytes(&[]),
+                _ => {
+                    de.pos = start;
+                    return de.parse_any(visitor);
+                }
+            };
+            let range = de.take(len)?;
+            de.input.slice(range).visit_bytes(visitor)
+        })
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.located(|de| {
+            if de.read_none_atom()? {
+                visitor.visit_none()
+            } else {
+                visitor.visit_some(de)
+            }
+        })
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        self.located(|de| {
+            let start = de.pos;
+            let len = match de.read_u8()? {
+                SMALL_TUPLE_EXT => usize::from(de.read_u8()?),
+                LARGE_TUPLE_EXT => de.read_len()?,
+                _ => {
+                    de.pos = start;
+                    return visitor.visit_enum(UnitVariant { de });
+                }
+            };
+            if len == 0 {
+                return Err(SerdeError::message("an empty tuple is not an enum variant"));
+            }
+            de.enter()?;
+            let value = visitor.visit_enum(TupleVariant {
+                de: &mut *de,
+                remaining: len - 1,
+            })?;
+            de.leave();
+            Ok(value)
+        })
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let options = DecodeOptions::new().max_depth(MAX_DEPTH);
+        self.pos += codec::term_size(self.input.bytes(), self.pos, options)?;
+        visitor.visit_unit()
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 unit unit_struct
+        seq tuple tuple_struct map struct
+    }
+}
+
+/// Elements of a list or tuple.
+struct Elements<'a, 'o, I> {
+    de: &'a mut Deserializer<'o, I>,
+    remaining: usize,
+
+    /// Whether the elements are the bytes of a `STRING_EXT` rather than terms.
+    bytes: bool,
+}
+impl<'de, I: Input<'de>> Elements<'_, '_, I> {
+    fn end(&self, len: usize) -> Result<()> {
+        if self.remaining != 0 {
+            return Err(de::Error::invalid_length(len, &"fewer elements"));
+        }
+        Ok(())
+    }
+}
+
+impl<'de, I: Input<'de>> de::SeqAccess<'de> for Elements<'_, '_, I> {
+    type Error = SerdeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        if self.bytes {
+            let byte: u8 = self.de.located(|de| de.read_u8())?;
+            seed.deserialize(byte.into_deserializer()).map(Some)
+        } else {
+            seed.deserialize(&mut *self.de).map(Some)
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+/// Entries of a map.
+struct Entries<'a, 'o, I> {
+    de: &'a mut Deserializer<'o, I>,
+    remaining: usize,
+}
+
+impl<'de, I: Input<'de>> de::MapAccess<'de> for Entries<'_, '_, I> {
+    type Error = SerdeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        seed.deserialize(&mut *self.de)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.remaining)
+    }
+}
+
+/// Enum variant represented by an atom.
+struct UnitVariant<'a, 'o, I> {
+    de: &'a mut Deserializer<'o, I>,
+}
+
+impl<'de, I: Input<'de>> de::EnumAccess<'de> for UnitVariant<'_, '_, I> {
+    type Error = SerdeError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let variant = seed.deserialize(&mut *self.de)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, I: Input<'de>> de::VariantAccess<'de> for UnitVariant<'_, '_, I> {
+    type Error = SerdeError;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, _seed: T) -> Result<T::Value> {
+        Err(de::Error::invalid_type(
+            Unexpected::UnitVariant,
+            &"newtype variant",
+        ))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, _len: usize, _visitor: V) -> Result<V::Value> {
+        Err(de::Error::invalid_type(
+            Unexpected::UnitVariant,
+            &"tuple variant",
+        ))
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value> {
+        Err(de::Error::invalid_type(
+            Unexpected::UnitVariant,
+            &"struct variant",
+        ))
+    }
+}
+
+/// Enum variant represented by a tuple whose first element is the variant.
+struct TupleVariant<'a, 'o, I> {
+    de: &'a mut Deserializer<'o, I>,
+
+    /// The number of the elements following the variant.
+    remaining: usize,
+}
+
+impl<'de, I: Input<'de>> de::EnumAccess<'de> for TupleVariant<'_, '_, I> {
+    type Error = SerdeError;
+    type Variant = Self;
+
+    fn variant_seed<V: DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let variant = seed.deserialize(&mut *self.de)?;
+        Ok((variant, self))
+    }
+}
+
+impl<'de, I: Input<'de>> de::VariantAccess<'de> for TupleVariant<'_, '_, I> {
+    type Error = SerdeError;
+
+    fn unit_variant(self) -> Result<()> {
+        if self.remaining != 0 {
+            return Err(arity_error(self.remaining + 1, 1));
+        }
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        if self.remaining != 1 {
+            return Err(arity_error(self.remaining + 1, 2));
+        }
+        seed.deserialize(self.de)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value> {
+        if self.remaining != len {
+            return Err(arity_error(self.remaining + 1, len + 1));
+        }
+        let mut elements = Elements {
+            de: self.de,
+            remaining: len,
+            bytes: false,
+        };
+        let value = visitor.visit_seq(&mut elements)?;
+        elements.end(len)?;
+        Ok(value)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        if self.remaining != 1 {
+            return Err(arity_error(self.remaining + 1, 2));
+        }
+        de::Deserializer::deserialize_struct(self.de, "", fields, visitor)
+    }
+}
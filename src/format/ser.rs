@@ -0,0 +1,383 @@
+use super::*;
+use crate::{BigInteger, Binary, FixInteger, Float, List, Map, Tuple};
+use num_bigint::BigInt;
+use serde::ser::{self, Serialize};
+use std::collections::HashMap;
+
+/// Serializer building a [`Term`].
+pub(super) struct Serializer<'a> {
+    options: &'a SerdeOptions,
+}
+impl<'a> Serializer<'a> {
+    pub(super) fn new(options: &'a SerdeOptions) -> Self {
+        Self { options }
+    }
+
+    fn integer<T>(v: T) -> Term
+    where
+        T: Copy + TryInto<i32> + Into<BigInt>,
+    {
+        match v.try_into() {
+            Ok(value) => Term::from(FixInteger { value }),
+            Err(_) => Term::from(BigInteger { value: v.into() }),
+        }
+    }
+
+    fn float(v: f64) -> Result<Term, SerdeError> {
+        Float::try_from(v)
+            .map(Term::from)
+            .map_err(|_| SerdeError::message(format!("non-finite float {v} cannot be serialized")))
+    }
+
+    fn atom(name: &str) -> Term {
+        Term::from(Atom::from(name))
+    }
+}
+
+impl<'a> ser::Serializer for Serializer<'a> {
+    type Ok = Term;
+    type Error = SerdeError;
+    type SerializeSeq = SerializeElements<'a>;
+    type SerializeTuple = SerializeElements<'a>;
+    type SerializeTupleStruct = SerializeElements<'a>;
+    type SerializeTupleVariant = SerializeElements<'a>;
+    type SerializeMap = SerializeMap<'a>;
+    type SerializeStruct = SerializeMap<'a>;
+    type SerializeStructVariant = SerializeMap<'a>;
+
+    fn serialize_bool(self, v: bool) -> Result<Term, SerdeError> {
+        Ok(Self::atom(if v { "true" } else { "false" }))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Term, SerdeError> {
+        Ok(Self::integer(v))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Term, SerdeError> {
+        Ok(Self::integer(v))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Term, SerdeError> {
+        Ok(Self::integer(v))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Term, SerdeError> {
+        Ok(Self::integer(v))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Term, SerdeError> {
+        Ok(Self::integer(v))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Term, SerdeError> {
+        Ok(Self::integer(v))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Term, SerdeError> {
+        Ok(Self::integer(v))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Term, SerdeError> {
+        Ok(Self::integer(v))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Term, SerdeError> {
+        Ok(Self::integer(v))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Term, SerdeError> {
+        Ok(Self::integer(v))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Term, SerdeError> {
+        Self::float(f64::from(v))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Term, SerdeError> {
+        Self::float(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<Term, SerdeError> {
+        Ok(Self::integer(u32::from(v)))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Term, SerdeError> {
+        Ok(Term::from(Binary::from(v.as_bytes())))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Term, SerdeError> {
+        Ok(Term::from(Binary::from(v)))
+    }
+
+    fn serialize_none(self) -> Result<Term, SerdeError> {
+        Ok(Term::from(self.options.none_atom.clone()))
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Term, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Term, SerdeError> {
+        Ok(Term::from(Tuple::from(Vec::new())))
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Term, SerdeError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Term, SerdeError> {
+        Ok(Self::atom(variant))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Term, SerdeError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Term, SerdeError> {
+        let value = value.serialize(Serializer::new(self.options))?;
+        Ok(Term::from(Tuple::from(vec![Self::atom(variant), value])))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SerializeElements<'a>, SerdeError> {
+        Ok(SerializeElements::new(self.options, len, Container::List))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SerializeElements<'a>, SerdeError> {
+        Ok(SerializeElements::new(
+            self.options,
+            Some(len),
+            Container::Tuple,
+        ))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeElements<'a>, SerdeError> {
+        self.serialize_tuple(len)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeElements<'a>, SerdeError> {
+        let mut serializer = SerializeElements::new(self.options, Some(len + 1), Container::Tuple);
+        serializer.elements.push(Self::atom(variant));
+        Ok(serializer)
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<SerializeMap<'a>, SerdeError> {
+        Ok(SerializeMap::new(self.options, len, None))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SerializeMap<'a>, SerdeError> {
+        Ok(SerializeMap::new(self.options, Some(len), None))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<SerializeMap<'a>, SerdeError> {
+        Ok(SerializeMap::new(self.options, Some(len), Some(variant)))
+    }
+}
+
+enum Container {
+    List,
+    Tuple,
+}
+
+/// Serializer of sequences, tuples and tuple variants.
+pub(super) struct SerializeElements<'a> {
+    options: &'a SerdeOptions,
+    elements: Vec<Term>,
+    container: Container,
+}
+impl<'a> SerializeElements<'a> {
+    fn new(options: &'a SerdeOptions, len: Option<usize>, container: Container) -> Self {
+        Self {
+            options,
+            elements: Vec::with_capacity(len.unwrap_or(0)),
+            container,
+        }
+    }
+
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        let value = value.serialize(Serializer::new(self.options))?;
+        self.elements.push(value);
+        Ok(())
+    }
+
+    fn finish(self) -> Term {
+        match self.container {
+            Container::List => Term::from(List::from(self.elements)),
+            Container::Tuple => Term::from(Tuple::from(self.elements)),
+        }
+    }
+}
+
+impl ser::SerializeSeq for SerializeElements<'_> {
+    type Ok = Term;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Term, SerdeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTuple for SerializeElements<'_> {
+    type Ok = Term;
+    type Error = SerdeError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Term, SerdeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleStruct for SerializeElements<'_> {
+    type Ok = Term;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Term, SerdeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeTupleVariant for SerializeElements<'_> {
+    type Ok = Term;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Term, SerdeError> {
+        Ok(self.finish())
+    }
+}
+
+/// Serializer of maps, structs and struct variants.
+pub(super) struct SerializeMap<'a> {
+    options: &'a SerdeOptions,
+    map: HashMap<Term, Term>,
+    key: Option<Term>,
+    variant: Option<&'static str>,
+}
+impl<'a> SerializeMap<'a> {
+    fn new(options: &'a SerdeOptions, len: Option<usize>, variant: Option<&'static str>) -> Self {
+        Self {
+            options,
+            map: HashMap::with_capacity(len.unwrap_or(0)),
+            key: None,
+            variant,
+        }
+    }
+
+    fn insert<T: Serialize + ?Sized>(&mut self, key: Term, value: &T) -> Result<(), SerdeError> {
+        let value = value.serialize(Serializer::new(self.options))?;
+        self.map.insert(key, value);
+        Ok(())
+    }
+
+    fn finish(self) -> Term {
+        let map = Term::from(Map::from(self.map));
+        match self.variant {
+            None => map,
+            Some(variant) => Term::from(Tuple::from(vec![Serializer::atom(variant), map])),
+        }
+    }
+}
+
+impl ser::SerializeMap for SerializeMap<'_> {
+    type Ok = Term;
+    type Error = SerdeError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SerdeError> {
+        self.key = Some(key.serialize(Serializer::new(self.options))?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SerdeError> {
+        let key = self
+            .key
+            .take()
+            .ok_or_else(|| SerdeError::message("serialize_value called before serialize_key"))?;
+        self.insert(key, value)
+    }
+
+    fn end(self) -> Result<Term, SerdeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStruct for SerializeMap<'_> {
+    type Ok = Term;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        self.insert(Serializer::atom(key), value)
+    }
+
+    fn end(self) -> Result<Term, SerdeError> {
+        Ok(self.finish())
+    }
+}
+
+impl ser::SerializeStructVariant for SerializeMap<'_> {
+    type Ok = Term;
+    type Error = SerdeError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), SerdeError> {
+        self.insert(Serializer::atom(key), value)
+    }
+
+    fn end(self) -> Result<Term, SerdeError> {
+        Ok(self.finish())
+    }
+}
@@ -0,0 +1,515 @@
+//! Printing of terms in the Elixir syntax, following `Kernel.inspect/2`.
+use crate::print::{bit_binary_parts, column, newline, write_float};
+use crate::{Atom, ExternalFun, InternalFun, Map, Pid, Port, Reference, Term};
+use num_bigint::{BigInt, Sign};
+
+/// Options for [`Term::to_elixir_string`], after the options of `Kernel.inspect/2`.
+#[derive(Debug, Clone)]
+pub struct InspectOptions {
+    width: usize,
+    limit: usize,
+}
+impl InspectOptions {
+    /// Default options: a width of 80 characters and a limit of 50 items, as in Elixir.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the line width (`:width`).
+    ///
+    /// `usize::MAX` prints every term on a single line.
+    #[must_use]
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Sets the maximum number of items printed for lists, tuples, maps and bitstrings (`:limit`).
+    ///
+    /// The items after the limit are printed as `...`. As in Elixir, the limit is decreased
+    /// with each item, and the rest of it applies to the terms within that item.
+    /// `usize::MAX` prints all the items.
+    #[must_use]
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+}
+impl Default for InspectOptions {
+    fn default() -> Self {
+        Self {
+            width: 80,
+            limit: 50,
+        }
+    }
+}
+
+impl Term {
+    /// Prints the term as `Kernel.inspect/2` does in Elixir.
+    ///
+    /// - Atoms are printed as `:ok`, `true`, `nil` or `MyApp.User`.
+    /// - Maps with atom keys are printed as `%{key: 1}`,
+    ///   and maps with a `__struct__` key as structs (`%MyApp.User{name: "alice"}`).
+    /// - Lists of two-element tuples with atom keys are printed as keyword lists (`[a: 1]`),
+    ///   and lists of printable ASCII characters as charlists (`~c"abc"`).
+    /// - Binaries of printable UTF-8 are printed as strings (`"abc"`).
+    ///
+    /// Map entries are printed in term order of their keys.
+    /// Pids, ports and references are printed with their node name (`#PID<node@host.1.2>`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use eetf::{InspectOptions, Term};
+    ///
+    /// let term: Term = r#"{ok, #{name => <<"alice">>, roles => [admin]}, [{timeout, 5}], "abc"}"#
+    ///     .parse()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     term.to_elixir_string(&InspectOptions::new()),
+    ///     r#"{:ok, %{name: "alice", roles: [:admin]}, [timeout: 5], ~c"abc"}"#
+    /// );
+    /// assert_eq!(
+    ///     term.to_elixir_string(&InspectOptions::new().width(40)),
+    ///     "{:ok,\n %{name: \"alice\", roles: [:admin]},\n [timeout: 5],\n ~c\"abc\"}"
+    /// );
+    /// assert_eq!(
+    ///     term.to_elixir_string(&InspectOptions::new().limit(3)),
+    ///     r#"{:ok, %{name: "alice", ...}, [...], ...}"#
+    /// );
+    /// ```
+    pub fn to_elixir_string(&self, options: &InspectOptions) -> String {
+        let doc = Doc::term(self, options.limit);
+        let mut out = String::new();
+        doc.render(&mut out, 0, options.width, 0);
+        out
+    }
+}
+
+/// Document which is printed on a single line if it fits, or broken into lines otherwise.
+enum Doc {
+    Text(String),
+
+    /// Documents printed one after another, e.g. a keyword and its value.
+    Concat(Vec<Doc>),
+
+    /// Items separated by commas.
+    ///
+    /// When broken, the items of a `block` are put on their own lines indented by two spaces,
+    /// with the closing text on its own line. The items of other containers are indented by
+    /// one space, and fill the lines if they are all simple, as in Elixir.
+    Container {
+        open: String,
+        items: Vec<Doc>,
+        close: &'static str,
+        block: bool,
+    },
+}
+impl Doc {
+    fn text(text: impl Into<String>) -> Self {
+        Self::Text(text.into())
+    }
+
+    fn term(term: &Term, limit: usize) -> Self {
+        match term {
+            Term::Atom(x) => Self::text(atom(&x.name)),
+            Term::FixInteger(x) => Self::text(x.value.to_string()),
+            Term::BigInteger(x) => Self::text(x.value.to_string()),
+            Term::Float(x) => {
+                let mut text = String::new();
+                write_float(&mut text, x.value);
+                Self::text(text)
+            }
+            Term::Pid(x) => Self::text(pid(x)),
+            Term::Port(x) => Self::text(port(x)),
+            Term::Reference(x) => Self::text(reference(x)),
+            Term::ExternalFun(x) => Self::text(external_fun(x)),
+            Term::InternalFun(x) => Self::text(internal_fun(x)),
+            Term::Binary(x) => Self::bitstring(&x.bytes, None, limit),
+            Term::BitBinary(x) => {
+                let (bytes, tail) = bit_binary_parts(&x.bytes, x.tail_bits_size);
+                Self::bitstring(bytes, tail, limit)
+            }
+            Term::ByteList(x) => {
+                let elements: Vec<_> = x
+                    .bytes
+                    .iter()
+                    .map(|&b| Term::from(crate::FixInteger::from(b)))
+                    .collect();
+                Self::list(&elements, None, limit)
+            }
+            Term::List(x) => Self::list(&x.elements, None, limit),
+            Term::ImproperList(x) => Self::list(&x.elements, Some(&x.last), limit),
+            Term::Tuple(x) => Self::container("{", &x.elements, "}", false, limit, Self::term),
+            Term::Map(x) => Self::map(x, limit),
+        }
+    }
+
+    /// Makes a container of `elements`, replacing those beyond `limit` with `...`.
+    fn container<T>(
+        open: impl Into<String>,
+        elements: &[T],
+        close: &'static str,
+        block: bool,
+        limit: usize,
+        item: impl Fn(&T, usize) -> Doc,
+    ) -> Self {
+        let mut items: Vec<_> = elements
+            .iter()
+            .take(limit)
+            .enumerate()
+            .map(|(i, x)| item(x, limit - i - 1))
+            .collect();
+        if elements.len() > limit {
+            items.push(Self::text("..."));
+        }
+        Self::Container {
+            open: open.into(),
+            items,
+            close,
+            block,
+        }
+    }
+
+    fn bitstring(bytes: &[u8], tail: Option<(u8, u8)>, limit: usize) -> Self {
+        if tail.is_none()
+            && let Ok(text) = std::str::from_utf8(bytes)
+            && text.chars().all(is_printable)
+        {
+            return Self::text(quote(text));
+        }
+        let mut items: Vec<_> = bytes.iter().map(|b| b.to_string()).collect();
+        if let Some((value, bits)) = tail {
+            items.push(format!("{value}::size({bits})"));
+        }
+        Self::container("<<", &items, ">>", false, limit, |x, _| Self::text(x))
+    }
+
+    fn list(elements: &[Term], tail: Option<&Term>, limit: usize) -> Self {
+        if tail.is_none()
+            && !elements.is_empty()
+            && let Some(text) = charlist(elements)
+        {
+            return Self::text(format!("~c{}", quote(&text)));
+        }
+        if tail.is_none()
+            && !elements.is_empty()
+            && let Some(entries) = keywords(elements)
+        {
+            return Self::container("[", &entries, "]", false, limit, |(k, v), limit| {
+                Self::Concat(vec![Self::text(keyword(k)), Self::term(v, limit)])
+            });
+        }
+        let mut doc = Self::container("[", elements, "]", false, limit, Self::term);
+        if let (Some(tail), Self::Container { items, .. }) = (tail, &mut doc)
+            && elements.len() <= limit
+        {
+            let last = items.pop().expect("non-empty improper list");
+            items.push(Self::Concat(vec![
+                last,
+                Self::text(" | "),
+                Self::term(tail, limit.saturating_sub(elements.len())),
+            ]));
+        }
+        doc
+    }
+
+    fn map(map: &Map, limit: usize) -> Self {
+        let mut entries = crate::order::sorted_entries(map);
+        let module = entries.iter().find_map(|(k, v)| match (k, v) {
+            (Term::Atom(k), Term::Atom(v)) if k.name == "__struct__" => alias(&v.name),
+            _ => None,
+        });
+        let all_atoms =
+            |entries: &[(&Term, &Term)]| entries.iter().all(|(k, _)| matches!(k, Term::Atom(_)));
+        if let Some(module) = module {
+            entries.retain(|(k, _)| !matches!(k, Term::Atom(k) if k.name == "__struct__"));
+            if all_atoms(&entries) {
+                let open = format!("%{module}{{");
+                return Self::container(open, &entries, "}", true, limit, keyword_entry);
+            }
+            entries = crate::order::sorted_entries(map);
+        }
+        if all_atoms(&entries) {
+            Self::container("%{", &entries, "}", true, limit, keyword_entry)
+        } else {
+            Self::container("%{", &entries, "}", true, limit, |(k, v), limit| {
+                Self::Concat(vec![
+                    Self::term(k, limit),
+                    Self::text(" => "),
+                    Self::term(v, limit),
+                ])
+            })
+        }
+    }
+
+    fn flat(&self, out: &mut String) {
+        match self {
+            Self::Text(text) => out.push_str(text),
+            Self::Concat(docs) => docs.iter().for_each(|doc| doc.flat(out)),
+            Self::Container {
+                open, items, close, ..
+            } => {
+                out.push_str(open);
+                for (i, item) in items.iter().enumerate() {
+                    if i != 0 {
+                        out.push_str(", ");
+                    }
+                    item.flat(out);
+                }
+                out.push_str(close);
+            }
+        }
+    }
+
+    /// Returns whether the document is printed without breaking, like an atom or `key: 1`.
+    fn is_simple(&self) -> bool {
+        match self {
+            Self::Text(_) => true,
+            Self::Concat(docs) => docs.iter().all(Self::is_simple),
+            Self::Container { items, .. } => items.is_empty(),
+        }
+    }
+
+    fn flat_len(&self) -> usize {
+        let mut out = String::new();
+        self.flat(&mut out);
+        out.chars().count()
+    }
+
+    /// Writes the document, breaking it if it does not fit in `width`.
+    ///
+    /// `indent` is the indentation of the current line,
+    /// and `trailing` the number of characters which will follow the document on its last line.
+    fn render(&self, out: &mut String, indent: usize, width: usize, trailing: usize) {
+        if column(out) + self.flat_len() + trailing <= width {
+            self.flat(out);
+            return;
+        }
+        match self {
+            Self::Text(text) => out.push_str(text),
+            Self::Concat(docs) => {
+                for (i, doc) in docs.iter().enumerate() {
+                    let rest: usize = docs[i + 1..].iter().map(Self::flat_len).sum();
+                    doc.render(out, indent, width, rest + trailing);
+                }
+            }
+            Self::Container {
+                open,
+                items,
+                close,
+                block: true,
+            } if !items.is_empty() => {
+                out.push_str(open);
+                for (i, item) in items.iter().enumerate() {
+                    if i != 0 {
+                        out.push(',');
+                    }
+                    newline(out, indent + 2);
+                    let after = usize::from(i + 1 != items.len());
+                    item.render(out, indent + 2, width, after);
+                }
+                newline(out, indent);
+                out.push_str(close);
+            }
+            Self::Container {
+                open, items, close, ..
+            } => {
+                out.push_str(open);
+                let indent = indent + 1;
+                let fill = items.iter().all(Self::is_simple);
+                for (i, item) in items.iter().enumerate() {
+                    let after = if i + 1 == items.len() {
+                        close.len() + trailing
+                    } else {
+                        1
+                    };
+                    if i != 0 {
+                        out.push(',');
+                        if fill && column(out) + 1 + item.flat_len() + after <= width {
+                            out.push(' ');
+                        } else {
+                            newline(out, indent);
+                        }
+                    }
+                    item.render(out, indent, width, after);
+                }
+                out.push_str(close);
+            }
+        }
+    }
+}
+
+fn keyword_entry((k, v): &(&Term, &Term), limit: usize) -> Doc {
+    let Term::Atom(k) = k else {
+        unreachable!("checked to be an atom")
+    };
+    Doc::Concat(vec![Doc::text(keyword(k)), Doc::term(v, limit)])
+}
+
+/// Returns the entries of a keyword list: a list of `{atom, value}`.
+fn keywords(elements: &[Term]) -> Option<Vec<(&Atom, &Term)>> {
+    elements
+        .iter()
+        .map(|x| match x {
+            Term::Tuple(x) => match x.elements.as_slice() {
+                [Term::Atom(k), v] => Some((k, v)),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns the text of a list of printable ASCII characters (`List.ascii_printable?/1`).
+fn charlist(elements: &[Term]) -> Option<String> {
+    elements
+        .iter()
+        .map(|x| match x {
+            Term::FixInteger(x) => u8::try_from(x.value)
+                .ok()
+                .filter(|&c| matches!(c, 7..=13 | 27 | 32..=126))
+                .map(char::from),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns whether `c` is printable in a string (`String.printable?/1`).
+fn is_printable(c: char) -> bool {
+    matches!(c,
+        '\u{7}'..='\u{d}' | '\u{1b}' | ' '..='~' | '\u{a0}'..='\u{d7ff}'
+        | '\u{e000}'..='\u{fffd}' | '\u{10000}'..='\u{10ffff}')
+}
+
+/// Quotes `text` as an Elixir string.
+fn quote(text: &str) -> String {
+    let mut out = String::from('"');
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '#' if chars.peek() == Some(&'{') => out.push_str("\\#"),
+            '\u{7}' => out.push_str("\\a"),
+            '\u{8}' => out.push_str("\\b"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\u{b}' => out.push_str("\\v"),
+            '\u{c}' => out.push_str("\\f"),
+            '\r' => out.push_str("\\r"),
+            '\u{1b}' => out.push_str("\\e"),
+            c if c.is_control() => out.push_str(&format!("\\x{{{:X}}}", u32::from(c))),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Returns the alias of a module atom: `Foo.Bar` for `Elixir.Foo.Bar`.
+fn alias(name: &str) -> Option<&str> {
+    let alias = name.strip_prefix("Elixir.")?;
+    alias
+        .split('.')
+        .all(|segment| {
+            let mut chars = segment.chars();
+            chars.next().is_some_and(|c| c.is_ascii_uppercase())
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        })
+        .then_some(alias)
+}
+
+/// Returns whether an atom can be written without quotes: `foo`, `foo?`, `Foo`, `foo@bar`.
+fn is_identifier(name: &str) -> bool {
+    let name = name
+        .strip_suffix('?')
+        .or_else(|| name.strip_suffix('!'))
+        .unwrap_or(name);
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '@')
+}
+
+const OPERATORS: &[&str] = &[
+    "+", "-", "*", "/", "==", "!=", "===", "!==", "<", ">", "<=", ">=", "=~", "++", "--", "<>",
+    "..", "!", "^", "&&", "||", "&&&", "|||", "<<<", ">>>", "~~~", "|>", "<~", "~>", "<~>", "@",
+    "&", "\\\\",
+];
+
+/// Writes an atom as an Elixir literal: `:ok`, `:"hello world"`, `true`, `nil` or `MyApp.User`.
+fn atom(name: &str) -> String {
+    if matches!(name, "true" | "false" | "nil") {
+        name.to_owned()
+    } else if let Some(alias) = alias(name) {
+        alias.to_owned()
+    } else if is_identifier(name) || OPERATORS.contains(&name) {
+        format!(":{name}")
+    } else {
+        format!(":{}", quote(name))
+    }
+}
+
+/// Writes an atom as the key of a keyword list or a map: `key: ` or `"hello world": `.
+fn keyword(key: &Atom) -> String {
+    if is_identifier(&key.name) {
+        format!("{}: ", key.name)
+    } else {
+        format!("{}: ", quote(&key.name))
+    }
+}
+
+/// Writes a module of a fun: `Enum` or `:lists`.
+fn module(name: &Atom) -> String {
+    match alias(&name.name) {
+        Some(alias) => alias.to_owned(),
+        None => atom(&name.name),
+    }
+}
+
+fn pid(pid: &Pid) -> String {
+    format!("#PID<{}.{}.{}>", pid.node.name, pid.id, pid.serial)
+}
+
+fn port(port: &Port) -> String {
+    format!("#Port<{}.{}>", port.node.name, port.id)
+}
+
+fn reference(reference: &Reference) -> String {
+    let mut out = format!("#Reference<{}", reference.node.name);
+    for n in &reference.id {
+        out.push_str(&format!(".{n}"));
+    }
+    out.push('>');
+    out
+}
+
+fn external_fun(fun: &ExternalFun) -> String {
+    let function = if is_identifier(&fun.function.name) {
+        fun.function.name.clone()
+    } else {
+        quote(&fun.function.name)
+    };
+    format!("&{}.{function}/{}", module(&fun.module), fun.arity)
+}
+
+fn internal_fun(fun: &InternalFun) -> String {
+    match fun {
+        InternalFun::Old {
+            module: m,
+            index,
+            uniq,
+            ..
+        } => format!("#Function<{index}.{uniq} in {}>", module(m)),
+        InternalFun::New {
+            module: m,
+            arity,
+            index,
+            uniq,
+            ..
+        } => {
+            let uniq = BigInt::from_bytes_be(Sign::Plus, uniq);
+            format!("#Function<{index}.{uniq}/{arity} in {}>", module(m))
+        }
+    }
+}
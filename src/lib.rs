@@ -36,15 +36,84 @@ use std::io;
 
 mod codec;
 pub mod convert;
+#[cfg(feature = "serde")]
+mod format;
+mod inspect;
+mod order;
+mod parse;
 pub mod pattern;
+mod print;
+mod term_ref;
 
+pub use crate::codec::AtomCache;
 pub use crate::codec::DecodeError;
+pub use crate::codec::DecodeErrorKind;
+pub use crate::codec::DecodeLimit;
+pub use crate::codec::DecodeOptions;
 pub use crate::codec::DecodeResult;
+pub use crate::codec::DecodeStatus;
+pub use crate::codec::Decoder;
+pub use crate::codec::DistributionFlags;
+pub use crate::codec::DistributionMessage;
 pub use crate::codec::EncodeError;
+pub use crate::codec::EncodeOptions;
 pub use crate::codec::EncodeResult;
+pub use crate::codec::Encoder;
+#[cfg(feature = "tokio-util")]
+pub use crate::codec::EtfFramedCodec;
+#[cfg(feature = "tokio-util")]
+pub use crate::codec::FramedCodecError;
+pub use crate::codec::IncrementalDecoder;
+pub use crate::codec::PathSegment;
+pub use crate::codec::TermReader;
+pub use crate::codec::TermWriter;
+pub use crate::convert::{ConvertError, ConvertErrorKind, FromTerm, ToTerm};
+#[cfg(feature = "serde")]
+pub use crate::format::{
+    SerdeError, SerdeOptions, from_slice, from_slice_with_options, to_term, to_term_with_options,
+    to_vec, to_vec_with_options,
+};
+pub use crate::inspect::InspectOptions;
+pub use crate::parse::{ParseError, ParseErrorKind};
+pub use crate::print::{PrintOptions, PrintStyle};
+pub use crate::term_ref::TermRef;
+#[cfg(feature = "derive")]
+pub use eetf_derive::{FromTerm, ToTerm};
 
 /// Term.
+///
+/// With the `serde` feature, `Term` and its variant types implement `Serialize` and `Deserialize`
+/// for embedding terms in other serde formats.
+/// The representation is lossless: a term is an externally tagged enum named after its variant
+/// (e.g. `{"Atom": "foo"}` in JSON), and a deserialized term is identical to the serialized one.
+/// Note that `eetf::to_vec` also uses this representation rather than encoding the term as is.
+///
+/// Terms are ordered in Erlang term order:
+/// `number < atom < reference < fun < port < pid < tuple < map < nil < list < bit string`.
+/// Numbers are compared by value, and map keys in map key order, where all integers are
+/// ordered before all floats. `ByteList`, `List` and `ImproperList` are all compared as lists.
+/// So that the order is consistent with `Eq`, terms which are equal in term order but have
+/// different representations, such as `1` and `1.0`, or `ByteList` `"ab"` and `List` `[97,98]`,
+/// are ordered by their representation: `1 < 1.0`.
+///
+/// ```
+/// use eetf::{Atom, ByteList, FixInteger, Float, List, Term};
+///
+/// let mut terms = vec![
+///     Term::from(Atom::from("ok")),
+///     Term::from(ByteList::from("ab")),
+///     Term::from(Float::try_from(1.0).unwrap()),
+///     Term::from(List::nil()),
+///     Term::from(FixInteger::from(1)),
+/// ];
+/// terms.sort();
+/// assert_eq!(
+///     terms.iter().map(|x| x.to_string()).collect::<Vec<_>>(),
+///     ["1", "1.0", "ok", "[]", "\"ab\""]
+/// );
+/// ```
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Term {
     Atom(Atom),
     FixInteger(FixInteger),
@@ -69,11 +138,114 @@ impl Term {
         codec::Decoder::new(reader).decode()
     }
 
+    /// Decodes a term with the given options.
+    pub fn decode_with_options<R: io::Read>(reader: R, options: DecodeOptions) -> DecodeResult {
+        codec::Decoder::with_options(reader, options).decode()
+    }
+
+    /// Decodes a term at the beginning of `bytes`.
+    ///
+    /// Returns the term and the number of bytes it occupies.
+    pub fn decode_from_slice(bytes: &[u8]) -> Result<(Term, usize), DecodeError> {
+        codec::Decoder::new(io::Cursor::new(bytes)).decode_from_slice()
+    }
+
+    /// Decodes a term occupying the whole of `bytes`.
+    ///
+    /// Returns [`DecodeErrorKind::TrailingBytes`] if any bytes follow the term.
+    pub fn decode_exact(bytes: &[u8]) -> DecodeResult {
+        codec::Decoder::new(io::Cursor::new(bytes)).decode_exact()
+    }
+
     /// Encodes the term.
     pub fn encode<W: io::Write>(&self, writer: W) -> EncodeResult {
         codec::Encoder::new(writer).encode(self)
     }
 
+    /// Encodes the term with the given options.
+    pub fn encode_with_options<W: io::Write>(
+        &self,
+        writer: W,
+        options: EncodeOptions,
+    ) -> EncodeResult {
+        codec::Encoder::with_options(writer, options).encode(self)
+    }
+
+    /// Decodes a term from an asynchronous reader.
+    ///
+    /// Exactly one term is read, including a compressed one, and the bytes following it are left unread.
+    /// As the reader is read in small chunks, wrapping it in a [`tokio::io::BufReader`] is recommended.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use eetf::{Atom, Term};
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let mut reader = &[131, 119, 3, 102, 111, 111, 1, 2][..];
+    /// let term = Term::decode_async(&mut reader).await.unwrap();
+    /// assert_eq!(term, Term::from(Atom::from("foo")));
+    /// assert_eq!(reader, [1, 2]);
+    /// # }
+    /// ```
+    #[cfg(feature = "tokio")]
+    pub async fn decode_async<R: tokio::io::AsyncRead + Unpin>(reader: R) -> DecodeResult {
+        codec::async_io::decode(reader, DecodeOptions::new()).await
+    }
+
+    /// Decodes a term from an asynchronous reader with the given options.
+    #[cfg(feature = "tokio")]
+    pub async fn decode_async_with_options<R: tokio::io::AsyncRead + Unpin>(
+        reader: R,
+        options: DecodeOptions,
+    ) -> DecodeResult {
+        codec::async_io::decode(reader, options).await
+    }
+
+    /// Encodes the term to an asynchronous writer.
+    ///
+    /// The term is written with a single `write_all` call. The writer is not flushed.
+    #[cfg(feature = "tokio")]
+    pub async fn encode_async<W: tokio::io::AsyncWrite + Unpin>(&self, writer: W) -> EncodeResult {
+        codec::async_io::encode(self, writer, EncodeOptions::new()).await
+    }
+
+    /// Encodes the term to an asynchronous writer with the given options.
+    #[cfg(feature = "tokio")]
+    pub async fn encode_async_with_options<W: tokio::io::AsyncWrite + Unpin>(
+        &self,
+        writer: W,
+        options: EncodeOptions,
+    ) -> EncodeResult {
+        codec::async_io::encode(self, writer, options).await
+    }
+
+    /// Returns the size of the encoded term in bytes, including the version byte,
+    /// without allocating a buffer for it.
+    ///
+    /// This is equivalent to `erlang:external_size/1`.
+    /// An error is returned if the term cannot be encoded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use eetf::{Atom, Term};
+    ///
+    /// let term = Term::from(Atom::from("foo"));
+    /// assert_eq!(term.encoded_len().unwrap(), 6);
+    /// ```
+    pub fn encoded_len(&self) -> Result<usize, EncodeError> {
+        codec::encoded_len(self, EncodeOptions::new())
+    }
+
+    /// Returns the size of the term encoded with the given options in bytes.
+    ///
+    /// Note that if compression is enabled, the term is actually compressed to know its size.
+    pub fn encoded_len_with_options(&self, options: EncodeOptions) -> Result<usize, EncodeError> {
+        codec::encoded_len(self, options)
+    }
+
     pub fn as_match<'a, P>(&'a self, pattern: P) -> pattern::Result<'a, P::Output>
     where
         P: pattern::Pattern<'a>,
@@ -83,24 +255,17 @@ impl Term {
 }
 impl fmt::Display for Term {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            Term::Atom(ref x) => x.fmt(f),
-            Term::FixInteger(ref x) => x.fmt(f),
-            Term::BigInteger(ref x) => x.fmt(f),
-            Term::Float(ref x) => x.fmt(f),
-            Term::Pid(ref x) => x.fmt(f),
-            Term::Port(ref x) => x.fmt(f),
-            Term::Reference(ref x) => x.fmt(f),
-            Term::ExternalFun(ref x) => x.fmt(f),
-            Term::InternalFun(ref x) => x.fmt(f),
-            Term::Binary(ref x) => x.fmt(f),
-            Term::BitBinary(ref x) => x.fmt(f),
-            Term::ByteList(ref x) => x.fmt(f),
-            Term::List(ref x) => x.fmt(f),
-            Term::ImproperList(ref x) => x.fmt(f),
-            Term::Tuple(ref x) => x.fmt(f),
-            Term::Map(ref x) => x.fmt(f),
-        }
+        print::display_term(f, self)
+    }
+}
+impl PartialOrd for Term {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for Term {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        order::compare(self, other, false).then_with(|| order::compare_representations(self, other))
     }
 }
 impl From<Atom> for Term {
@@ -193,17 +358,15 @@ impl From<Map> for Term {
 
 /// Atom.
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Atom {
     /// The name of the atom.
     pub name: String,
 }
 impl fmt::Display for Atom {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(
-            f,
-            "'{}'",
-            self.name.replace('\\', "\\\\").replace('\'', "\\'")
-        )
+        print::display(f, |p, out| p.atom(out, &self.name))
     }
 }
 impl<'a> From<&'a str> for Atom {
@@ -221,6 +384,8 @@ impl From<String> for Atom {
 
 /// Fixed width integer.
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct FixInteger {
     /// The value of the integer
     pub value: i32,
@@ -266,6 +431,8 @@ impl From<i32> for FixInteger {
 
 /// Multiple precision integer.
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct BigInteger {
     /// The value of the integer
     pub value: BigInt,
@@ -355,13 +522,15 @@ impl From<&FixInteger> for BigInteger {
 
 /// Floating point number
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "f64", into = "f64"))]
 pub struct Float {
     /// The value of the number
     pub value: f64,
 }
 impl fmt::Display for Float {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.value)
+        print::display(f, |p, out| p.float(out, self.value))
     }
 }
 impl TryFrom<f32> for Float {
@@ -373,7 +542,7 @@ impl TryFrom<f32> for Float {
                 value: f64::from(value),
             })
         } else {
-            Err(DecodeError::NonFiniteFloat)
+            Err(DecodeErrorKind::NonFiniteFloat.into())
         }
     }
 }
@@ -384,10 +553,15 @@ impl TryFrom<f64> for Float {
         if value.is_finite() {
             Ok(Float { value })
         } else {
-            Err(DecodeError::NonFiniteFloat)
+            Err(DecodeErrorKind::NonFiniteFloat.into())
         }
     }
 }
+impl From<Float> for f64 {
+    fn from(x: Float) -> Self {
+        x.value
+    }
+}
 impl Eq for Float {}
 impl std::hash::Hash for Float {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
@@ -404,6 +578,7 @@ impl std::hash::Hash for Float {
 
 /// Process Identifier.
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pid {
     pub node: Atom,
     pub id: u32,
@@ -425,7 +600,7 @@ impl Pid {
 }
 impl fmt::Display for Pid {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "<{}.{}.{}>", self.node, self.id, self.serial)
+        print::display(f, |p, out| p.pid(out, self))
     }
 }
 // TODO: delete
@@ -442,6 +617,7 @@ impl<'a> From<(&'a str, u32, u32)> for Pid {
 
 /// Port.
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Port {
     pub node: Atom,
     pub id: u64,
@@ -449,7 +625,7 @@ pub struct Port {
 }
 impl fmt::Display for Port {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "#Port<{}.{}>", self.node, self.id)
+        print::display(f, |p, out| p.port(out, self))
     }
 }
 impl<'a> From<(&'a str, u32)> for Port {
@@ -464,6 +640,7 @@ impl<'a> From<(&'a str, u32)> for Port {
 
 /// Reference.
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Reference {
     pub node: Atom,
     pub id: Vec<u32>,
@@ -471,11 +648,7 @@ pub struct Reference {
 }
 impl fmt::Display for Reference {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "#Ref<{}", self.node)?;
-        for n in &self.id {
-            write!(f, ".{}", n)?;
-        }
-        write!(f, ">")
+        print::display(f, |p, out| p.reference(out, self))
     }
 }
 impl<'a> From<(&'a str, u32)> for Reference {
@@ -499,6 +672,7 @@ impl<'a> From<(&'a str, Vec<u32>)> for Reference {
 
 /// External Function.
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ExternalFun {
     pub module: Atom,
     pub function: Atom,
@@ -506,7 +680,7 @@ pub struct ExternalFun {
 }
 impl fmt::Display for ExternalFun {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "fun {}:{}/{}", self.module, self.function, self.arity)
+        print::display(f, |p, out| p.external_fun(out, self))
     }
 }
 impl<'a, 'b> From<(&'a str, &'b str, u8)> for ExternalFun {
@@ -521,6 +695,7 @@ impl<'a, 'b> From<(&'a str, &'b str, u8)> for ExternalFun {
 
 /// Internal Function.
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum InternalFun {
     /// Old representation.
     Old {
@@ -544,43 +719,21 @@ pub enum InternalFun {
 }
 impl fmt::Display for InternalFun {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            InternalFun::Old {
-                ref module,
-                index,
-                uniq,
-                ..
-            } => write!(f, "#Fun<{}.{}.{}>", module, index, uniq),
-            InternalFun::New {
-                ref module,
-                index,
-                uniq,
-                ..
-            } => {
-                use num_bigint::Sign;
-                let uniq = BigInt::from_bytes_be(Sign::Plus, &uniq);
-                write!(f, "#Fun<{}.{}.{}>", module, index, uniq)
-            }
-        }
+        print::display(f, |p, out| p.internal_fun(out, self))
     }
 }
 
 /// Binary.
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Binary {
+    #[cfg_attr(feature = "serde", serde(with = "serde_bytes"))]
     pub bytes: Vec<u8>,
 }
 impl fmt::Display for Binary {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "<<")?;
-        for (i, b) in self.bytes.iter().enumerate() {
-            if i != 0 {
-                write!(f, ",")?;
-            }
-            write!(f, "{}", b)?;
-        }
-        write!(f, ">>")?;
-        Ok(())
+        print::display(f, |p, out| p.bits(out, &self.bytes, None))
     }
 }
 impl<'a> From<&'a [u8]> for Binary {
@@ -598,28 +751,16 @@ impl From<Vec<u8>> for Binary {
 
 /// Bit string.
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BitBinary {
+    #[cfg_attr(feature = "serde", serde(with = "serde_bytes"))]
     pub bytes: Vec<u8>,
     pub tail_bits_size: u8,
 }
 impl fmt::Display for BitBinary {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "<<")?;
-        for (i, b) in self.bytes.iter().enumerate() {
-            if i == self.bytes.len() - 1 && self.tail_bits_size == 0 {
-                break;
-            }
-            if i != 0 {
-                write!(f, ",")?;
-            }
-            if i == self.bytes.len() - 1 && self.tail_bits_size < 8 {
-                write!(f, "{}:{}", b, self.tail_bits_size)?;
-            } else {
-                write!(f, "{}", b)?;
-            }
-        }
-        write!(f, ">>")?;
-        Ok(())
+        let (bytes, tail) = print::bit_binary_parts(&self.bytes, self.tail_bits_size);
+        print::display(f, |p, out| p.bits(out, bytes, tail))
     }
 }
 impl From<Binary> for BitBinary {
@@ -647,20 +788,15 @@ impl From<(Vec<u8>, u8)> for BitBinary {
 /// See: https://erlang.org/doc/apps/erts/erl_ext_dist.html#STRING_EXT
 ///
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct ByteList {
+    #[cfg_attr(feature = "serde", serde(with = "serde_bytes"))]
     pub bytes: Vec<u8>,
 }
 impl fmt::Display for ByteList {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "[")?;
-        for (i, b) in self.bytes.iter().enumerate() {
-            if i != 0 {
-                write!(f, ",")?;
-            }
-            write!(f, "{}", b)?;
-        }
-        write!(f, "]")?;
-        Ok(())
+        print::display(f, |p, out| p.byte_list(out, &self.bytes))
     }
 }
 impl From<String> for ByteList {
@@ -692,6 +828,8 @@ impl<const N: usize> From<&[u8; N]> for ByteList {
 
 /// List.
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct List {
     pub elements: Vec<Term>,
 }
@@ -710,15 +848,7 @@ impl List {
 }
 impl fmt::Display for List {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "[")?;
-        for (i, x) in self.elements.iter().enumerate() {
-            if i != 0 {
-                write!(f, ",")?;
-            }
-            write!(f, "{}", x)?;
-        }
-        write!(f, "]")?;
-        Ok(())
+        print::display(f, |p, out| p.list(out, &self.elements, None))
     }
 }
 impl From<Vec<Term>> for List {
@@ -743,22 +873,14 @@ impl From<ByteList> for List {
 
 /// Improper list.
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ImproperList {
     pub elements: Vec<Term>,
     pub last: Box<Term>,
 }
 impl fmt::Display for ImproperList {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "[")?;
-        for (i, x) in self.elements.iter().enumerate() {
-            if i != 0 {
-                write!(f, ",")?;
-            }
-            write!(f, "{}", x)?;
-        }
-        write!(f, "|{}", self.last)?;
-        write!(f, "]")?;
-        Ok(())
+        print::display(f, |p, out| p.list(out, &self.elements, Some(&self.last)))
     }
 }
 impl From<(Vec<Term>, Term)> for ImproperList {
@@ -772,6 +894,8 @@ impl From<(Vec<Term>, Term)> for ImproperList {
 
 /// Tuple.
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Tuple {
     pub elements: Vec<Term>,
 }
@@ -784,15 +908,7 @@ impl Tuple {
 }
 impl fmt::Display for Tuple {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{{")?;
-        for (i, x) in self.elements.iter().enumerate() {
-            if i != 0 {
-                write!(f, ",")?;
-            }
-            write!(f, "{}", x)?;
-        }
-        write!(f, "}}")?;
-        Ok(())
+        print::display(f, |p, out| p.tuple(out, &self.elements))
     }
 }
 impl From<Vec<Term>> for Tuple {
@@ -803,20 +919,14 @@ impl From<Vec<Term>> for Tuple {
 
 /// Map.
 #[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct Map {
     pub map: HashMap<Term, Term>,
 }
 impl fmt::Display for Map {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "#{{")?;
-        for (i, (k, v)) in self.map.iter().enumerate() {
-            if i != 0 {
-                write!(f, ",")?;
-            }
-            write!(f, "{}=>{}", k, v)?;
-        }
-        write!(f, "}}")?;
-        Ok(())
+        print::display(f, |p, out| p.map(out, self))
     }
 }
 impl Hash for Map {
@@ -0,0 +1,421 @@
+//! Erlang term order.
+//!
+//! See: https://www.erlang.org/doc/system/expressions.html#term-comparisons
+use super::*;
+use num_traits::FromPrimitive;
+use std::cmp::Ordering;
+
+/// Compares terms in Erlang term order:
+/// `number < atom < reference < fun < port < pid < tuple < map < nil < list < bit string`.
+///
+/// If `exact` is `true`, numbers are compared in map key order:
+/// all integers are ordered before all floats, whatever their values.
+pub(crate) fn compare(a: &Term, b: &Term, exact: bool) -> Ordering {
+    let class = class(a).cmp(&class(b));
+    if class != Ordering::Equal {
+        return class;
+    }
+    match (a, b) {
+        (Term::Atom(a), Term::Atom(b)) => a.name.cmp(&b.name),
+        (Term::Reference(a), Term::Reference(b)) => compare_references(a, b),
+        (Term::ExternalFun(a), Term::ExternalFun(b)) => (&a.module.name, &a.function.name, a.arity)
+            .cmp(&(&b.module.name, &b.function.name, b.arity)),
+        // As in `erts_cmp`, local funs are ordered before external ones.
+        (Term::ExternalFun(_), Term::InternalFun(_)) => Ordering::Greater,
+        (Term::InternalFun(_), Term::ExternalFun(_)) => Ordering::Less,
+        (Term::InternalFun(a), Term::InternalFun(b)) => compare_internal_funs(a, b),
+        (Term::Port(a), Term::Port(b)) => {
+            (&a.node.name, a.creation, a.id).cmp(&(&b.node.name, b.creation, b.id))
+        }
+        (Term::Pid(a), Term::Pid(b)) => compare_pids(a, b),
+        (Term::Tuple(a), Term::Tuple(b)) => a
+            .elements
+            .len()
+            .cmp(&b.elements.len())
+            .then_with(|| compare_slices(&a.elements, &b.elements, exact)),
+        (Term::Map(a), Term::Map(b)) => compare_maps(a, b, exact),
+        _ => match (list(a), list(b)) {
+            (Some(a), Some(b)) => compare_lists(a, b, exact),
+            _ => match (number(a), number(b)) {
+                (Some(a), Some(b)) => compare_numbers(a, b, exact),
+                _ => compare_bit_strings(bit_string(a), bit_string(b)),
+            },
+        },
+    }
+}
+
+/// Sorts map entries by their keys in map key order.
+pub(crate) fn sorted_entries(map: &Map) -> Vec<(&Term, &Term)> {
+    first_entries(map, usize::MAX)
+}
+
+/// Returns the first `n` map entries in map key order, without sorting the others.
+pub(crate) fn first_entries(map: &Map, n: usize) -> Vec<(&Term, &Term)> {
+    let order = |a: &(&Term, &Term), b: &(&Term, &Term)| {
+        compare(a.0, b.0, true).then_with(|| compare_representations(a.0, b.0))
+    };
+    let mut entries = map.map.iter().collect::<Vec<_>>();
+    if n < entries.len() {
+        entries.select_nth_unstable_by(n, order);
+        entries.truncate(n);
+    }
+    entries.sort_by(order);
+    entries
+}
+
+/// Compares terms which are equal in term order by their representation,
+/// e.g. `1` and `1.0`, or a `ByteList` and a `List` of the same integers.
+///
+/// Ordering such terms makes `Ord for Term` consistent with `Eq`.
+pub(crate) fn compare_representations(a: &Term, b: &Term) -> Ordering {
+    let variant = variant(a).cmp(&variant(b));
+    if variant.is_ne() {
+        return variant;
+    }
+    match (a, b) {
+        (Term::Reference(a), Term::Reference(b)) => a.id.cmp(&b.id),
+        (Term::InternalFun(a), Term::InternalFun(b)) => compare_internal_fun_representations(a, b),
+        (Term::BitBinary(a), Term::BitBinary(b)) => {
+            (&a.bytes, a.tail_bits_size).cmp(&(&b.bytes, b.tail_bits_size))
+        }
+        (Term::List(a), Term::List(b)) => a.elements.cmp(&b.elements),
+        (Term::ImproperList(a), Term::ImproperList(b)) => {
+            (&a.elements, &a.last).cmp(&(&b.elements, &b.last))
+        }
+        (Term::Tuple(a), Term::Tuple(b)) => a.elements.cmp(&b.elements),
+        (Term::Map(a), Term::Map(b)) => sorted_entries(a).cmp(&sorted_entries(b)),
+        // The other terms equal in term order are identical.
+        _ => Ordering::Equal,
+    }
+}
+
+fn class(term: &Term) -> u8 {
+    match term {
+        Term::FixInteger(_) | Term::BigInteger(_) | Term::Float(_) => 0,
+        Term::Atom(_) => 1,
+        Term::Reference(_) => 2,
+        Term::ExternalFun(_) | Term::InternalFun(_) => 3,
+        Term::Port(_) => 4,
+        Term::Pid(_) => 5,
+        Term::Tuple(_) => 6,
+        Term::Map(_) => 7,
+        Term::List(x) if x.elements.is_empty() => 8,
+        Term::ByteList(x) if x.bytes.is_empty() => 8,
+        Term::List(_) | Term::ByteList(_) | Term::ImproperList(_) => 9,
+        Term::Binary(_) | Term::BitBinary(_) => 10,
+    }
+}
+
+fn variant(term: &Term) -> u8 {
+    match term {
+        Term::Atom(_) => 0,
+        Term::FixInteger(_) => 1,
+        Term::BigInteger(_) => 2,
+        Term::Float(_) => 3,
+        Term::Pid(_) => 4,
+        Term::Port(_) => 5,
+        Term::Reference(_) => 6,
+        Term::ExternalFun(_) => 7,
+        Term::InternalFun(_) => 8,
+        Term::Binary(_) => 9,
+        Term::BitBinary(_) => 10,
+        Term::ByteList(_) => 11,
+        Term::List(_) => 12,
+        Term::ImproperList(_) => 13,
+        Term::Tuple(_) => 14,
+        Term::Map(_) => 15,
+    }
+}
+
+fn compare_slices(a: &[Term], b: &[Term], exact: bool) -> Ordering {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| compare(a, b, exact))
+        .find(|o| o.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+fn compare_references(a: &Reference, b: &Reference) -> Ordering {
+    (&a.node.name, a.creation)
+        .cmp(&(&b.node.name, b.creation))
+        .then_with(|| {
+            // IDs are compared from the most significant word, missing words being zero.
+            let len = a.id.len().max(b.id.len());
+            (0..len)
+                .rev()
+                .map(|i| {
+                    let x = a.id.get(i).copied().unwrap_or(0);
+                    let y = b.id.get(i).copied().unwrap_or(0);
+                    x.cmp(&y)
+                })
+                .find(|o| o.is_ne())
+                .unwrap_or(Ordering::Equal)
+        })
+}
+
+fn compare_pids(a: &Pid, b: &Pid) -> Ordering {
+    (&a.node.name, a.creation, a.serial, a.id).cmp(&(&b.node.name, b.creation, b.serial, b.id))
+}
+
+fn compare_internal_funs(a: &InternalFun, b: &InternalFun) -> Ordering {
+    fn key(f: &InternalFun) -> (&str, i32, i32, &[Term]) {
+        match f {
+            InternalFun::Old {
+                module,
+                index,
+                uniq,
+                free_vars,
+                ..
+            } => (&module.name, *index, *uniq, free_vars),
+            InternalFun::New {
+                module,
+                old_index,
+                old_uniq,
+                free_vars,
+                ..
+            } => (&module.name, *old_index, *old_uniq, free_vars),
+        }
+    }
+    let (a, b) = (key(a), key(b));
+    (a.0, a.1, a.2, a.3.len())
+        .cmp(&(b.0, b.1, b.2, b.3.len()))
+        .then_with(|| compare_slices(a.3, b.3, false))
+}
+
+/// Compares the fields of funs which are not compared in term order.
+fn compare_internal_fun_representations(a: &InternalFun, b: &InternalFun) -> Ordering {
+    match (a, b) {
+        (
+            InternalFun::Old {
+                pid: a_pid,
+                free_vars: a_free_vars,
+                ..
+            },
+            InternalFun::Old {
+                pid: b_pid,
+                free_vars: b_free_vars,
+                ..
+            },
+        ) => compare_pids(a_pid, b_pid).then_with(|| a_free_vars.cmp(b_free_vars)),
+        (
+            InternalFun::New {
+                arity: a_arity,
+                pid: a_pid,
+                free_vars: a_free_vars,
+                index: a_index,
+                uniq: a_uniq,
+                ..
+            },
+            InternalFun::New {
+                arity: b_arity,
+                pid: b_pid,
+                free_vars: b_free_vars,
+                index: b_index,
+                uniq: b_uniq,
+                ..
+            },
+        ) => (a_arity, a_index, a_uniq)
+            .cmp(&(b_arity, b_index, b_uniq))
+            .then_with(|| compare_pids(a_pid, b_pid))
+            .then_with(|| a_free_vars.cmp(b_free_vars)),
+        (InternalFun::Old { .. }, InternalFun::New { .. }) => Ordering::Less,
+        (InternalFun::New { .. }, InternalFun::Old { .. }) => Ordering::Greater,
+    }
+}
+
+fn compare_maps(a: &Map, b: &Map, exact: bool) -> Ordering {
+    a.map.len().cmp(&b.map.len()).then_with(|| {
+        let a = sorted_entries(a);
+        let b = sorted_entries(b);
+        a.iter()
+            .zip(&b)
+            .map(|(x, y)| compare(x.0, y.0, true))
+            .chain(a.iter().zip(&b).map(|(x, y)| compare(x.1, y.1, exact)))
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal)
+    })
+}
+
+#[derive(Clone, Copy)]
+enum Items<'a> {
+    Bytes(&'a [u8]),
+    Terms(&'a [Term]),
+}
+impl<'a> Items<'a> {
+    fn len(&self) -> usize {
+        match self {
+            Items::Bytes(x) => x.len(),
+            Items::Terms(x) => x.len(),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ListView<'a> {
+    items: Items<'a>,
+    tail: Option<&'a Term>,
+}
+
+fn list(term: &Term) -> Option<ListView<'_>> {
+    match term {
+        Term::List(x) => Some(ListView {
+            items: Items::Terms(&x.elements),
+            tail: None,
+        }),
+        Term::ByteList(x) => Some(ListView {
+            items: Items::Bytes(&x.bytes),
+            tail: None,
+        }),
+        Term::ImproperList(x) => Some(ListView {
+            items: Items::Terms(&x.elements),
+            tail: Some(&x.last),
+        }),
+        _ => None,
+    }
+}
+
+fn compare_lists<'a>(a: ListView<'a>, b: ListView<'a>, exact: bool) -> Ordering {
+    let n = a.items.len().min(b.items.len());
+    for i in 0..n {
+        let ordering = match (a.items, b.items) {
+            (Items::Bytes(x), Items::Bytes(y)) => x[i].cmp(&y[i]),
+            (Items::Terms(x), Items::Terms(y)) => compare(&x[i], &y[i], exact),
+            (Items::Bytes(x), Items::Terms(y)) => {
+                compare(&Term::from(FixInteger::from(x[i])), &y[i], exact)
+            }
+            (Items::Terms(x), Items::Bytes(y)) => {
+                compare(&x[i], &Term::from(FixInteger::from(y[i])), exact)
+            }
+        };
+        if ordering.is_ne() {
+            return ordering;
+        }
+    }
+
+    // Compares the rest of the lists, where at least one of them is just its tail.
+    let rest = |view: ListView<'a>| ListView {
+        items: match view.items {
+            Items::Bytes(x) => Items::Bytes(&x[n..]),
+            Items::Terms(x) => Items::Terms(&x[n..]),
+        },
+        tail: view.tail,
+    };
+    let (a, b) = (rest(a), rest(b));
+    match (a.items.len(), b.items.len()) {
+        (0, 0) => compare_tails(a.tail, b.tail, exact),
+        (0, _) => compare_tail_with_list(a.tail, b, exact),
+        _ => compare_tail_with_list(b.tail, a, exact).reverse(),
+    }
+}
+
+fn compare_tails(a: Option<&Term>, b: Option<&Term>, exact: bool) -> Ordering {
+    match (a, b) {
+        (None, None) => Ordering::Equal,
+        (None, Some(b)) => class(b).cmp(&8).reverse(),
+        (Some(a), None) => class(a).cmp(&8),
+        (Some(a), Some(b)) => compare(a, b, exact),
+    }
+}
+
+/// Compares the tail of a list with a non-empty list.
+fn compare_tail_with_list(tail: Option<&Term>, list: ListView, exact: bool) -> Ordering {
+    match tail.and_then(self::list) {
+        Some(tail) => compare_lists(tail, list, exact),
+        None => match tail {
+            None => Ordering::Less,
+            Some(tail) => class(tail).cmp(&9),
+        },
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Number<'a> {
+    Fix(i32),
+    Big(&'a BigInt),
+    Float(f64),
+}
+
+fn number(term: &Term) -> Option<Number<'_>> {
+    match term {
+        Term::FixInteger(x) => Some(Number::Fix(x.value)),
+        Term::BigInteger(x) => Some(Number::Big(&x.value)),
+        Term::Float(x) => Some(Number::Float(x.value)),
+        _ => None,
+    }
+}
+
+fn compare_numbers(a: Number, b: Number, exact: bool) -> Ordering {
+    if exact {
+        let is_float = |x| matches!(x, Number::Float(_));
+        let class = is_float(a).cmp(&is_float(b));
+        if class.is_ne() {
+            return class;
+        }
+    }
+    match (a, b) {
+        (Number::Fix(a), Number::Fix(b)) => a.cmp(&b),
+        (Number::Fix(a), Number::Big(b)) => BigInt::from(a).cmp(b),
+        (Number::Big(a), Number::Fix(b)) => a.cmp(&BigInt::from(b)),
+        (Number::Big(a), Number::Big(b)) => a.cmp(b),
+        (Number::Float(a), Number::Float(b)) => compare_floats(a, b),
+        (Number::Fix(a), Number::Float(b)) => compare_floats(f64::from(a), b),
+        (Number::Float(a), Number::Fix(b)) => compare_floats(a, f64::from(b)),
+        (Number::Big(a), Number::Float(b)) => compare_big_with_float(a, b),
+        (Number::Float(a), Number::Big(b)) => compare_big_with_float(b, a).reverse(),
+    }
+}
+
+/// Compares floats by value, so that `-0.0` equals `0.0` as in `Eq for Float`.
+fn compare_floats(a: f64, b: f64) -> Ordering {
+    // Non-finite floats are not valid terms, but are handled anyway.
+    a.partial_cmp(&b).unwrap_or_else(|| a.total_cmp(&b))
+}
+
+fn compare_big_with_float(a: &BigInt, b: f64) -> Ordering {
+    let floor = b.floor();
+    match BigInt::from_f64(floor) {
+        Some(floor_int) => a.cmp(&floor_int).then(if floor < b {
+            Ordering::Less
+        } else {
+            Ordering::Equal
+        }),
+        // Non-finite floats are not valid terms, but are handled anyway.
+        None if b > 0.0 => Ordering::Less,
+        None => Ordering::Greater,
+    }
+}
+
+/// Bit string as `(whole bytes, bits in the last partial byte, number of those bits)`.
+fn bit_string(term: &Term) -> (&[u8], u8, u8) {
+    match term {
+        Term::Binary(x) => (&x.bytes, 0, 0),
+        Term::BitBinary(x) => match x.bytes.split_last() {
+            Some((&last, rest)) if x.tail_bits_size < 8 => {
+                let bits = x.tail_bits_size;
+                (rest, last & ((1u16 << bits) - 1) as u8, bits)
+            }
+            _ => (&x.bytes, 0, 0),
+        },
+        _ => unreachable!(),
+    }
+}
+
+fn compare_bit_strings(a: (&[u8], u8, u8), b: (&[u8], u8, u8)) -> Ordering {
+    let bit = |(bytes, last, last_bits): (&[u8], u8, u8), i: usize| -> Option<u8> {
+        if i < bytes.len() * 8 {
+            Some((bytes[i / 8] >> (7 - i % 8)) & 1)
+        } else {
+            let j = i - bytes.len() * 8;
+            (j < usize::from(last_bits)).then(|| (last >> (usize::from(last_bits) - 1 - j)) & 1)
+        }
+    };
+    let whole = a.0.len().min(b.0.len());
+    let ordering = a.0[..whole].cmp(&b.0[..whole]);
+    if ordering.is_ne() {
+        return ordering;
+    }
+    (whole * 8..)
+        .map(|i| (bit(a, i), bit(b, i)))
+        .find(|(x, y)| x != y || x.is_none())
+        .map_or(Ordering::Equal, |(x, y)| x.cmp(&y))
+}
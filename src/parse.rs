@@ -0,0 +1,903 @@
+//! Parser of the Erlang term syntax.
+use crate::{
+    Atom, BigInteger, Binary, BitBinary, ByteList, ExternalFun, FixInteger, Float, ImproperList,
+    List, Map, Pid, Port, Reference, Term, Tuple,
+};
+use num_bigint::BigInt;
+use num_traits::{Signed, ToPrimitive};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// Maximum nesting of lists, tuples and maps, which keeps deeply nested input from overflowing the stack.
+const MAX_DEPTH: usize = 512;
+
+/// Maximum size of a binary in bits (1 MiB), which keeps short input from allocating a lot:
+/// a segment size applies to each character of a string, and segments can be repeated.
+const MAX_BINARY_SIZE: usize = 8 << 20;
+
+/// Node of the pids, ports and references written with the node number `0` (e.g. `<0.1.2>`).
+const LOCAL_NODE: &str = "nonode@nohost";
+
+/// Parses a term written in the Erlang syntax.
+///
+/// Besides the usual literals, the parser accepts:
+///
+/// - strings (`"abc"`), which become a [`ByteList`] (or a [`List`] of code points above 255);
+/// - binaries of up to 1 MiB with integer and string segments (`<<1, 2:4, "abc", "é"/utf8>>`);
+/// - pids, ports and references as printed by `Display` (`<node@host.1.2>`, `#Port<node@host.3>`,
+///   `#Ref<node@host.1.2.3>`), or by the Erlang shell with the local node `0` (`<0.1.2>`);
+/// - external funs (`fun lists:map/2`).
+///
+/// Comments (`% ...`) and a trailing `.` are allowed.
+///
+/// # Examples
+///
+/// ```
+/// use eetf::{Atom, ByteList, FixInteger, Term, Tuple};
+///
+/// let term: Term = "{ok, \"abc\", 16#FF}.".parse().unwrap();
+/// assert_eq!(
+///     term,
+///     Term::from(Tuple::from(vec![
+///         Term::from(Atom::from("ok")),
+///         Term::from(ByteList::from("abc")),
+///         Term::from(FixInteger::from(255)),
+///     ]))
+/// );
+///
+/// let error = "[1, 2".parse::<Term>().unwrap_err();
+/// assert_eq!(error.to_string(), "unexpected end of input, expected ',', '|' or ']' at line 1, column 6");
+/// ```
+impl FromStr for Term {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser::new(s);
+        parser.skip_whitespace();
+        let term = parser.term()?;
+        parser.skip_whitespace();
+        if parser.peek() == Some('.') {
+            parser.bump();
+            parser.skip_whitespace();
+        }
+        match parser.peek() {
+            None => Ok(term),
+            Some(_) => Err(parser.unexpected("end of input")),
+        }
+    }
+}
+
+/// Error of parsing a term, located by its line and column (both starting at 1).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    kind: ParseErrorKind,
+    line: usize,
+    column: usize,
+}
+impl ParseError {
+    pub fn kind(&self) -> &ParseErrorKind {
+        &self.kind
+    }
+
+    pub fn into_kind(self) -> ParseErrorKind {
+        self.kind
+    }
+
+    /// Returns the line of the error, starting at 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns the column of the error in characters, starting at 1.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.kind, self.line, self.column
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Kinds of errors which can occur when parsing a term.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    /// A character which cannot appear here.
+    UnexpectedChar { found: char, expected: String },
+
+    /// The input ended in the middle of a term.
+    UnexpectedEnd { expected: String },
+
+    /// An invalid escape sequence in a string, a quoted atom or a character.
+    InvalidEscape { sequence: String },
+
+    /// A malformed or out of range number (e.g. `37#1`, `1.0e999`, or a pid field above `u32::MAX`).
+    InvalidNumber { text: String },
+
+    /// A node number other than `0`, whose node name is unknown.
+    UnknownNode { number: String },
+
+    /// A binary segment which cannot be built (e.g. an unsupported type specifier).
+    InvalidSegment { reason: String },
+
+    /// Lists, tuples and maps are nested too deeply.
+    TooDeep { max: usize },
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedChar { found, expected } => {
+                write!(f, "unexpected {found:?}, expected {expected}")
+            }
+            Self::UnexpectedEnd { expected } => {
+                write!(f, "unexpected end of input, expected {expected}")
+            }
+            Self::InvalidEscape { sequence } => write!(f, "invalid escape sequence {sequence:?}"),
+            Self::InvalidNumber { text } => write!(f, "invalid number {text:?}"),
+            Self::UnknownNode { number } => write!(
+                f,
+                "unknown node {number} (only the local node 0 can be written as a number)"
+            ),
+            Self::InvalidSegment { reason } => write!(f, "invalid binary segment: {reason}"),
+            Self::TooDeep { max } => write!(f, "terms are nested more than {max} levels deep"),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Position {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    position: Position,
+    depth: usize,
+}
+impl<'a> Parser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self {
+            input,
+            position: Position {
+                offset: 0,
+                line: 1,
+                column: 1,
+            },
+            depth: 0,
+        }
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.position.offset..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.rest().chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position.offset += c.len_utf8();
+        if c == '\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
+        }
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if c == '%' {
+                while self.peek().is_some_and(|c| c != '\n') {
+                    self.bump();
+                }
+            } else if c.is_whitespace() {
+                self.bump();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn error_at(&self, position: Position, kind: ParseErrorKind) -> ParseError {
+        ParseError {
+            kind,
+            line: position.line,
+            column: position.column,
+        }
+    }
+
+    /// Makes an error reporting the next character (or the end of input) as unexpected.
+    fn unexpected(&self, expected: &str) -> ParseError {
+        let expected = expected.to_owned();
+        let kind = match self.peek() {
+            Some(found) => ParseErrorKind::UnexpectedChar { found, expected },
+            None => ParseErrorKind::UnexpectedEnd { expected },
+        };
+        self.error_at(self.position, kind)
+    }
+
+    fn expect(&mut self, c: char, expected: &str) -> Result<(), ParseError> {
+        if self.peek() == Some(c) {
+            self.bump();
+            Ok(())
+        } else {
+            Err(self.unexpected(expected))
+        }
+    }
+
+    fn expect_str(&mut self, s: &str) -> Result<(), ParseError> {
+        for c in s.chars() {
+            self.expect(c, &format!("{s:?}"))?;
+        }
+        Ok(())
+    }
+
+    fn enter(&mut self) -> Result<(), ParseError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error_at(self.position, ParseErrorKind::TooDeep { max: MAX_DEPTH }));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    fn term(&mut self) -> Result<Term, ParseError> {
+        match self.peek() {
+            Some('0'..='9') => self.number(false),
+            Some(c @ ('-' | '+')) if self.peek_nth(1).is_some_and(|c| c.is_ascii_digit()) => {
+                self.bump();
+                self.number(c == '-')
+            }
+            Some('$') => {
+                self.bump();
+                let c = self.char()?;
+                Ok(Term::from(FixInteger::from(c as i32)))
+            }
+            Some('\'') => Ok(Term::from(Atom::from(self.quoted('\'')?))),
+            Some('"') => self.string(),
+            Some('[') => self.list(),
+            Some('{') => self.tuple(),
+            Some('#') => self.hash(),
+            Some('<') if self.peek_nth(1) == Some('<') => self.binary(),
+            Some('<') => self.pid(),
+            Some(c) if c.is_lowercase() => {
+                let name = self.unquoted_atom();
+                if name == "fun" {
+                    self.external_fun()
+                } else {
+                    Ok(Term::from(Atom::from(name)))
+                }
+            }
+            _ => Err(self.unexpected("a term")),
+        }
+    }
+
+    fn number(&mut self, negative: bool) -> Result<Term, ParseError> {
+        let start = self.position;
+        let digits = self.digits(10);
+        let invalid = |parser: &Self| {
+            let text = parser.input[start.offset..parser.position.offset].to_owned();
+            parser.error_at(start, ParseErrorKind::InvalidNumber { text })
+        };
+
+        let value = if self.peek() == Some('#') {
+            self.bump();
+            let base = digits.parse::<u32>().unwrap_or(0);
+            if !(2..=36).contains(&base) {
+                return Err(invalid(self));
+            }
+            let digits = self.digits(base);
+            BigInt::parse_bytes(digits.as_bytes(), base).ok_or_else(|| invalid(self))?
+        } else if self.peek() == Some('.') && self.peek_nth(1).is_some_and(|c| c.is_ascii_digit()) {
+            self.bump();
+            let mut text = format!("{digits}.{}", self.digits(10));
+            if matches!(self.peek(), Some('e' | 'E')) {
+                let sign = match self.peek_nth(1) {
+                    Some(c @ ('-' | '+')) => Some(c),
+                    _ => None,
+                };
+                let exponent_start = 1 + usize::from(sign.is_some());
+                if self
+                    .peek_nth(exponent_start)
+                    .is_some_and(|c| c.is_ascii_digit())
+                {
+                    self.bump();
+                    if sign.is_some() {
+                        self.bump();
+                    }
+                    text.push('e');
+                    text.extend(sign);
+                    text.push_str(&self.digits(10));
+                }
+            }
+            let value = text.parse::<f64>().map_err(|_| invalid(self))?;
+            let value = if negative { -value } else { value };
+            return Float::try_from(value)
+                .map(Term::from)
+                .map_err(|_| invalid(self));
+        } else {
+            BigInt::parse_bytes(digits.as_bytes(), 10).ok_or_else(|| invalid(self))?
+        };
+        Ok(integer(if negative { -value } else { value }))
+    }
+
+    /// Reads the digits (and `_` separators between them) of the given base.
+    fn digits(&mut self, base: u32) -> String {
+        let mut digits = String::new();
+        while let Some(c) = self.peek() {
+            if c.is_digit(base) {
+                digits.push(c);
+            } else if c == '_'
+                && !digits.is_empty()
+                && self.peek_nth(1).is_some_and(|c| c.is_digit(base))
+            {
+            } else {
+                break;
+            }
+            self.bump();
+        }
+        digits
+    }
+
+    /// Reads an unsigned integer field of a pid, port or reference.
+    fn field<T: FromStr>(&mut self) -> Result<T, ParseError> {
+        let start = self.position;
+        let digits = self.digits(10);
+        if digits.is_empty() {
+            return Err(self.unexpected("an integer"));
+        }
+        digits
+            .parse()
+            .map_err(|_| self.error_at(start, ParseErrorKind::InvalidNumber { text: digits }))
+    }
+
+    /// Reads the character of a `$c` literal.
+    fn char(&mut self) -> Result<char, ParseError> {
+        match self.bump() {
+            Some('\\') => self.escape(),
+            Some(c) => Ok(c),
+            None => Err(self.unexpected("a character")),
+        }
+    }
+
+    /// Reads an escape sequence following a backslash.
+    fn escape(&mut self) -> Result<char, ParseError> {
+        let start = Position {
+            offset: self.position.offset - 1,
+            column: self.position.column - 1,
+            ..self.position
+        };
+        let invalid = |parser: &Self| {
+            let sequence = parser.input[start.offset..parser.position.offset].to_owned();
+            parser.error_at(start, ParseErrorKind::InvalidEscape { sequence })
+        };
+        let c = self
+            .bump()
+            .ok_or_else(|| self.unexpected("an escape sequence"))?;
+        let code = match c {
+            'b' => 8,
+            'd' => 127,
+            'e' => 27,
+            'f' => 12,
+            'n' => 10,
+            'r' => 13,
+            's' => 32,
+            't' => 9,
+            'v' => 11,
+            '0'..='7' => {
+                let mut code = c.to_digit(8).expect("checked");
+                for _ in 0..2 {
+                    match self.peek().and_then(|c| c.to_digit(8)) {
+                        Some(digit) => {
+                            self.bump();
+                            code = code * 8 + digit;
+                        }
+                        None => break,
+                    }
+                }
+                code
+            }
+            'x' => {
+                let digits = if self.peek() == Some('{') {
+                    self.bump();
+                    let digits = self.digits(16);
+                    if self.peek() != Some('}') {
+                        return Err(invalid(self));
+                    }
+                    self.bump();
+                    digits
+                } else {
+                    let mut digits = String::new();
+                    for _ in 0..2 {
+                        match self.peek() {
+                            Some(c) if c.is_ascii_hexdigit() => {
+                                self.bump();
+                                digits.push(c);
+                            }
+                            _ => return Err(invalid(self)),
+                        }
+                    }
+                    digits
+                };
+                u32::from_str_radix(&digits, 16).map_err(|_| invalid(self))?
+            }
+            '^' => match self.bump() {
+                Some(c) if c.is_ascii_alphabetic() => u32::from(c) & 31,
+                _ => return Err(invalid(self)),
+            },
+            c => u32::from(c),
+        };
+        char::from_u32(code).ok_or_else(|| invalid(self))
+    }
+
+    /// Reads a quoted atom or string delimited by `quote`.
+    fn quoted(&mut self, quote: char) -> Result<String, ParseError> {
+        self.bump();
+        let mut text = String::new();
+        loop {
+            match self.bump() {
+                Some(c) if c == quote => return Ok(text),
+                Some('\\') => text.push(self.escape()?),
+                Some(c) => text.push(c),
+                None => return Err(self.unexpected(&format!("{quote:?}"))),
+            }
+        }
+    }
+
+    fn unquoted_atom(&mut self) -> &'a str {
+        let start = self.position.offset;
+        while self
+            .peek()
+            .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '@')
+        {
+            self.bump();
+        }
+        &self.input[start..self.position.offset]
+    }
+
+    fn atom(&mut self) -> Result<Atom, ParseError> {
+        match self.peek() {
+            Some('\'') => Ok(Atom::from(self.quoted('\'')?)),
+            Some(c) if c.is_lowercase() => Ok(Atom::from(self.unquoted_atom())),
+            _ => Err(self.unexpected("an atom")),
+        }
+    }
+
+    /// Reads one or more adjacent strings, which are concatenated as in Erlang.
+    fn strings(&mut self) -> Result<String, ParseError> {
+        let mut text = self.quoted('"')?;
+        loop {
+            let position = self.position;
+            self.skip_whitespace();
+            if self.peek() == Some('"') {
+                text.push_str(&self.quoted('"')?);
+            } else {
+                self.position = position;
+                return Ok(text);
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<Term, ParseError> {
+        let text = self.strings()?;
+        if text.is_empty() {
+            Ok(Term::from(List::nil()))
+        } else if let Some(bytes) = latin1(&text) {
+            Ok(Term::from(ByteList::from(bytes)))
+        } else {
+            let elements = text
+                .chars()
+                .map(|c| Term::from(FixInteger::from(c as i32)))
+                .collect::<Vec<_>>();
+            Ok(Term::from(List::from(elements)))
+        }
+    }
+
+    fn list(&mut self) -> Result<Term, ParseError> {
+        self.enter()?;
+        self.bump();
+        self.skip_whitespace();
+        let mut elements = Vec::new();
+        if self.peek() == Some(']') {
+            self.bump();
+            self.depth -= 1;
+            return Ok(Term::from(List::nil()));
+        }
+        let tail = loop {
+            elements.push(self.term()?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.bump();
+                    self.skip_whitespace();
+                }
+                Some('|') => {
+                    self.bump();
+                    self.skip_whitespace();
+                    let tail = self.term()?;
+                    self.skip_whitespace();
+                    self.expect(']', "']'")?;
+                    break tail;
+                }
+                Some(']') => {
+                    self.bump();
+                    break Term::from(List::nil());
+                }
+                _ => return Err(self.unexpected("',', '|' or ']'")),
+            }
+        };
+        self.depth -= 1;
+
+        // `[1 | [2, 3]]` is the proper list `[1, 2, 3]`.
+        match tail {
+            Term::List(tail) => {
+                elements.extend(tail.elements);
+                Ok(Term::from(List::from(elements)))
+            }
+            Term::ByteList(tail) => {
+                elements.extend(List::from(tail).elements);
+                Ok(Term::from(List::from(elements)))
+            }
+            Term::ImproperList(tail) => {
+                elements.extend(tail.elements);
+                Ok(Term::from(ImproperList::from((elements, *tail.last))))
+            }
+            tail => Ok(Term::from(ImproperList::from((elements, tail)))),
+        }
+    }
+
+    fn tuple(&mut self) -> Result<Term, ParseError> {
+        self.enter()?;
+        self.bump();
+        let elements = self.sequence('}', |parser| parser.term())?;
+        self.depth -= 1;
+        Ok(Term::from(Tuple::from(elements)))
+    }
+
+    /// Reads items separated by commas until `close`, which is consumed.
+    fn sequence<T>(
+        &mut self,
+        close: char,
+        mut item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.peek() == Some(close) {
+            self.bump();
+            return Ok(items);
+        }
+        loop {
+            items.push(item(self)?);
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.bump();
+                    self.skip_whitespace();
+                }
+                Some(c) if c == close => {
+                    self.bump();
+                    return Ok(items);
+                }
+                _ => return Err(self.unexpected(&format!("',' or {close:?}"))),
+            }
+        }
+    }
+
+    /// Reads a map, a reference or a port, which start with `#`.
+    fn hash(&mut self) -> Result<Term, ParseError> {
+        self.bump();
+        if self.peek() == Some('{') {
+            return self.map();
+        }
+        let start = self.position;
+        match self.unquoted_name() {
+            "Ref" => {
+                self.expect('<', "'<'")?;
+                let node = self.node()?;
+                let mut id = Vec::new();
+                while self.peek() == Some('.') {
+                    self.bump();
+                    id.push(self.field()?);
+                }
+                self.expect('>', "'.' or '>'")?;
+                Ok(Term::from(Reference {
+                    node,
+                    id,
+                    creation: 0,
+                }))
+            }
+            "Port" => {
+                self.expect('<', "'<'")?;
+                let node = self.node()?;
+                self.expect('.', "'.'")?;
+                let id = self.field()?;
+                self.expect('>', "'>'")?;
+                Ok(Term::from(Port {
+                    node,
+                    id,
+                    creation: 0,
+                }))
+            }
+            _ => {
+                self.position = start;
+                Err(self.unexpected("'{', \"Ref\" or \"Port\""))
+            }
+        }
+    }
+
+    fn unquoted_name(&mut self) -> &'a str {
+        let start = self.position.offset;
+        while self.peek().is_some_and(|c| c.is_ascii_alphanumeric()) {
+            self.bump();
+        }
+        &self.input[start..self.position.offset]
+    }
+
+    fn map(&mut self) -> Result<Term, ParseError> {
+        self.enter()?;
+        self.bump();
+        let entries = self.sequence('}', |parser| {
+            let key = parser.term()?;
+            parser.skip_whitespace();
+            parser.expect_str("=>")?;
+            parser.skip_whitespace();
+            let value = parser.term()?;
+            Ok((key, value))
+        })?;
+        self.depth -= 1;
+        Ok(Term::from(Map::from(
+            entries.into_iter().collect::<HashMap<_, _>>(),
+        )))
+    }
+
+    /// Reads the node of a pid, port or reference: an atom or the local node `0`.
+    fn node(&mut self) -> Result<Atom, ParseError> {
+        if !self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            return self.atom();
+        }
+        let start = self.position;
+        let number = self.digits(10);
+        if number.trim_start_matches('0').is_empty() {
+            Ok(Atom::from(LOCAL_NODE))
+        } else {
+            Err(self.error_at(start, ParseErrorKind::UnknownNode { number }))
+        }
+    }
+
+    fn pid(&mut self) -> Result<Term, ParseError> {
+        self.bump();
+        let node = self.node()?;
+        self.expect('.', "'.'")?;
+        let id = self.field()?;
+        self.expect('.', "'.'")?;
+        let serial = self.field()?;
+        self.expect('>', "'>'")?;
+        Ok(Term::from(Pid::new(node, id, serial, 0)))
+    }
+
+    /// Reads `module:function/arity` following `fun`.
+    fn external_fun(&mut self) -> Result<Term, ParseError> {
+        self.skip_whitespace();
+        if !self.peek().is_some_and(|c| c == '\'' || c.is_lowercase()) {
+            // The bare atom `fun`, e.g. `{fun, 1}`.
+            return Ok(Term::from(Atom::from("fun")));
+        }
+        let module = self.atom()?;
+        self.expect(':', "':'")?;
+        let function = self.atom()?;
+        self.expect('/', "'/'")?;
+        let arity = self.field()?;
+        Ok(Term::from(ExternalFun {
+            module,
+            function,
+            arity,
+        }))
+    }
+
+    fn binary(&mut self) -> Result<Term, ParseError> {
+        self.bump();
+        self.bump();
+        let mut bits = BitWriter::default();
+        self.skip_whitespace();
+        if self.peek() == Some('>') {
+            self.expect_str(">>")?;
+            return Ok(Term::from(Binary::from(Vec::new())));
+        }
+        loop {
+            self.segment(&mut bits)?;
+            self.skip_whitespace();
+            match self.peek() {
+                Some(',') => {
+                    self.bump();
+                    self.skip_whitespace();
+                }
+                Some('>') => {
+                    self.expect_str(">>")?;
+                    return Ok(bits.finish());
+                }
+                _ => return Err(self.unexpected("',' or \">>\"")),
+            }
+        }
+    }
+
+    /// Reads a binary segment: `"text"`, `Value`, `Value:Size` or either of them followed by `/utf8`.
+    fn segment(&mut self, bits: &mut BitWriter) -> Result<(), ParseError> {
+        let start = self.position;
+        let invalid = |parser: &Self, reason: &str| {
+            parser.error_at(
+                start,
+                ParseErrorKind::InvalidSegment {
+                    reason: reason.to_owned(),
+                },
+            )
+        };
+
+        let value = if self.peek() == Some('"') {
+            Segment::Text(self.strings()?)
+        } else {
+            match self.term()? {
+                Term::FixInteger(x) => Segment::Integer(BigInt::from(x.value)),
+                Term::BigInteger(x) => Segment::Integer(x.value),
+                _ => return Err(invalid(self, "only integers and strings are supported")),
+            }
+        };
+        let size = if self.peek() == Some(':') {
+            self.bump();
+            Some(self.field::<usize>()?)
+        } else {
+            None
+        };
+        let utf8 = if self.peek() == Some('/') {
+            self.bump();
+            match self.unquoted_name() {
+                "utf8" => true,
+                "integer" => false,
+                _ => {
+                    return Err(invalid(
+                        self,
+                        "only the `utf8` and `integer` types are supported",
+                    ));
+                }
+            }
+        } else {
+            false
+        };
+
+        // Checked before anything is pushed.
+        let reserve = |parser: &Self, bits: &BitWriter, size: usize| {
+            if bits.len.saturating_add(size) > MAX_BINARY_SIZE {
+                Err(invalid(parser, "the binary is larger than 1 MiB"))
+            } else {
+                Ok(())
+            }
+        };
+        match (value, size, utf8) {
+            (Segment::Text(text), None, true) => {
+                reserve(self, bits, text.len().saturating_mul(8))?;
+                bits.push_bytes(text.as_bytes());
+            }
+            (Segment::Text(text), size, false) => {
+                let size = size.unwrap_or(8);
+                reserve(self, bits, text.chars().count().saturating_mul(size))?;
+                for c in text.chars() {
+                    bits.push_integer(&BigInt::from(u32::from(c)), size);
+                }
+            }
+            (Segment::Integer(value), None, true) => {
+                let c = value
+                    .to_u32()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| invalid(self, "the value is not a code point"))?;
+                reserve(self, bits, c.len_utf8() * 8)?;
+                bits.push_bytes(c.encode_utf8(&mut [0; 4]).as_bytes());
+            }
+            (Segment::Integer(value), size, false) => {
+                let size = size.unwrap_or(8);
+                reserve(self, bits, size)?;
+                bits.push_integer(&value, size);
+            }
+            (_, Some(_), true) => return Err(invalid(self, "`utf8` segments cannot have a size")),
+        }
+        Ok(())
+    }
+}
+
+enum Segment {
+    Text(String),
+    Integer(BigInt),
+}
+
+/// Bitstring under construction.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    len: usize,
+}
+impl BitWriter {
+    fn push_bit(&mut self, bit: bool) {
+        if self.len.is_multiple_of(8) {
+            self.bytes.push(0);
+        }
+        if bit {
+            *self.bytes.last_mut().expect("pushed") |= 0x80 >> (self.len % 8);
+        }
+        self.len += 1;
+    }
+
+    fn push_byte(&mut self, byte: u8) {
+        match self.len % 8 {
+            0 => self.bytes.push(byte),
+            offset => {
+                *self.bytes.last_mut().expect("pushed") |= byte >> offset;
+                self.bytes.push(byte << (8 - offset));
+            }
+        }
+        self.len += 8;
+    }
+
+    /// Pushes the lowest `size` bits of `value` in two's complement, most significant first.
+    fn push_integer(&mut self, value: &BigInt, size: usize) {
+        if size == 0 {
+            return;
+        }
+        let digits = value.to_signed_bytes_be();
+        let fill = if value.is_negative() { 0xFF } else { 0 };
+        let len = size.div_ceil(8);
+        let mut bytes = std::iter::repeat_n(fill, len.saturating_sub(digits.len()))
+            .chain(digits[digits.len().saturating_sub(len)..].iter().copied());
+        // The first byte only contributes its lowest bits when `size` is not a multiple of 8.
+        let first = bytes.next().expect("non-empty");
+        for i in (0..(size - 1) % 8 + 1).rev() {
+            self.push_bit(first >> i & 1 == 1);
+        }
+        for b in bytes {
+            self.push_byte(b);
+        }
+    }
+
+    fn push_bytes(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.push_byte(b);
+        }
+    }
+
+    fn finish(mut self) -> Term {
+        match self.len % 8 {
+            0 => Term::from(Binary::from(self.bytes)),
+            tail_bits_size => {
+                // `BitBinary` keeps the bits of the last byte in its least significant bits.
+                *self.bytes.last_mut().expect("non-empty") >>= 8 - tail_bits_size;
+                Term::from(BitBinary::from((self.bytes, tail_bits_size as u8)))
+            }
+        }
+    }
+}
+
+fn integer(value: BigInt) -> Term {
+    match value.to_i32() {
+        Some(value) => Term::from(FixInteger::from(value)),
+        None => Term::from(BigInteger { value }),
+    }
+}
+
+/// Returns the Latin-1 bytes of `text`, or `None` if a character is above 255.
+fn latin1(text: &str) -> Option<Vec<u8>> {
+    text.chars().map(|c| u8::try_from(c).ok()).collect()
+}
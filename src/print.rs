@@ -0,0 +1,818 @@
+//! Printing of terms in the Erlang syntax, following `io_lib`.
+use crate::{ExternalFun, InternalFun, Map, Pid, Port, Reference, Term};
+use num_bigint::{BigInt, Sign};
+use std::cell::Cell;
+use std::fmt;
+
+/// Style of [`Term::to_erlang_string`], after the control sequences of `io:format/2`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrintStyle {
+    /// `~w`: lists and binaries are printed element by element, on a single line.
+    Write,
+
+    /// `~p`: printable lists and binaries are printed as strings (`"abc"`, `<<"abc">>`),
+    /// and terms wider than the line width are broken into several lines.
+    Pretty,
+}
+
+/// Options for [`Term::to_erlang_string`].
+#[derive(Debug, Clone)]
+pub struct PrintOptions {
+    style: PrintStyle,
+    line_width: usize,
+    depth: usize,
+    chars_limit: usize,
+}
+impl PrintOptions {
+    /// Default options: the `~p` style with a line width of 80 characters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the style.
+    #[must_use]
+    pub fn style(mut self, style: PrintStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Sets the line width of the `~p` style (`~Np` in `io:format/2`).
+    ///
+    /// `usize::MAX` prints every term on a single line.
+    #[must_use]
+    pub fn line_width(mut self, width: usize) -> Self {
+        self.line_width = width;
+        self
+    }
+
+    /// Sets the depth, as `~P` and `~W` in `io:format/2` do.
+    ///
+    /// Terms nested deeper than `depth` are printed as `...`. Each element of a list, tuple,
+    /// map or binary is one level deeper than the previous one, so that only the first elements
+    /// of long containers are printed: `[1,2|...]`, `{a,b,...}`, `<<"ab"...>>`.
+    /// `usize::MAX` (the default) prints the whole term.
+    #[must_use]
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Sets the maximum number of characters printed.
+    ///
+    /// Longer output is cut after `limit` characters and followed by `...` and the number of
+    /// elements of lists, tuples, maps and binaries which were left out of the containers
+    /// being printed at the cut: `{ok,[1,2,3...(1234 more elements)`.
+    /// Elements past the limit are not formatted, but lists and binaries are still scanned
+    /// to tell whether they are printed as strings, and maps to find their first keys.
+    /// `usize::MAX` (the default) prints all the characters.
+    #[must_use]
+    pub fn chars_limit(mut self, limit: usize) -> Self {
+        self.chars_limit = limit;
+        self
+    }
+}
+impl Default for PrintOptions {
+    fn default() -> Self {
+        Self {
+            style: PrintStyle::Pretty,
+            line_width: 80,
+            depth: usize::MAX,
+            chars_limit: usize::MAX,
+        }
+    }
+}
+
+impl Term {
+    /// Prints the term as `io_lib:format("~w", [Term])` or `io_lib:format("~p", [Term])` does.
+    ///
+    /// Unlike Erlang, map entries are always printed in term order of their keys.
+    /// Pids, ports and references are printed with their node name (`<node@host.1.2>`),
+    /// which [`Term::from_str`](std::str::FromStr::from_str) parses back.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use eetf::{PrintOptions, PrintStyle, Term};
+    ///
+    /// let term: Term = r#"{ok, "abc", <<"abc">>, 'Hello', 1.0e10}"#.parse().unwrap();
+    /// assert_eq!(
+    ///     term.to_erlang_string(&PrintOptions::new()),
+    ///     r#"{ok,"abc",<<"abc">>,'Hello',1.0e10}"#
+    /// );
+    /// assert_eq!(
+    ///     term.to_erlang_string(&PrintOptions::new().style(PrintStyle::Write)),
+    ///     "{ok,[97,98,99],<<97,98,99>>,'Hello',1.0e10}"
+    /// );
+    /// assert_eq!(
+    ///     term.to_erlang_string(&PrintOptions::new().line_width(20)),
+    ///     "{ok,\"abc\",\n    <<\"abc\">>,\n    'Hello',\n    1.0e10}"
+    /// );
+    /// assert_eq!(
+    ///     term.to_erlang_string(&PrintOptions::new().depth(4)),
+    ///     r#"{ok,"a"...,<<...>>,...}"#
+    /// );
+    /// assert_eq!(
+    ///     term.to_erlang_string(&PrintOptions::new().chars_limit(10)),
+    ///     r#"{ok,"abc",...(5 more elements)"#
+    /// );
+    /// ```
+    pub fn to_erlang_string(&self, options: &PrintOptions) -> String {
+        let mut out = String::new();
+        let limit = (options.chars_limit != usize::MAX).then(|| Limit::new(options.chars_limit));
+        let printer = Printer {
+            depth: options.depth,
+            limit: limit.as_ref(),
+            ..match options.style {
+                PrintStyle::Write => Printer::WRITE,
+                PrintStyle::Pretty => Printer::PRETTY,
+            }
+        };
+        match options.style {
+            PrintStyle::Write => printer.term(&mut out, self),
+            PrintStyle::Pretty => printer.pretty(&mut out, self, options.line_width, 0),
+        }
+        // Closing brackets may follow the limit, but no element does.
+        let end = out
+            .char_indices()
+            .nth(options.chars_limit)
+            .map_or(out.len(), |(end, _)| end);
+        let elided = limit.map_or(0, |limit| limit.elided.get());
+        if end < out.len() || elided != 0 {
+            out.truncate(end);
+            out.push_str("...");
+            match elided {
+                0 => {}
+                1 => out.push_str("(1 more element)"),
+                n => out.push_str(&format!("({n} more elements)")),
+            }
+        }
+        out
+    }
+}
+
+/// Writes a term for `Display`: `~p` on a single line, or with lines of 80 characters for `{:#}`.
+pub(crate) fn display_term(f: &mut fmt::Formatter<'_>, term: &Term) -> fmt::Result {
+    let mut out = String::new();
+    if f.alternate() {
+        Printer::PRETTY.pretty(&mut out, term, 80, 0);
+    } else {
+        Printer::PRETTY.term(&mut out, term);
+    }
+    f.write_str(&out)
+}
+
+/// Writes a part of a term for `Display` in the `~p` style.
+pub(crate) fn display(
+    f: &mut fmt::Formatter<'_>,
+    write: impl FnOnce(&Printer<'_>, &mut String),
+) -> fmt::Result {
+    let mut out = String::new();
+    write(&Printer::PRETTY, &mut out);
+    f.write_str(&out)
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct Printer<'a> {
+    /// Whether printable lists and binaries are printed as strings (`~p`).
+    strings: bool,
+
+    /// Separator between map keys and values.
+    arrow: &'static str,
+
+    /// Depth of `~P` and `~W`: terms at depth 0 are printed as `...`.
+    depth: usize,
+
+    /// Number of characters after which no more elements are written.
+    limit: Option<&'a Limit>,
+}
+impl Printer<'_> {
+    const WRITE: Self = Self {
+        strings: false,
+        arrow: "=>",
+        depth: usize::MAX,
+        limit: None,
+    };
+    const PRETTY: Self = Self {
+        strings: true,
+        arrow: " => ",
+        depth: usize::MAX,
+        limit: None,
+    };
+
+    /// Returns the printer of the `i`th element of a list, tuple, map or binary.
+    fn element(&self, i: usize) -> Self {
+        Self {
+            depth: self.depth.saturating_sub(i + 1),
+            ..*self
+        }
+    }
+
+    /// Returns how many of `len` elements are printed before `...`.
+    fn shown(&self, len: usize) -> usize {
+        len.min(self.depth.saturating_sub(1))
+    }
+
+    /// Returns how many characters may still be written to `out`.
+    fn remaining(&self, out: &str) -> usize {
+        self.limit.map_or(usize::MAX, |limit| limit.remaining(out))
+    }
+
+    /// Returns whether the limit is reached, in which case the `n` elements
+    /// which would have been written next are counted as left out.
+    fn elide(&self, out: &str, n: usize) -> bool {
+        match self.limit {
+            Some(limit) if limit.remaining(out) == 0 => {
+                limit.elided.set(limit.elided.get() + n);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns whether `term` printed in a single line fits in `available` characters.
+    fn fits(&self, term: &Term, available: usize) -> bool {
+        // Measuring stops after `available` characters, so that it is bounded by the width.
+        let limit = Limit::new(available.saturating_add(1));
+        let mut flat = String::new();
+        Printer {
+            limit: Some(&limit),
+            ..*self
+        }
+        .term(&mut flat, term);
+        limit.elided.get() == 0 && flat.chars().count() <= available
+    }
+
+    pub(crate) fn term(&self, out: &mut String, term: &Term) {
+        if self.depth == 0 {
+            out.push_str("...");
+            return;
+        }
+        match term {
+            Term::Atom(x) => self.atom(out, &x.name),
+            Term::FixInteger(x) => out.push_str(&x.value.to_string()),
+            Term::BigInteger(x) => out.push_str(&x.value.to_string()),
+            Term::Float(x) => self.float(out, x.value),
+            Term::Pid(x) => self.pid(out, x),
+            Term::Port(x) => self.port(out, x),
+            Term::Reference(x) => self.reference(out, x),
+            Term::ExternalFun(x) => self.external_fun(out, x),
+            Term::InternalFun(x) => self.internal_fun(out, x),
+            Term::Binary(x) => self.bits(out, &x.bytes, None),
+            Term::BitBinary(x) => {
+                let (bytes, tail) = bit_binary_parts(&x.bytes, x.tail_bits_size);
+                self.bits(out, bytes, tail)
+            }
+            Term::ByteList(x) => self.byte_list(out, &x.bytes),
+            Term::List(x) => self.list(out, &x.elements, None),
+            Term::ImproperList(x) => self.list(out, &x.elements, Some(&x.last)),
+            Term::Tuple(x) => self.tuple(out, &x.elements),
+            Term::Map(x) => self.map(out, x),
+        }
+    }
+
+    /// Writes an atom, quoted unless it reads back as the same atom without quotes.
+    pub(crate) fn atom(&self, out: &mut String, name: &str) {
+        if needs_quotes(name) {
+            write_quoted(out, name, '\'', true);
+        } else {
+            out.push_str(name);
+        }
+    }
+
+    pub(crate) fn float(&self, out: &mut String, value: f64) {
+        write_float(out, value);
+    }
+
+    pub(crate) fn pid(&self, out: &mut String, pid: &Pid) {
+        out.push('<');
+        self.atom(out, &pid.node.name);
+        out.push_str(&format!(".{}.{}>", pid.id, pid.serial));
+    }
+
+    pub(crate) fn port(&self, out: &mut String, port: &Port) {
+        out.push_str("#Port<");
+        self.atom(out, &port.node.name);
+        out.push_str(&format!(".{}>", port.id));
+    }
+
+    pub(crate) fn reference(&self, out: &mut String, reference: &Reference) {
+        out.push_str("#Ref<");
+        self.atom(out, &reference.node.name);
+        for n in &reference.id {
+            out.push_str(&format!(".{n}"));
+        }
+        out.push('>');
+    }
+
+    pub(crate) fn external_fun(&self, out: &mut String, fun: &ExternalFun) {
+        out.push_str("fun ");
+        self.atom(out, &fun.module.name);
+        out.push(':');
+        self.atom(out, &fun.function.name);
+        out.push_str(&format!("/{}", fun.arity));
+    }
+
+    pub(crate) fn internal_fun(&self, out: &mut String, fun: &InternalFun) {
+        let (module, index, uniq) = match fun {
+            InternalFun::Old {
+                module,
+                index,
+                uniq,
+                ..
+            } => (module, i64::from(*index), BigInt::from(*uniq)),
+            InternalFun::New {
+                module,
+                index,
+                uniq,
+                ..
+            } => (
+                module,
+                i64::from(*index),
+                BigInt::from_bytes_be(Sign::Plus, uniq),
+            ),
+        };
+        out.push_str("#Fun<");
+        self.atom(out, &module.name);
+        out.push_str(&format!(".{index}.{uniq}>"));
+    }
+
+    /// Writes a bitstring of whole `bytes` followed by the `(value, bits)` of a partial byte.
+    pub(crate) fn bits(&self, out: &mut String, bytes: &[u8], tail: Option<(u8, u8)>) {
+        out.push_str("<<");
+        let len = bytes.len() + usize::from(tail.is_some());
+        let shown = self.shown(len);
+        let string = self.is_string(bytes);
+        let tail = tail.filter(|_| shown > bytes.len());
+        let bytes = &bytes[..shown.min(bytes.len())];
+        if string {
+            if !self.quoted(out, bytes.iter().map(|&b| char::from(b)), bytes.len()) {
+                self.elide(out, usize::from(tail.is_some()));
+                return;
+            }
+        } else {
+            for (i, b) in bytes.iter().enumerate() {
+                if self.elide(out, shown - i) {
+                    return;
+                }
+                if i != 0 {
+                    out.push(',');
+                }
+                out.push_str(&b.to_string());
+            }
+        }
+        if let Some((value, bits)) = tail {
+            if self.elide(out, 1) {
+                return;
+            }
+            if !bytes.is_empty() {
+                out.push(',');
+            }
+            out.push_str(&format!("{value}:{bits}"));
+        }
+        if shown < len {
+            if shown != 0 && !string {
+                out.push(',');
+            }
+            out.push_str("...");
+        }
+        out.push_str(">>");
+    }
+
+    pub(crate) fn byte_list(&self, out: &mut String, bytes: &[u8]) {
+        if self.is_string(bytes) {
+            self.string(out, bytes.iter().map(|&b| char::from(b)), bytes.len());
+            return;
+        }
+        out.push('[');
+        let shown = self.shown(bytes.len());
+        for (i, b) in bytes[..shown].iter().enumerate() {
+            if self.elide(out, shown - i) {
+                return;
+            }
+            if i != 0 {
+                out.push(',');
+            }
+            out.push_str(&b.to_string());
+        }
+        if shown < bytes.len() {
+            out.push_str(if shown == 0 { "..." } else { "|..." });
+        }
+        out.push(']');
+    }
+
+    pub(crate) fn list(&self, out: &mut String, elements: &[Term], tail: Option<&Term>) {
+        if tail.is_none() && self.is_printable_list(elements) {
+            self.string(out, elements.iter().map(list_char), elements.len());
+            return;
+        }
+        out.push('[');
+        self.elements(out, elements, tail, '|');
+        out.push(']');
+    }
+
+    pub(crate) fn tuple(&self, out: &mut String, elements: &[Term]) {
+        out.push('{');
+        self.elements(out, elements, None, ',');
+        out.push('}');
+    }
+
+    pub(crate) fn map(&self, out: &mut String, map: &Map) {
+        out.push_str("#{");
+        let shown = self.shown(map.map.len());
+        // Each entry takes at least one character, so those past the limit are not sorted.
+        let entries =
+            crate::order::first_entries(map, shown.min(self.remaining(out).saturating_add(1)));
+        for (i, (k, v)) in entries.into_iter().enumerate() {
+            if self.elide(out, shown - i) {
+                return;
+            }
+            if i != 0 {
+                out.push(',');
+            }
+            self.element(i).term(out, k);
+            out.push_str(self.arrow);
+            self.element(i).term(out, v);
+        }
+        if shown < map.map.len() {
+            out.push_str(if shown == 0 { "..." } else { ",..." });
+        }
+        out.push('}');
+    }
+
+    /// Writes the elements within the depth, followed by `separator` and `...` if some are not,
+    /// or by `|` and `tail` if all are.
+    fn elements(&self, out: &mut String, elements: &[Term], tail: Option<&Term>, separator: char) {
+        let shown = self.shown(elements.len());
+        let tail = tail.filter(|_| shown == elements.len());
+        for (i, x) in elements[..shown].iter().enumerate() {
+            if self.elide(out, shown - i + usize::from(tail.is_some())) {
+                return;
+            }
+            if i != 0 {
+                out.push(',');
+            }
+            self.element(i).term(out, x);
+        }
+        if shown < elements.len() {
+            if shown != 0 {
+                out.push(separator);
+            }
+            out.push_str("...");
+        } else if let Some(tail) = tail {
+            if self.elide(out, 1) {
+                return;
+            }
+            out.push('|');
+            self.element(elements.len()).term(out, tail);
+        }
+    }
+
+    /// Writes a string of `len` characters, followed by `...` if they are not all within the depth.
+    fn string(&self, out: &mut String, text: impl Iterator<Item = char>, len: usize) {
+        let shown = self.shown(len);
+        if self.quoted(out, text.take(shown), shown) && shown < len {
+            out.push_str("...");
+        }
+    }
+
+    /// Writes `len` characters between double quotes.
+    ///
+    /// Returns whether they were all written before the limit.
+    fn quoted(&self, out: &mut String, text: impl Iterator<Item = char>, len: usize) -> bool {
+        out.push('"');
+        for (i, c) in text.enumerate() {
+            if self.elide(out, len - i) {
+                return false;
+            }
+            write_quoted_char(out, c, '"', false);
+        }
+        out.push('"');
+        true
+    }
+
+    /// Returns whether non-empty printable Latin-1 `bytes` are printed as a string.
+    fn is_string(&self, bytes: &[u8]) -> bool {
+        self.strings
+            && self.depth > 1
+            && !bytes.is_empty()
+            && bytes.iter().all(|&b| is_printable(u32::from(b)))
+    }
+
+    /// Returns whether a non-empty list of printable Latin-1 characters is printed as a string.
+    fn is_printable_list(&self, elements: &[Term]) -> bool {
+        self.strings
+            && self.depth > 1
+            && !elements.is_empty()
+            && elements.iter().all(|x| {
+                matches!(x, Term::FixInteger(x) if u32::try_from(x.value).is_ok_and(is_printable))
+            })
+    }
+
+    /// Writes `term`, breaking lists, tuples and maps which do not fit in `width`
+    /// so that their elements are aligned after the opening bracket.
+    ///
+    /// `trailing` is the number of characters which will follow the term on its last line.
+    fn pretty(&self, out: &mut String, term: &Term, width: usize, trailing: usize) {
+        let available = width.saturating_sub(column(out) + trailing);
+        // Any term fits in an unlimited width, and measuring it could take as long as printing it.
+        if width == usize::MAX || self.depth <= 1 || self.fits(term, available) {
+            self.term(out, term);
+            return;
+        }
+        match term {
+            Term::Tuple(x) if !x.elements.is_empty() => {
+                self.pretty_elements(out, ("{", "}"), &x.elements, None, width, trailing);
+            }
+            Term::List(x) if !self.is_printable_list(&x.elements) && !x.elements.is_empty() => {
+                self.pretty_elements(out, ("[", "]"), &x.elements, None, width, trailing);
+            }
+            Term::ImproperList(x) => {
+                self.pretty_elements(out, ("[", "]"), &x.elements, Some(&x.last), width, trailing);
+            }
+            Term::ByteList(x) if !self.is_string(&x.bytes) && !x.bytes.is_empty() => {
+                // Each element takes at least one character, so those past the limit are not built.
+                let len = x.bytes.len().min(self.remaining(out).saturating_add(1));
+                let elements = x.bytes[..len]
+                    .iter()
+                    .map(|&b| Term::from(crate::FixInteger::from(b)));
+                let elements: Vec<_> = elements.collect();
+                self.pretty_elements(out, ("[", "]"), &elements, None, width, trailing);
+                // Counts the elements which were not built as left out too.
+                let unbuilt = self.shown(x.bytes.len()) - self.shown(len);
+                if unbuilt != 0 {
+                    self.elide(out, unbuilt);
+                }
+            }
+            Term::Map(x) if !x.map.is_empty() => self.pretty_map(out, x, width, trailing),
+            _ => self.term(out, term),
+        }
+    }
+
+    fn pretty_elements(
+        &self,
+        out: &mut String,
+        (open, close): (&str, &str),
+        elements: &[Term],
+        tail: Option<&Term>,
+        width: usize,
+        trailing: usize,
+    ) {
+        out.push_str(open);
+        let shown = self.shown(elements.len());
+        let (mut elements, elision) = match shown < elements.len() {
+            true if open == "[" => (&elements[..shown], "|..."),
+            true => (&elements[..shown], ",..."),
+            false => (elements, ""),
+        };
+        let tail = tail.filter(|_| elision.is_empty());
+        let mut first = 0;
+        // A tagged tuple keeps its tag on the first line: `{error,{badarg,...`.
+        if open == "{"
+            && elements.len() > 1
+            && let Term::Atom(tag) = &elements[0]
+        {
+            if self.elide(out, elements.len()) {
+                return;
+            }
+            self.atom(out, &tag.name);
+            out.push(',');
+            elements = &elements[1..];
+            first = 1;
+        }
+        let indent = column(out);
+        // Lists of numbers and atoms fill their lines; others have one element per line.
+        let fill = elements.iter().chain(tail).all(is_simple);
+        for (i, x) in elements.iter().enumerate() {
+            if self.elide(out, elements.len() - i + usize::from(tail.is_some())) {
+                return;
+            }
+            let last = i + 1 == elements.len() && tail.is_none();
+            let after = if last {
+                elision.len() + close.len() + trailing
+            } else {
+                1
+            };
+            if i != 0 {
+                out.push(',');
+                let fits = fill && self.fits(x, width.saturating_sub(column(out) + after));
+                if !fits {
+                    newline(out, indent);
+                }
+            }
+            self.element(first + i).pretty(out, x, width, after);
+        }
+        out.push_str(elision);
+        if let Some(tail) = tail {
+            if self.elide(out, 1) {
+                return;
+            }
+            out.push('|');
+            self.element(first + elements.len())
+                .pretty(out, tail, width, close.len() + trailing);
+        }
+        out.push_str(close);
+    }
+
+    fn pretty_map(&self, out: &mut String, map: &Map, width: usize, trailing: usize) {
+        out.push_str("#{");
+        let indent = column(out);
+        let len = map.map.len();
+        let shown = self.shown(len);
+        // Each entry takes at least one character, so those past the limit are not sorted.
+        let entries =
+            crate::order::first_entries(map, shown.min(self.remaining(out).saturating_add(1)));
+        for (i, (k, v)) in entries.into_iter().enumerate() {
+            if self.elide(out, shown - i) {
+                return;
+            }
+            if i != 0 {
+                out.push(',');
+                newline(out, indent);
+            }
+            let after = if i + 1 == len { 1 + trailing } else { 1 };
+            self.element(i).pretty(out, k, width, self.arrow.len());
+            out.push_str(self.arrow);
+            self.element(i).pretty(out, v, width, after);
+        }
+        if shown < len {
+            out.push(',');
+            newline(out, indent);
+            out.push_str("...");
+        }
+        out.push('}');
+    }
+}
+
+/// Character limit of [`Term::to_erlang_string`], shared by the printers of all the subterms.
+pub(crate) struct Limit {
+    chars: usize,
+
+    /// Length of the output in bytes and characters when it was last counted.
+    counted: Cell<(usize, usize)>,
+
+    /// Number of elements left out because the limit was reached.
+    elided: Cell<usize>,
+}
+impl Limit {
+    fn new(chars: usize) -> Self {
+        Self {
+            chars,
+            counted: Cell::new((0, 0)),
+            elided: Cell::new(0),
+        }
+    }
+
+    /// Returns how many characters may still be written to `out`.
+    ///
+    /// `out` only grows, so that only the characters written since the last call are counted.
+    fn remaining(&self, out: &str) -> usize {
+        let (bytes, chars) = self.counted.get();
+        let chars = chars + out[bytes..].chars().count();
+        self.counted.set((out.len(), chars));
+        self.chars.saturating_sub(chars)
+    }
+}
+
+/// Returns the character of an element of a printable list.
+fn list_char(x: &Term) -> char {
+    match x {
+        Term::FixInteger(x) => u32::try_from(x.value)
+            .ok()
+            .and_then(char::from_u32)
+            .expect("printable"),
+        _ => unreachable!("printable"),
+    }
+}
+
+/// Splits a `BitBinary` into its whole bytes and the `(value, bits)` of its partial last byte.
+pub(crate) fn bit_binary_parts(bytes: &[u8], tail_bits_size: u8) -> (&[u8], Option<(u8, u8)>) {
+    match bytes.split_last() {
+        None => (bytes, None),
+        Some(_) if tail_bits_size >= 8 => (bytes, None),
+        Some((_, whole)) if tail_bits_size == 0 => (whole, None),
+        Some((&last, whole)) => {
+            let mask = (1u8 << tail_bits_size) - 1;
+            (whole, Some((last & mask, tail_bits_size)))
+        }
+    }
+}
+
+/// Writes the shortest representation reading back as the same float, as `io_lib_format:fwrite_g/1`.
+pub(crate) fn write_float(out: &mut String, value: f64) {
+    if value.is_sign_negative() {
+        out.push('-');
+    }
+    if value == 0.0 {
+        out.push_str("0.0");
+        return;
+    }
+    // `{:e}` gives the shortest digits which read back as the same float, e.g. "1.2345e3".
+    let scientific = format!("{:e}", value.abs());
+    let (mantissa, exponent) = scientific.split_once('e').expect("scientific notation");
+    let digits = mantissa.replace('.', "");
+    let exponent: i64 = exponent.parse().expect("integer exponent");
+    out.push_str(&fwrite_g(&digits, exponent + 1));
+}
+
+/// Returns the column of the end of `out`, which starts at column 0.
+pub(crate) fn column(out: &str) -> usize {
+    out.rsplit('\n').next().unwrap_or("").chars().count()
+}
+
+pub(crate) fn newline(out: &mut String, indent: usize) {
+    out.push('\n');
+    out.extend(std::iter::repeat_n(' ', indent));
+}
+
+fn is_simple(term: &Term) -> bool {
+    matches!(
+        term,
+        Term::Atom(_) | Term::FixInteger(_) | Term::BigInteger(_) | Term::Float(_)
+    )
+}
+
+/// Returns whether `c` is a character of `io_lib:printable_latin1_list/1`.
+fn is_printable(c: u32) -> bool {
+    matches!(c, 8..=13 | 27 | 32..=126 | 160..=255)
+}
+
+/// Formats a float of the shortest `digits` such that the value is `0.digits * 10^place`.
+fn fwrite_g(digits: &str, place: i64) -> String {
+    let len = digits.len() as i64;
+    if place == 0 {
+        return format!("0.{digits}");
+    }
+    if (0..len).contains(&place) {
+        let (integer, fraction) = digits.split_at(place as usize);
+        return format!("{integer}.{fraction}");
+    }
+    // Uses an exponent only if it is shorter than padding with zeros.
+    let exponent = (place - 1).to_string();
+    let exponent_dot = if len == 1 { 2 } else { 1 };
+    let exponent_cost = exponent.len() as i64 + 1 + exponent_dot;
+    if place < 0 && 2 - place <= exponent_cost {
+        format!("0.{}{digits}", "0".repeat((-place) as usize))
+    } else if place > 0 && place - len + 2 <= exponent_cost {
+        format!("{digits}{}.0", "0".repeat((place - len) as usize))
+    } else {
+        let (first, rest) = digits.split_at(1);
+        let rest = if rest.is_empty() { "0" } else { rest };
+        format!("{first}.{rest}e{exponent}")
+    }
+}
+
+const RESERVED_WORDS: &[&str] = &[
+    "after", "and", "andalso", "band", "begin", "bnot", "bor", "bsl", "bsr", "bxor", "case",
+    "catch", "cond", "div", "else", "end", "fun", "if", "let", "maybe", "not", "of", "or",
+    "orelse", "receive", "rem", "try", "when", "xor",
+];
+
+/// Returns whether an atom must be quoted, as `io_lib:quote_atom/2`.
+fn needs_quotes(name: &str) -> bool {
+    let mut chars = name.chars();
+    let starts_lower = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_lowercase() || (('ß'..='ÿ').contains(&c) && c != '÷'));
+    let name_chars = chars.all(|c| {
+        c.is_ascii_alphanumeric()
+            || c == '_'
+            || c == '@'
+            || (('ß'..='ÿ').contains(&c) && c != '÷')
+            || (('À'..='Þ').contains(&c) && c != '×')
+    });
+    !(starts_lower && name_chars) || RESERVED_WORDS.contains(&name)
+}
+
+/// Writes `text` between `quote`s with the escapes of `io_lib:write_string/2`.
+///
+/// Characters above 255 are written as is if `unicode`, and escaped otherwise.
+fn write_quoted(out: &mut String, text: &str, quote: char, unicode: bool) {
+    out.push(quote);
+    for c in text.chars() {
+        write_quoted_char(out, c, quote, unicode);
+    }
+    out.push(quote);
+}
+
+/// Writes a character of a quoted text, as [`write_quoted`] does.
+fn write_quoted_char(out: &mut String, c: char, quote: char, unicode: bool) {
+    match c {
+        '\\' => out.push_str("\\\\"),
+        c if c == quote => {
+            out.push('\\');
+            out.push(c);
+        }
+        ' '..='~' | '\u{a0}'..='\u{ff}' => out.push(c),
+        '\n' => out.push_str("\\n"),
+        '\r' => out.push_str("\\r"),
+        '\t' => out.push_str("\\t"),
+        '\u{b}' => out.push_str("\\v"),
+        '\u{8}' => out.push_str("\\b"),
+        '\u{c}' => out.push_str("\\f"),
+        '\u{1b}' => out.push_str("\\e"),
+        '\u{7f}' => out.push_str("\\d"),
+        c if u32::from(c) < 0xa0 => out.push_str(&format!("\\{:03o}", u32::from(c))),
+        c if unicode => out.push(c),
+        c => out.push_str(&format!("\\x{{{:X}}}", u32::from(c))),
+    }
+}
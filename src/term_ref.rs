@@ -0,0 +1,151 @@
+use super::*;
+use std::borrow::Cow;
+
+/// Term borrowing its atoms, binaries and byte lists from the input bytes.
+///
+/// This is useful to decode large messages without copying their binaries.
+/// Other variants, such as pids and funs, are decoded as owned values.
+/// Data in a compressed term cannot be borrowed, so it is decoded as owned `Cow`s.
+///
+/// # Examples
+///
+/// ```
+/// use eetf::{Term, TermRef};
+/// use std::borrow::Cow;
+///
+/// let bytes = [131, 109, 0, 0, 0, 3, 1, 2, 3];
+/// let term = TermRef::decode(&bytes).unwrap();
+/// assert!(matches!(term, TermRef::Binary(Cow::Borrowed(&[1, 2, 3]))));
+/// assert_eq!(term.to_owned(), Term::decode(&bytes[..]).unwrap());
+/// ```
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum TermRef<'a> {
+    Atom(Cow<'a, str>),
+    FixInteger(FixInteger),
+    BigInteger(BigInteger),
+    Float(Float),
+    Pid(Pid),
+    Port(Port),
+    Reference(Box<Reference>),
+    ExternalFun(Box<ExternalFun>),
+    InternalFun(Box<InternalFun>),
+    Binary(Cow<'a, [u8]>),
+    /// Bit string in the wire format.
+    ///
+    /// Unlike [`BitBinary`], the `tail_bits_size` significant bits of the last byte
+    /// are its high-order bits.
+    BitBinary {
+        bytes: Cow<'a, [u8]>,
+        tail_bits_size: u8,
+    },
+    ByteList(Cow<'a, [u8]>),
+    List(Vec<TermRef<'a>>),
+    ImproperList {
+        elements: Vec<TermRef<'a>>,
+        last: Box<TermRef<'a>>,
+    },
+    Tuple(Vec<TermRef<'a>>),
+    /// Key-value pairs in the order of appearance.
+    Map(Vec<(TermRef<'a>, TermRef<'a>)>),
+}
+impl<'a> TermRef<'a> {
+    /// Decodes a term borrowing from `bytes`.
+    pub fn decode(bytes: &'a [u8]) -> Result<Self, DecodeError> {
+        codec::Decoder::new(io::Cursor::new(bytes)).decode_borrowed()
+    }
+
+    /// Decodes a term borrowing from `bytes` with the given options.
+    pub fn decode_with_options(
+        bytes: &'a [u8],
+        options: DecodeOptions,
+    ) -> Result<Self, DecodeError> {
+        codec::Decoder::with_options(io::Cursor::new(bytes), options).decode_borrowed()
+    }
+
+    /// Converts the term into an owned [`Term`] by copying the borrowed data.
+    pub fn to_owned(&self) -> Term {
+        Term::from(self.clone())
+    }
+}
+impl From<TermRef<'_>> for Term {
+    fn from(x: TermRef<'_>) -> Self {
+        let into_vec = |elements: Vec<TermRef<'_>>| elements.into_iter().map(Term::from).collect();
+        match x {
+            TermRef::Atom(x) => Term::from(Atom::from(x.into_owned())),
+            TermRef::FixInteger(x) => Term::from(x),
+            TermRef::BigInteger(x) => Term::from(x),
+            TermRef::Float(x) => Term::from(x),
+            TermRef::Pid(x) => Term::from(x),
+            TermRef::Port(x) => Term::from(x),
+            TermRef::Reference(x) => Term::Reference(x),
+            TermRef::ExternalFun(x) => Term::ExternalFun(x),
+            TermRef::InternalFun(x) => Term::InternalFun(x),
+            TermRef::Binary(x) => Term::from(Binary::from(x.into_owned())),
+            TermRef::BitBinary {
+                bytes,
+                tail_bits_size,
+            } => {
+                let mut bytes = bytes.into_owned();
+                if let Some(last) = bytes.last_mut() {
+                    *last = last
+                        .checked_shr(8 - u32::from(tail_bits_size.min(8)))
+                        .unwrap_or(0);
+                }
+                Term::from(BitBinary::from((bytes, tail_bits_size)))
+            }
+            TermRef::ByteList(x) => Term::from(ByteList::from(x.into_owned())),
+            TermRef::List(x) => Term::from(List::from(into_vec(x))),
+            TermRef::ImproperList { elements, last } => {
+                Term::from(ImproperList::from((into_vec(elements), Term::from(*last))))
+            }
+            TermRef::Tuple(x) => Term::from(Tuple::from(into_vec(x))),
+            TermRef::Map(x) => Term::from(Map::from(
+                x.into_iter()
+                    .map(|(k, v)| (Term::from(k), Term::from(v)))
+                    .collect::<HashMap<_, _>>(),
+            )),
+        }
+    }
+}
+impl From<Term> for TermRef<'static> {
+    fn from(x: Term) -> Self {
+        let from_vec = |elements: Vec<Term>| elements.into_iter().map(TermRef::from).collect();
+        match x {
+            Term::Atom(x) => TermRef::Atom(Cow::Owned(x.name)),
+            Term::FixInteger(x) => TermRef::FixInteger(x),
+            Term::BigInteger(x) => TermRef::BigInteger(x),
+            Term::Float(x) => TermRef::Float(x),
+            Term::Pid(x) => TermRef::Pid(x),
+            Term::Port(x) => TermRef::Port(x),
+            Term::Reference(x) => TermRef::Reference(x),
+            Term::ExternalFun(x) => TermRef::ExternalFun(x),
+            Term::InternalFun(x) => TermRef::InternalFun(x),
+            Term::Binary(x) => TermRef::Binary(Cow::Owned(x.bytes)),
+            Term::BitBinary(x) => {
+                let mut bytes = x.bytes;
+                if let Some(last) = bytes.last_mut() {
+                    *last = last
+                        .checked_shl(8 - u32::from(x.tail_bits_size.min(8)))
+                        .unwrap_or(0);
+                }
+                TermRef::BitBinary {
+                    bytes: Cow::Owned(bytes),
+                    tail_bits_size: x.tail_bits_size,
+                }
+            }
+            Term::ByteList(x) => TermRef::ByteList(Cow::Owned(x.bytes)),
+            Term::List(x) => TermRef::List(from_vec(x.elements)),
+            Term::ImproperList(x) => TermRef::ImproperList {
+                elements: from_vec(x.elements),
+                last: Box::new(TermRef::from(*x.last)),
+            },
+            Term::Tuple(x) => TermRef::Tuple(from_vec(x.elements)),
+            Term::Map(x) => TermRef::Map(
+                x.map
+                    .into_iter()
+                    .map(|(k, v)| (TermRef::from(k), TermRef::from(v)))
+                    .collect(),
+            ),
+        }
+    }
+}
@@ -4,7 +4,13 @@ use std::io::Cursor;
 #[test]
 fn atom_test() {
     // Display
-    assert_eq!("'foo'", Atom::from("foo").to_string());
+    assert_eq!("foo", Atom::from("foo").to_string());
+    assert_eq!("node@host_1", Atom::from("node@host_1").to_string());
+    assert_eq!("'Foo'", Atom::from("Foo").to_string());
+    assert_eq!("'foo bar'", Atom::from("foo bar").to_string());
+    assert_eq!("''", Atom::from("").to_string());
+    assert_eq!("'receive'", Atom::from("receive").to_string());
+    assert_eq!(r"'a\nb'", Atom::from("a\nb").to_string());
     assert_eq!(r#"'fo\'o'"#, Atom::from(r#"fo'o"#).to_string());
     assert_eq!(r#"'fo\\o'"#, Atom::from(r#"fo\o"#).to_string());
 
@@ -95,13 +101,13 @@ fn integer_test() {
 #[test]
 fn float_test() {
     // Display
-    assert_eq!("123", Float::try_from(123.0).unwrap().to_string());
+    assert_eq!("123.0", Float::try_from(123.0).unwrap().to_string());
     assert_eq!("123.4", Float::try_from(123.4).unwrap().to_string());
     assert_eq!("-123.4", Float::try_from(-123.4).unwrap().to_string());
 
     // Decode
     assert_eq!(
-        Ok(Float::try_from("1.23".parse::<f32>().unwrap() as f64).unwrap()),
+        Ok(Float::try_from(1.23).unwrap()),
         decode(&[
             131, 99, 49, 46, 50, 50, 57, 57, 57, 57, 57, 57, 57, 57, 57, 57, 57, 57, 57, 57, 56,
             50, 50, 52, 101, 43, 48, 48, 0, 0, 0, 0, 0
@@ -125,18 +131,18 @@ fn float_test() {
         encode(Term::from(Float::try_from(123.456).unwrap()))
     );
 
-    for f in &[std::f32::NAN, std::f32::INFINITY, std::f32::NEG_INFINITY] {
-        match Float::try_from(*f) {
-            Err(_) => assert!(true),
-            _ => assert!(false, "Non-finite value must not be converted"),
-        }
+    for f in &[f32::NAN, f32::INFINITY, f32::NEG_INFINITY] {
+        assert!(
+            Float::try_from(*f).is_err(),
+            "Non-finite value must not be converted"
+        );
     }
 
-    for f in &[std::f64::NAN, std::f64::INFINITY, std::f64::NEG_INFINITY] {
-        match Float::try_from(*f) {
-            Err(_) => assert!(true),
-            _ => assert!(false, "Non-finite value must not be converted"),
-        }
+    for f in &[f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+        assert!(
+            Float::try_from(*f).is_err(),
+            "Non-finite value must not be converted"
+        );
     }
 }
 
@@ -144,7 +150,7 @@ fn float_test() {
 fn pid_test() {
     // Display
     assert_eq!(
-        r#"<'nonode@nohost'.1.2>"#,
+        "<nonode@nohost.1.2>",
         Pid::from(("nonode@nohost", 1, 2)).to_string()
     );
 
@@ -181,7 +187,7 @@ fn pid_test() {
 fn port_test() {
     // Display
     assert_eq!(
-        r#"#Port<'nonode@nohost'.1>"#,
+        "#Port<nonode@nohost.1>",
         Port::from(("nonode@nohost", 1)).to_string()
     );
 
@@ -209,7 +215,7 @@ fn port_test() {
 fn reference_test() {
     // Display
     assert_eq!(
-        r#"#Ref<'nonode@nohost'.1>"#,
+        "#Ref<nonode@nohost.1>",
         Reference::from(("nonode@nohost", 1)).to_string()
     );
 
@@ -241,7 +247,7 @@ fn reference_test() {
 fn external_fun_test() {
     // Display
     assert_eq!(
-        r#"fun 'foo':'bar'/3"#,
+        "fun foo:bar/3",
         ExternalFun::from(("foo", "bar", 3)).to_string()
     );
 
@@ -360,7 +366,7 @@ fn byte_list_test() {
 fn list_test() {
     // Display
     assert_eq!(
-        "['a',1]",
+        "[a,1]",
         List::from(vec![
             Term::from(Atom::from("a")),
             Term::from(FixInteger::from(1))
@@ -388,7 +394,7 @@ fn list_test() {
 fn improper_list_test() {
     // Display
     assert_eq!(
-        "[0,'a'|1]",
+        "[0,a|1]",
         ImproperList::from((
             vec![Term::from(FixInteger::from(0)), Term::from(Atom::from("a"))],
             Term::from(FixInteger::from(1))
@@ -419,7 +425,7 @@ fn improper_list_test() {
 fn tuple_test() {
     // Display
     assert_eq!(
-        "{'a',1}",
+        "{a,1}",
         Tuple::from(vec![
             Term::from(Atom::from("a")),
             Term::from(FixInteger::from(1))
@@ -459,8 +465,7 @@ fn map_test() {
 
     // Display
     let as_str = map.to_string();
-    // Hashmap Iter is not deterministic, so we need to check both possible outputs
-    assert!("#{'a'=>'b',1=>2}" == as_str || "#{1=>2,'a'=>'b'}" == as_str);
+    assert_eq!("#{1 => 2,a => b}", as_str);
     assert_eq!("#{}", Map::from([]).to_string());
 
     // Decode
@@ -532,12 +537,2672 @@ fn compressed_term_truncated_test() {
     // Header (131, 80) + uncompressed size (0, 0, 2, 9) + a few zlib bytes,
     // then cut off mid-stream so the decoder hits EOF before finishing.
     let bytes: &[u8] = &[131, 80, 0, 0, 2, 9, 120, 218, 21, 210, 3, 187];
-    match Term::decode(Cursor::new(bytes)).unwrap_err() {
-        DecodeError::Io(e) => assert_eq!(e.kind(), std::io::ErrorKind::UnexpectedEof),
+    match Term::decode(Cursor::new(bytes)).unwrap_err().into_kind() {
+        DecodeErrorKind::Io(e) => assert_eq!(e.kind(), std::io::ErrorKind::UnexpectedEof),
         e => panic!("expected Io(UnexpectedEof), got {e:?}"),
     }
 }
 
+#[test]
+fn compressed_term_size_test() {
+    fn check(bytes: &[u8]) -> [Result<Term, DecodeError>; 4] {
+        [
+            Term::decode(bytes),
+            Term::decode_from_slice(bytes).map(|(term, _)| term),
+            TermRef::decode(bytes).map(Term::from),
+            match IncrementalDecoder::new().feed(bytes) {
+                Ok(DecodeStatus::Complete(term, _)) => Ok(term),
+                Ok(status) => panic!("expected a term, got {status:?}"),
+                Err(e) => Err(e),
+            },
+        ]
+    }
+    // A `COMPRESSED_TERM` whose data is a single stored deflate block
+    fn stored(size: u32, data: &[u8]) -> Vec<u8> {
+        let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), &x| {
+            let a = (a + u32::from(x)) % 65521;
+            (a, (b + a) % 65521)
+        });
+        let len = data.len() as u16;
+        let mut bytes = vec![131, 80];
+        bytes.extend_from_slice(&size.to_be_bytes());
+        bytes.extend_from_slice(&[0x78, 0x01, 0x01]);
+        bytes.extend_from_slice(&len.to_le_bytes());
+        bytes.extend_from_slice(&(!len).to_le_bytes());
+        bytes.extend_from_slice(data);
+        bytes.extend_from_slice(&((b << 16) | a).to_be_bytes());
+        bytes
+    }
+
+    let one = Term::from(FixInteger::from(1));
+    for result in check(&stored(2, &[97, 1])) {
+        assert_eq!(result.unwrap(), one);
+    }
+    // The declared size is larger or smaller than the decompressed data
+    for result in check(&stored(3, &[97, 1]))
+        .into_iter()
+        .chain(check(&stored(1, &[97, 1])))
+    {
+        assert!(matches!(
+            result.map_err(DecodeError::into_kind),
+            Err(DecodeErrorKind::Io(_))
+        ));
+    }
+    // The decompressed data continues after the term
+    for result in check(&stored(3, &[97, 1, 0])) {
+        assert!(matches!(
+            result.map_err(DecodeError::into_kind),
+            Err(DecodeErrorKind::Io(e)) if e.kind() == std::io::ErrorKind::InvalidData
+        ));
+    }
+    // The declared size counts towards the allocation limit
+    let limits = DecodeOptions::new().max_allocated_bytes(1);
+    assert!(matches!(
+        Term::decode_with_options(&stored(2, &[97, 1])[..], limits).map_err(DecodeError::into_kind),
+        Err(DecodeErrorKind::LimitExceeded { .. })
+    ));
+}
+
+#[test]
+fn compressed_encode_test() {
+    let term = Term::from(List::from(
+        (1..257)
+            .map(|i| Term::from(FixInteger::from(i)))
+            .collect::<Vec<_>>(),
+    ));
+    let uncompressed = encode(term.clone());
+
+    // Compressed
+    let mut buf = Vec::new();
+    term.encode_with_options(&mut buf, EncodeOptions::new().compressed(6))
+        .unwrap();
+    assert_eq!(&buf[..2], &[131, 80]);
+    assert_eq!(
+        &buf[2..6],
+        &(uncompressed.len() as u32 - 1).to_be_bytes()[..]
+    );
+    assert!(buf.len() < uncompressed.len());
+    assert_eq!(decode(&buf), term);
+
+    // Levels 1 to 9 are clamped to the default level
+    for level in 1..=9 {
+        assert_eq!(
+            EncodeOptions::new().compressed(level),
+            EncodeOptions::new().compressed(6)
+        );
+    }
+    assert_ne!(
+        EncodeOptions::new().compressed(0),
+        EncodeOptions::new().compressed(6)
+    );
+
+    // Compression level 0 never makes the payload smaller
+    let mut buf = Vec::new();
+    term.encode_with_options(&mut buf, EncodeOptions::new().compressed(0))
+        .unwrap();
+    assert_eq!(buf, uncompressed);
+
+    // A term too small to benefit from compression is left as is
+    let atom = Term::from(Atom::from("foo"));
+    let mut buf = Vec::new();
+    atom.encode_with_options(&mut buf, EncodeOptions::new().compressed(9))
+        .unwrap();
+    assert_eq!(buf, vec![131, 119, 3, 102, 111, 111]);
+
+    // ... unless compression is forced
+    for level in [0, 9] {
+        let mut buf = Vec::new();
+        let options = EncodeOptions::new().compressed(level).always_compress();
+        atom.encode_with_options(&mut buf, options).unwrap();
+        assert_eq!(&buf[..6], &[131, 80, 0, 0, 0, 5]);
+        assert_eq!(decode(&buf), atom);
+    }
+
+    // `always_compress` alone does not enable compression
+    let mut buf = Vec::new();
+    atom.encode_with_options(&mut buf, EncodeOptions::new().always_compress())
+        .unwrap();
+    assert_eq!(buf, vec![131, 119, 3, 102, 111, 111]);
+}
+
+#[test]
+fn distribution_header_test() {
+    // Decode: a new cache entry 'foo' at index 0x105 and a reference to it
+    let mut cache = AtomCache::new();
+    let bytes = [
+        131, 68, 1, 9, 5, 3, 102, 111, 111, 104, 2, 97, 2, 82, 0, 104, 1, 82, 0,
+    ];
+    let message = DistributionMessage::decode(Cursor::new(&bytes[..]), &mut cache).unwrap();
+    let foo = Term::from(Atom::from("foo"));
+    assert_eq!(
+        message.control,
+        Term::from(Tuple::from(vec![
+            Term::from(FixInteger::from(2)),
+            foo.clone()
+        ]))
+    );
+    assert_eq!(message.payload, Some(Term::from(Tuple::from(vec![foo]))));
+    assert_eq!(cache.get(0x105), Some(&Atom::from("foo")));
+
+    // Decode: a reference to the existing cache entry
+    let bytes = [131, 68, 1, 1, 5, 82, 0];
+    let message = DistributionMessage::decode(Cursor::new(&bytes[..]), &mut cache).unwrap();
+    assert_eq!(message.control, Term::from(Atom::from("foo")));
+    assert_eq!(message.payload, None);
+
+    // Decode: errors
+    assert!(matches!(
+        DistributionMessage::decode(Cursor::new(&bytes[..]), &mut AtomCache::new())
+            .map_err(DecodeError::into_kind),
+        Err(DecodeErrorKind::MissingAtomCacheEntry { index: 0x105 })
+    ));
+    assert!(matches!(
+        Term::decode(Cursor::new(&bytes[..])).map_err(DecodeError::into_kind),
+        Err(DecodeErrorKind::UnexpectedDistributionHeader)
+    ));
+    assert!(matches!(
+        Term::decode(Cursor::new(&[131, 82, 0])).map_err(DecodeError::into_kind),
+        Err(DecodeErrorKind::InvalidAtomCacheRef { index: 0 })
+    ));
+    assert!(matches!(
+        DistributionMessage::decode(Cursor::new(&[131, 106]), &mut cache)
+            .map_err(DecodeError::into_kind),
+        Err(DecodeErrorKind::MissingDistributionHeader { tag: 106 })
+    ));
+
+    // Encode
+    let long_name = "é".repeat(200);
+    let message = DistributionMessage {
+        control: Term::from(Tuple::from(vec![
+            Term::from(FixInteger::from(6)),
+            Term::from(Pid::from(("foo@localhost", 1, 2))),
+            Term::from(Atom::from("")),
+            Term::from(Atom::from("registered_name")),
+        ])),
+        payload: Some(Term::from(List::from(vec![
+            Term::from(Atom::from("registered_name")),
+            Term::from(Atom::from(long_name.as_str())),
+        ]))),
+    };
+    let mut sender_cache = AtomCache::new();
+    let mut receiver_cache = AtomCache::new();
+    for _ in 0..2 {
+        let mut buf = Vec::new();
+        message.encode(&mut buf, &mut sender_cache).unwrap();
+        assert_eq!(&buf[..3], &[131, 68, 4]);
+        let decoded = DistributionMessage::decode(Cursor::new(&buf), &mut receiver_cache).unwrap();
+        assert_eq!(decoded, message);
+        assert_eq!(sender_cache, receiver_cache);
+    }
+
+    // Encode: messages without atoms have an empty header
+    let message = DistributionMessage {
+        control: Term::from(List::nil()),
+        payload: None,
+    };
+    let mut buf = Vec::new();
+    message.encode(&mut buf, &mut sender_cache).unwrap();
+    assert_eq!(buf, [131, 68, 0, 106]);
+}
+
+#[test]
+fn decode_limits_test() {
+    fn decode_with(bytes: &[u8], options: DecodeOptions) -> DecodeResult {
+        Term::decode_with_options(Cursor::new(bytes), options)
+    }
+    fn limit_of(result: DecodeResult) -> DecodeLimit {
+        match result.map_err(DecodeError::into_kind) {
+            Err(DecodeErrorKind::LimitExceeded { limit, .. }) => limit,
+            r => panic!("expected LimitExceeded, got {r:?}"),
+        }
+    }
+
+    // Huge length fields fail without allocating the requested size
+    let huge_binary = [131, 109, 255, 255, 255, 255, 1, 2, 3];
+    assert!(matches!(
+        Term::decode(Cursor::new(&huge_binary)).map_err(DecodeError::into_kind),
+        Err(DecodeErrorKind::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof
+    ));
+    let huge_list = [131, 108, 255, 255, 255, 255, 97, 1];
+    assert!(matches!(
+        Term::decode(Cursor::new(&huge_list)).map_err(DecodeError::into_kind),
+        Err(DecodeErrorKind::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof
+    ));
+
+    // Limits
+    let options = DecodeOptions::new().max_binary_size(2);
+    assert_eq!(
+        limit_of(decode_with(&huge_binary, options.clone())),
+        DecodeLimit::BinarySize
+    );
+    assert!(decode_with(&[131, 109, 0, 0, 0, 2, 1, 2], options).is_ok());
+
+    let options = DecodeOptions::new().max_allocated_bytes(1024);
+    assert_eq!(
+        limit_of(decode_with(&huge_binary, options.clone())),
+        DecodeLimit::AllocatedBytes
+    );
+    assert_eq!(
+        limit_of(decode_with(&huge_list, options)),
+        DecodeLimit::AllocatedBytes
+    );
+
+    let options = DecodeOptions::new().max_list_length(1);
+    assert_eq!(
+        limit_of(decode_with(&[131, 107, 0, 2, 1, 2], options.clone())),
+        DecodeLimit::ListLength
+    );
+    assert!(decode_with(&[131, 108, 0, 0, 0, 1, 97, 1, 106], options).is_ok());
+
+    let options = DecodeOptions::new().max_tuple_size(1);
+    assert_eq!(
+        limit_of(decode_with(&[131, 104, 2, 97, 1, 97, 2], options)),
+        DecodeLimit::TupleSize
+    );
+
+    let options = DecodeOptions::new().max_map_size(1);
+    let map = [131, 116, 0, 0, 0, 2, 97, 1, 97, 2, 97, 3, 97, 4];
+    assert_eq!(limit_of(decode_with(&map, options)), DecodeLimit::MapSize);
+
+    let nested = [131, 104, 1, 104, 1, 104, 1, 106]; // {{{[]}}}
+    assert!(decode_with(&nested, DecodeOptions::new().max_depth(3)).is_ok());
+    assert_eq!(
+        limit_of(decode_with(&nested, DecodeOptions::new().max_depth(2))),
+        DecodeLimit::Depth
+    );
+
+    let atoms = [131, 104, 2, 119, 1, 97, 119, 1, 98]; // {a,b}
+    assert!(decode_with(&atoms, DecodeOptions::new().max_atoms(2)).is_ok());
+    assert_eq!(
+        limit_of(decode_with(&atoms, DecodeOptions::new().max_atoms(1))),
+        DecodeLimit::AtomCount
+    );
+}
+
+#[test]
+fn allowed_atoms_test() {
+    let bytes = encode(Term::from(Tuple::from(vec![
+        Term::from(Atom::from("ok")),
+        Term::from(Pid::from(("nonode@nohost", 1, 2))),
+    ])));
+
+    let options = DecodeOptions::new().allowed_atoms(["ok", "nonode@nohost"]);
+    assert!(Term::decode_with_options(Cursor::new(&bytes), options).is_ok());
+
+    let options = DecodeOptions::new().allowed_atoms(["ok"]);
+    match Term::decode_with_options(Cursor::new(&bytes), options).map_err(DecodeError::into_kind) {
+        Err(DecodeErrorKind::DisallowedAtom { name }) => assert_eq!(name, "nonode@nohost"),
+        r => panic!("expected DisallowedAtom, got {r:?}"),
+    }
+
+    let options = DecodeOptions::new().atom_filter(|name| name != "ok");
+    match Term::decode_with_options(Cursor::new(&bytes), options).map_err(DecodeError::into_kind) {
+        Err(DecodeErrorKind::DisallowedAtom { name }) => assert_eq!(name, "ok"),
+        r => panic!("expected DisallowedAtom, got {r:?}"),
+    }
+
+    // ATOM_EXT
+    let options = DecodeOptions::new().allowed_atoms(["bar"]);
+    assert!(matches!(
+        Term::decode_with_options(Cursor::new(&[131, 100, 0, 3, 102, 111, 111]), options)
+            .map_err(DecodeError::into_kind),
+        Err(DecodeErrorKind::DisallowedAtom { .. })
+    ));
+}
+
+#[test]
+fn term_ref_test() {
+    use std::borrow::Cow;
+
+    let term = Term::from(Tuple::from(vec![
+        Term::from(Atom::from("foo")),
+        Term::from(Binary::from(vec![1, 2, 3])),
+        Term::from(BitBinary::from((vec![1, 2, 3], 5))),
+        Term::from(ByteList::from("abc")),
+        Term::from(List::from(vec![Term::from(Atom::from("bar"))])),
+        Term::from(ImproperList::from((
+            vec![Term::from(FixInteger::from(1))],
+            Term::from(FixInteger::from(2)),
+        ))),
+        Term::from(Map::from([(
+            Term::from(Atom::from("k")),
+            Term::from(Binary::from(vec![4])),
+        )])),
+        Term::from(Pid::from(("nonode@nohost", 1, 2))),
+    ]));
+    let bytes = encode(term.clone());
+
+    // Decode
+    let term_ref = TermRef::decode(&bytes).unwrap();
+    let TermRef::Tuple(elements) = &term_ref else {
+        panic!("expected a tuple, got {term_ref:?}");
+    };
+    assert!(matches!(elements[0], TermRef::Atom(Cow::Borrowed("foo"))));
+    assert!(matches!(
+        elements[1],
+        TermRef::Binary(Cow::Borrowed(&[1, 2, 3]))
+    ));
+    assert!(matches!(
+        elements[2],
+        TermRef::BitBinary {
+            bytes: Cow::Borrowed(&[1, 2, 24]),
+            tail_bits_size: 5
+        }
+    ));
+    assert!(matches!(
+        elements[3],
+        TermRef::ByteList(Cow::Borrowed(b"abc"))
+    ));
+    assert_eq!(term_ref.to_owned(), term);
+    assert_eq!(TermRef::from(term.clone()), term_ref);
+
+    // Compressed terms are decoded as owned data
+    let mut bytes = Vec::new();
+    let options = EncodeOptions::new().compressed(6).always_compress();
+    term.encode_with_options(&mut bytes, options).unwrap();
+    let term_ref = TermRef::decode(&bytes).unwrap();
+    let TermRef::Tuple(elements) = &term_ref else {
+        panic!("expected a tuple, got {term_ref:?}");
+    };
+    assert!(matches!(elements[1], TermRef::Binary(Cow::Owned(_))));
+    assert_eq!(Term::from(term_ref), term);
+
+    // Truncated input
+    assert!(matches!(
+        TermRef::decode(&[131, 109, 0, 0, 0, 3, 1, 2]).map_err(DecodeError::into_kind),
+        Err(DecodeErrorKind::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof
+    ));
+}
+
+#[test]
+fn incremental_decoder_test() {
+    let plain = Term::from(Tuple::from(vec![
+        Term::from(Atom::from("foo")),
+        Term::from(Binary::from(vec![1, 2, 3])),
+        Term::from(Map::from([(
+            Term::from(FixInteger::from(1)),
+            Term::from(ImproperList::from((
+                vec![Term::from(Float::try_from(1.5).unwrap())],
+                Term::from(Pid::from(("nonode@nohost", 1, 2))),
+            ))),
+        )])),
+        Term::from(Reference::from(("nonode@nohost", vec![1, 2, 3]))),
+        Term::from(BigInteger::from(u64::MAX)),
+    ]));
+    let compressed = Term::from(List::from(
+        (1..300)
+            .map(|i| Term::from(FixInteger::from(i % 7 * 1000)))
+            .collect::<Vec<_>>(),
+    ));
+    let mut input = encode(plain.clone());
+    compressed
+        .encode_with_options(&mut input, EncodeOptions::new().compressed(6))
+        .unwrap();
+    input.extend(encode(plain.clone()));
+
+    // Chunks of various sizes
+    for chunk_size in [1, 2, 3, 7, 64, input.len()] {
+        let mut decoder = IncrementalDecoder::new();
+        let mut terms = Vec::new();
+        for mut chunk in input.chunks(chunk_size) {
+            while let DecodeStatus::Complete(term, consumed) = decoder.feed(chunk).unwrap() {
+                terms.push(term);
+                chunk = &chunk[consumed..];
+            }
+        }
+        assert_eq!(
+            terms,
+            [plain.clone(), compressed.clone(), plain.clone()],
+            "chunk_size={chunk_size}"
+        );
+    }
+
+    // Hint
+    let mut decoder = IncrementalDecoder::new();
+    assert_eq!(
+        decoder.feed(&[131, 109, 0, 0]).unwrap(),
+        DecodeStatus::NeedMore(2)
+    );
+    assert_eq!(decoder.feed(&[0, 3, 1]).unwrap(), DecodeStatus::NeedMore(2));
+    assert_eq!(
+        decoder.feed(&[2, 3]).unwrap(),
+        DecodeStatus::Complete(Term::from(Binary::from(vec![1, 2, 3])), 2)
+    );
+
+    // Errors
+    assert!(matches!(
+        IncrementalDecoder::new()
+            .feed(&[130])
+            .map_err(DecodeError::into_kind),
+        Err(DecodeErrorKind::UnsupportedVersion { version: 130 })
+    ));
+    assert!(matches!(
+        IncrementalDecoder::new()
+            .feed(&[131, 1])
+            .map_err(DecodeError::into_kind),
+        Err(DecodeErrorKind::UnknownTag { tag: 1 })
+    ));
+    let mut corrupted = Vec::new();
+    compressed
+        .encode_with_options(&mut corrupted, EncodeOptions::new().compressed(6))
+        .unwrap();
+    *corrupted.last_mut().unwrap() ^= 1;
+    assert!(IncrementalDecoder::new().feed(&corrupted).is_err());
+    let limits = DecodeOptions::new().max_allocated_bytes(16);
+    assert!(matches!(
+        IncrementalDecoder::with_options(limits).feed(&[131, 109, 0, 0, 1, 0]),
+        Ok(DecodeStatus::NeedMore(_))
+    ));
+    let limits = DecodeOptions::new().max_allocated_bytes(16);
+    assert!(matches!(
+        IncrementalDecoder::with_options(limits)
+            .feed(&[131, 109, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0])
+            .map_err(DecodeError::into_kind),
+        Err(DecodeErrorKind::LimitExceeded { .. })
+    ));
+}
+
+#[test]
+fn term_stream_test() {
+    let terms = vec![
+        Term::from(Atom::from("foo")),
+        Term::from(List::from(
+            (0..1000)
+                .map(|i| Term::from(FixInteger::from(i % 3 * 1000)))
+                .collect::<Vec<_>>(),
+        )),
+        Term::from(Binary::from(vec![1, 2, 3])),
+    ];
+
+    // Write
+    let mut writer = TermWriter::with_options(Vec::new(), EncodeOptions::new().compressed(6));
+    for term in &terms {
+        writer.write_term(term).unwrap();
+    }
+    let bytes = writer.into_inner();
+    assert_eq!(bytes[..6], encode(terms[0].clone())[..]);
+    assert_eq!(bytes[6..8], [131, 80]);
+
+    // Read
+    let decoded = TermReader::new(&bytes[..])
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(decoded, terms);
+    assert_eq!(TermReader::new(&[][..]).count(), 0);
+
+    // Truncated
+    let mut reader = TermReader::new(&bytes[..bytes.len() - 1]);
+    assert_eq!(reader.next().unwrap().unwrap(), terms[0]);
+    assert_eq!(reader.next().unwrap().unwrap(), terms[1]);
+    match reader.next().map(|r| r.map_err(DecodeError::into_kind)) {
+        Some(Err(DecodeErrorKind::Io(e))) => {
+            assert_eq!(e.kind(), std::io::ErrorKind::UnexpectedEof)
+        }
+        other => panic!("unexpected result: {other:?}"),
+    }
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn decode_from_slice_test() {
+    let atom = Term::from(Atom::from("foo"));
+    let mut bytes = encode(atom.clone());
+    let len = bytes.len();
+    assert_eq!(
+        Term::decode_from_slice(&bytes).unwrap(),
+        (atom.clone(), len)
+    );
+    assert_eq!(Term::decode_exact(&bytes).unwrap(), atom);
+
+    bytes.extend([1, 2]);
+    assert_eq!(
+        Term::decode_from_slice(&bytes).unwrap(),
+        (atom.clone(), len)
+    );
+    assert!(matches!(
+        Term::decode_exact(&bytes).map_err(DecodeError::into_kind),
+        Err(DecodeErrorKind::TrailingBytes { offset }) if offset == len
+    ));
+
+    // Compressed terms are not over-read
+    let list = Term::from(List::from(
+        (0..100)
+            .map(|i| Term::from(FixInteger::from(i % 3 * 1000)))
+            .collect::<Vec<_>>(),
+    ));
+    let mut bytes = Vec::new();
+    list.encode_with_options(&mut bytes, EncodeOptions::new().compressed(6))
+        .unwrap();
+    let len = bytes.len();
+    bytes.extend(encode(atom.clone()));
+    assert_eq!(
+        Term::decode_from_slice(&bytes).unwrap(),
+        (list.clone(), len)
+    );
+    assert_eq!(
+        Term::decode_from_slice(&bytes[len..]).unwrap(),
+        (atom.clone(), bytes.len() - len)
+    );
+    assert!(matches!(
+        Term::decode_exact(&bytes).map_err(DecodeError::into_kind),
+        Err(DecodeErrorKind::TrailingBytes { offset }) if offset == len
+    ));
+    assert!(Term::decode_exact(&bytes[..len - 1]).is_err());
+}
+
+#[test]
+fn decode_error_context_test() {
+    let mut elements = vec![Term::from(Atom::from("x")); 17];
+    elements.push(Term::from(Atom::from("zzz")));
+    let mut map = std::collections::HashMap::new();
+    map.insert(
+        Term::from(Atom::from("opts")),
+        Term::from(List::from(elements)),
+    );
+    let term = Term::from(Tuple::from(vec![
+        Term::from(Atom::from("a")),
+        Term::from(Atom::from("b")),
+        Term::from(Map::from(map)),
+    ]));
+    let mut bytes = encode(term);
+    let offset = bytes
+        .windows(5)
+        .position(|x| x == [119, 3, b'z', b'z', b'z'])
+        .unwrap();
+    bytes[offset] = 1;
+
+    let expected_path = [
+        PathSegment::TupleElement(2),
+        PathSegment::MapValue(Term::from(Atom::from("opts"))),
+        PathSegment::ListElement(17),
+    ];
+    let e = Term::decode(Cursor::new(&bytes)).unwrap_err();
+    assert!(matches!(e.kind(), DecodeErrorKind::UnknownTag { tag: 1 }));
+    assert_eq!(e.offset(), Some(offset));
+    assert_eq!(e.path(), expected_path);
+    assert_eq!(
+        e.to_string(),
+        format!("unknown tag 1 at byte {offset} in tuple[2] -> map value for key opts -> list[17]")
+    );
+
+    let e = TermRef::decode(&bytes).unwrap_err();
+    assert_eq!(e.offset(), Some(offset));
+    assert_eq!(e.path(), expected_path);
+
+    let mut decoder = IncrementalDecoder::new();
+    assert!(decoder.feed(&bytes[..offset - 1]).is_ok());
+    assert_eq!(
+        decoder.feed(&bytes[offset - 1..]).unwrap_err().offset(),
+        Some(offset)
+    );
+
+    // Truncated input
+    let e = Term::decode(Cursor::new(&bytes[..5])).unwrap_err();
+    assert!(matches!(e.kind(), DecodeErrorKind::Io(_)));
+    assert_eq!(e.path(), [PathSegment::TupleElement(0)]);
+}
+
+#[test]
+fn deterministic_encode_test() {
+    let encode_deterministic = |term: &Term| {
+        let mut buf = Vec::new();
+        term.encode_with_options(&mut buf, EncodeOptions::new().deterministic())
+            .unwrap();
+        buf
+    };
+    let atom = |name: &str| Term::from(Atom::from(name));
+    let int = |value: i32| Term::from(FixInteger::from(value));
+
+    // erlang:term_to_binary(#{b => 1, a => 2}, [deterministic])
+    let map = Term::from(Map::from([(atom("b"), int(1)), (atom("a"), int(2))]));
+    assert_eq!(
+        encode_deterministic(&map),
+        [131, 116, 0, 0, 0, 2, 119, 1, 97, 97, 2, 119, 1, 98, 97, 1]
+    );
+
+    // erlang:term_to_binary(#{1.0 => a, 1 => b}, [deterministic])
+    let map = Term::from(Map::from([
+        (Term::from(Float::try_from(1.0).unwrap()), atom("a")),
+        (int(1), atom("b")),
+    ]));
+    assert_eq!(
+        encode_deterministic(&map),
+        [
+            131, 116, 0, 0, 0, 2, 97, 1, 119, 1, 98, 70, 63, 240, 0, 0, 0, 0, 0, 0, 119, 1, 97
+        ]
+    );
+
+    // erlang:term_to_binary(#{1.0 => a, 2 => b}, [deterministic])
+    // (all integers come before all floats in map key order)
+    let map = Term::from(Map::from([
+        (Term::from(Float::try_from(1.0).unwrap()), atom("a")),
+        (int(2), atom("b")),
+    ]));
+    assert_eq!(
+        encode_deterministic(&map),
+        [
+            131, 116, 0, 0, 0, 2, 97, 2, 119, 1, 98, 70, 63, 240, 0, 0, 0, 0, 0, 0, 119, 1, 97
+        ]
+    );
+
+    // Keys of different types, and nested maps
+    let map = Term::from(Map::from([
+        (Term::from(Binary::from(vec![1])), int(1)),
+        (Term::from(List::nil()), int(2)),
+        (Term::from(Tuple::from(vec![atom("a")])), int(3)),
+        (
+            atom("foo"),
+            Term::from(Map::from([(int(300), int(0)), (int(-1), int(0))])),
+        ),
+        (int(2), int(5)),
+    ]));
+    let bytes = encode_deterministic(&map);
+    assert_eq!(
+        bytes,
+        [
+            131, 116, 0, 0, 0, 5, //
+            97, 2, 97, 5, //
+            119, 3, 102, 111, 111, 116, 0, 0, 0, 2, 98, 255, 255, 255, 255, 97, 0, 98, 0, 0, 1, 44,
+            97, 0, //
+            104, 1, 119, 1, 97, 97, 3, //
+            106, 97, 2, //
+            109, 0, 0, 0, 1, 1, 97, 1
+        ]
+    );
+    for _ in 0..10 {
+        assert_eq!(encode_deterministic(&decode(&bytes)), bytes);
+    }
+}
+
+#[test]
+fn distribution_flags_test() {
+    let encode_with_flags = |term: Term, flags: DistributionFlags| {
+        let mut buf = Vec::new();
+        term.encode_with_options(&mut buf, EncodeOptions::new().distribution_flags(flags))
+            .map(|()| buf)
+    };
+    let old = DistributionFlags::EXTENDED_REFERENCES | DistributionFlags::SMALL_ATOM_TAGS;
+
+    // Atoms
+    let atom = Term::from(Atom::from("é"));
+    assert_eq!(
+        encode_with_flags(atom.clone(), DistributionFlags::empty()).unwrap(),
+        [131, 100, 0, 1, 0xE9]
+    );
+    assert_eq!(
+        encode_with_flags(atom.clone(), old).unwrap(),
+        [131, 115, 1, 0xE9]
+    );
+    assert_eq!(decode(&[131, 115, 1, 0xE9]), atom);
+    assert!(matches!(
+        encode_with_flags(Term::from(Atom::from("日")), old),
+        Err(EncodeError::UnsupportedByPeer { required, .. }) if required == DistributionFlags::UTF8_ATOMS
+    ));
+
+    // erlang:term_to_binary(1.5, [{minor_version, 0}])
+    let float = Term::from(Float::try_from(1.5).unwrap());
+    let mut expected = vec![131, 99];
+    expected.extend(b"1.50000000000000000000e+00");
+    expected.resize(33, 0);
+    assert_eq!(encode_with_flags(float.clone(), old).unwrap(), expected);
+    assert_eq!(decode(&expected), float);
+    let float = Term::from(Float::try_from(-0.1).unwrap());
+    let bytes = encode_with_flags(float.clone(), old).unwrap();
+    assert_eq!(&bytes[2..29], b"-1.00000000000000005551e-01");
+    assert_eq!(decode(&bytes), float);
+
+    // Pids, ports and references
+    let pid = Term::from(Pid::from(("a", 1, 2)));
+    assert_eq!(
+        encode_with_flags(pid.clone(), old).unwrap(),
+        [131, 103, 115, 1, 97, 0, 0, 0, 1, 0, 0, 0, 2, 0]
+    );
+    let port = Term::from(Port {
+        node: Atom::from("a"),
+        id: 3,
+        creation: 4,
+    });
+    assert_eq!(
+        encode_with_flags(port, old).unwrap(),
+        [131, 102, 115, 1, 97, 0, 0, 0, 3, 4]
+    );
+    let reference = Term::from(Reference::from(("a", vec![1, 2])));
+    assert_eq!(
+        encode_with_flags(reference.clone(), old).unwrap(),
+        [131, 114, 0, 2, 115, 1, 97, 0, 0, 0, 0, 1, 0, 0, 0, 2]
+    );
+    assert!(matches!(
+        encode_with_flags(reference, DistributionFlags::empty()),
+        Err(EncodeError::UnsupportedByPeer { required, .. }) if required == DistributionFlags::EXTENDED_REFERENCES
+    ));
+    assert_eq!(
+        encode_with_flags(
+            Term::from(Reference::from(("a", vec![7]))),
+            DistributionFlags::empty()
+        )
+        .unwrap(),
+        [131, 101, 100, 0, 1, 97, 0, 0, 0, 7, 0]
+    );
+    let big_creation = Term::from(Pid {
+        creation: 0x100,
+        ..Pid::from(("a", 1, 2))
+    });
+    assert!(matches!(
+        encode_with_flags(big_creation, old),
+        Err(EncodeError::UnsupportedByPeer { required, .. }) if required == DistributionFlags::BIG_CREATION
+    ));
+    let v4_port = Term::from(Port {
+        node: Atom::from("a"),
+        id: 1 << 40,
+        creation: 4,
+    });
+    assert!(
+        encode_with_flags(
+            v4_port,
+            DistributionFlags::all().difference(DistributionFlags::V4_NC)
+        )
+        .is_err()
+    );
+
+    // Bitstrings, external funs and maps
+    let bits = Term::from(BitBinary::from((vec![1, 0b101], 3)));
+    assert_eq!(
+        encode_with_flags(bits, old).unwrap(),
+        [131, 104, 2, 109, 0, 0, 0, 2, 1, 0b1010_0000, 97, 3]
+    );
+    let fun = Term::from(ExternalFun::from(("m", "f", 1)));
+    assert_eq!(
+        encode_with_flags(fun, old).unwrap(),
+        [131, 104, 2, 115, 1, 109, 115, 1, 102]
+    );
+    assert!(matches!(
+        encode_with_flags(Term::from(Map::from([])), old),
+        Err(EncodeError::UnsupportedByPeer { required, .. }) if required == DistributionFlags::MAP_TAG
+    ));
+
+    // All flags are assumed by default
+    assert_eq!(
+        encode_with_flags(pid.clone(), DistributionFlags::all()).unwrap(),
+        encode(pid)
+    );
+    assert_eq!(
+        DistributionFlags::from_bits(0x30000).to_string(),
+        "UTF8_ATOMS | MAP_TAG"
+    );
+}
+
+#[test]
+fn encoded_len_test() {
+    let terms = [
+        Term::from(Atom::from("foo")),
+        Term::from(FixInteger::from(-1)),
+        Term::from(BigInteger::from(u64::MAX)),
+        Term::from(ByteList::from("abc")),
+        Term::from(List::from(vec![Term::from(FixInteger::from(1000))])),
+        Term::from(Tuple::from(vec![
+            Term::from(Pid::from(("nonode@nohost", 1, 2))),
+            Term::from(Map::from([(
+                Term::from(Binary::from(vec![1, 2, 3])),
+                Term::from(Float::try_from(1.5).unwrap()),
+            )])),
+        ])),
+    ];
+    for term in terms {
+        assert_eq!(term.encoded_len().unwrap(), encode(term.clone()).len());
+
+        let options = EncodeOptions::new()
+            .compressed(9)
+            .always_compress()
+            .distribution_flags(DistributionFlags::UTF8_ATOMS | DistributionFlags::MAP_TAG);
+        let mut buf = Vec::new();
+        term.encode_with_options(&mut buf, options.clone()).unwrap();
+        assert_eq!(term.encoded_len_with_options(options).unwrap(), buf.len());
+    }
+
+    // Sizes given by OTP 26
+    let big =
+        |hex_zeros: usize| -> Term { format!("16#1{}", "0".repeat(hex_zeros)).parse().unwrap() };
+    let sizes = [
+        // erlang:external_size(foo)
+        (Term::from(Atom::from("foo")), 6),
+        // erlang:external_size('é')
+        (Term::from(Atom::from("é")), 5),
+        // erlang:external_size(list_to_atom(lists:duplicate(255, $é)))
+        (Term::from(Atom::from("é".repeat(255))), 514),
+        // erlang:external_size(255), external_size(256), external_size(-1)
+        (Term::from(FixInteger::from(255)), 3),
+        (Term::from(FixInteger::from(256)), 6),
+        (Term::from(FixInteger::from(-1)), 6),
+        // erlang:external_size(1 bsl 31), external_size(-(1 bsl 31))
+        (Term::from(BigInteger::from(1i64 << 31)), 8),
+        (Term::from(FixInteger::from(i32::MIN)), 6),
+        // erlang:external_size(1 bsl 64), external_size(1 bsl 2036), external_size(1 bsl 2040)
+        (big(16), 13),
+        (big(509), 259),
+        (big(510), 263),
+        // erlang:external_size(1.5)
+        (Term::from(Float::try_from(1.5).unwrap()), 10),
+        // erlang:external_size("abc")
+        (Term::from(ByteList::from("abc")), 7),
+        // erlang:external_size(lists:duplicate(65535, $a))
+        (Term::from(ByteList::from(vec![b'a'; 65535])), 65539),
+        // erlang:external_size(lists:duplicate(65536, $a))
+        (Term::from(ByteList::from(vec![b'a'; 65536])), 131079),
+        // erlang:external_size([1000])
+        (
+            Term::from(List::from(vec![Term::from(FixInteger::from(1000))])),
+            12,
+        ),
+        // erlang:external_size({c:pid(0, 1, 2), #{<<1, 2, 3>> => 1.5}})
+        (
+            Term::from(Tuple::from(vec![
+                Term::from(Pid::from(("nonode@nohost", 1, 2))),
+                Term::from(Map::from([(
+                    Term::from(Binary::from(vec![1, 2, 3])),
+                    Term::from(Float::try_from(1.5).unwrap()),
+                )])),
+            ])),
+            53,
+        ),
+    ];
+    for (term, size) in sizes {
+        assert_eq!(term.encoded_len().unwrap(), size, "{term}");
+    }
+
+    // Terms which do not shrink are not compressed, so that their size is the same as in OTP:
+    // byte_size(term_to_binary(foo, [compressed])),
+    // byte_size(term_to_binary(list_to_binary(lists:seq(0, 255)), [compressed]))
+    let options = EncodeOptions::new().compressed(6);
+    let atom = Term::from(Atom::from("foo"));
+    assert_eq!(atom.encoded_len_with_options(options.clone()).unwrap(), 6);
+    let binary = Term::from(Binary::from((0..=255).collect::<Vec<u8>>()));
+    assert_eq!(binary.encoded_len_with_options(options).unwrap(), 262);
+
+    let atom = Term::from(Atom::from("a".repeat(70000)));
+    assert!(matches!(
+        atom.encoded_len(),
+        Err(EncodeError::TooLongAtomName(_))
+    ));
+}
+
+#[test]
+fn malformed_input_test() {
+    fn decode_kind(bytes: &[u8]) -> DecodeErrorKind {
+        Term::decode(Cursor::new(bytes)).unwrap_err().into_kind()
+    }
+
+    // BIT_BINARY_EXT with a bit count out of range
+    for bits in [0, 9, 255] {
+        let bytes = [131, 77, 0, 0, 0, 1, bits, 0xFF];
+        assert!(matches!(
+            decode_kind(&bytes),
+            DecodeErrorKind::InvalidBitCount { size: 1, tail_bits_size } if tail_bits_size == bits
+        ));
+        assert!(TermRef::decode(&bytes).is_err());
+    }
+    assert!(matches!(
+        decode_kind(&[131, 77, 0, 0, 0, 0, 3]),
+        DecodeErrorKind::InvalidBitCount { size: 0, .. }
+    ));
+    assert_eq!(
+        decode(&[131, 77, 0, 0, 0, 1, 8, 0xFF]),
+        Term::from(BitBinary::from((vec![0xFF], 8)))
+    );
+
+    // Invalid UTF-8 atom names
+    assert!(matches!(
+        decode_kind(&[131, 119, 2, 0xC3, 0x28]),
+        DecodeErrorKind::InvalidUtf8Atom { .. }
+    ));
+    assert!(matches!(
+        TermRef::decode(&[131, 118, 0, 1, 0xFF]).map_err(DecodeError::into_kind),
+        Err(DecodeErrorKind::InvalidUtf8Atom { .. })
+    ));
+    assert_eq!(
+        Term::from(TermRef::decode(&[131, 115, 1, 0xE9]).unwrap()),
+        Term::from(Atom::from("é"))
+    );
+
+    // Big integers with a bad sign byte
+    assert!(matches!(
+        decode_kind(&[131, 110, 1, 2, 1]),
+        DecodeErrorKind::InvalidSign { sign: 2 }
+    ));
+
+    // FLOAT_EXT
+    let mut float = [0; 33];
+    float[..2].copy_from_slice(&[131, 99]);
+    float[2..5].copy_from_slice(b"inf");
+    assert!(matches!(
+        decode_kind(&float),
+        DecodeErrorKind::NonFiniteFloat
+    ));
+    float[2..7].copy_from_slice(b"1e999");
+    assert!(matches!(
+        decode_kind(&float),
+        DecodeErrorKind::NonFiniteFloat
+    ));
+    float[2..7].copy_from_slice(b"1.0x0");
+    assert!(matches!(
+        decode_kind(&float),
+        DecodeErrorKind::InvalidFloat { .. }
+    ));
+    float[2..7].copy_from_slice(b"\xFF\xFF\xFF\xFF\xFF");
+    assert!(matches!(
+        decode_kind(&float),
+        DecodeErrorKind::InvalidFloat { .. }
+    ));
+
+    // Node names of pids are subject to the depth limit
+    let mut nested_pids = vec![131];
+    nested_pids.extend(std::iter::repeat_n(88, 100_000));
+    assert!(matches!(
+        Term::decode_with_options(
+            Cursor::new(&nested_pids),
+            DecodeOptions::new().max_depth(100)
+        )
+        .map_err(DecodeError::into_kind),
+        Err(DecodeErrorKind::LimitExceeded {
+            limit: DecodeLimit::Depth,
+            ..
+        })
+    ));
+}
+
+#[cfg(feature = "tokio")]
+#[tokio::test]
+async fn async_io_test() {
+    let atom = Term::from(Atom::from("foo"));
+    let list = Term::from(List::from(
+        (0..100)
+            .map(|i| Term::from(FixInteger::from(i % 3 * 1000)))
+            .collect::<Vec<_>>(),
+    ));
+
+    let mut bytes = Vec::new();
+    list.encode_async_with_options(&mut bytes, EncodeOptions::new().compressed(6))
+        .await
+        .unwrap();
+    assert_eq!(bytes[1], 80);
+    atom.encode_async(&mut bytes).await.unwrap();
+    list.encode_async(&mut bytes).await.unwrap();
+    bytes.push(0);
+
+    // Each call reads exactly one term
+    let mut reader = &bytes[..];
+    assert_eq!(Term::decode_async(&mut reader).await.unwrap(), list);
+    assert_eq!(Term::decode_async(&mut reader).await.unwrap(), atom);
+    assert_eq!(Term::decode_async(&mut reader).await.unwrap(), list);
+    assert_eq!(reader, [0]);
+
+    // Truncated
+    let mut reader = &bytes[..10];
+    assert!(matches!(
+        Term::decode_async(&mut reader).await.map_err(DecodeError::into_kind),
+        Err(DecodeErrorKind::Io(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof
+    ));
+    let options = DecodeOptions::new().max_list_length(10);
+    let mut reader = &bytes[..];
+    assert!(matches!(
+        Term::decode_async_with_options(&mut reader, options)
+            .await
+            .map_err(DecodeError::into_kind),
+        Err(DecodeErrorKind::LimitExceeded { .. })
+    ));
+}
+
+#[cfg(feature = "tokio-util")]
+#[test]
+fn framed_codec_test() {
+    use bytes::BytesMut;
+    use tokio_util::codec::{Decoder, Encoder};
+
+    let atom = Term::from(Atom::from("foo"));
+    let binary = Term::from(Binary::from(vec![0; 300]));
+    for packet in [1, 2, 4] {
+        let mut codec = EtfFramedCodec::new(packet);
+        let mut buf = BytesMut::new();
+        codec.encode(&atom, &mut buf).unwrap();
+        codec.encode(atom.clone(), &mut buf).unwrap();
+        assert_eq!(buf.len(), 2 * (usize::from(packet) + 6));
+        assert_eq!(usize::from(buf[usize::from(packet) - 1]), 6);
+
+        // Frames may arrive in pieces
+        let bytes = buf.split().freeze();
+        for &b in &bytes[..bytes.len() - 1] {
+            buf.extend_from_slice(&[b]);
+            if let Some(term) = codec.decode(&mut buf).unwrap() {
+                assert_eq!(term, atom);
+            }
+        }
+        assert_eq!(codec.decode(&mut buf).unwrap(), None);
+        buf.extend_from_slice(&bytes[bytes.len() - 1..]);
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(atom.clone()));
+        assert!(buf.is_empty());
+    }
+
+    // Oversize frames
+    let mut codec = EtfFramedCodec::new(1);
+    assert!(matches!(
+        codec.encode(&binary, &mut BytesMut::new()),
+        Err(FramedCodecError::FrameTooLarge {
+            size: 306,
+            max: 255
+        })
+    ));
+    let mut codec = EtfFramedCodec::new(4).max_frame(100);
+    let mut buf = BytesMut::new();
+    assert!(matches!(
+        codec.encode(&binary, &mut buf),
+        Err(FramedCodecError::FrameTooLarge {
+            size: 306,
+            max: 100
+        })
+    ));
+    assert!(buf.is_empty());
+    buf.extend_from_slice(&[0, 0, 1, 50]);
+    assert!(matches!(
+        codec.decode(&mut buf),
+        Err(FramedCodecError::FrameTooLarge {
+            size: 306,
+            max: 100
+        })
+    ));
+
+    // Invalid frames
+    let mut codec = EtfFramedCodec::new(2);
+    let mut buf = BytesMut::from(&[0, 3, 131, 106, 0][..]);
+    match codec.decode(&mut buf) {
+        Err(FramedCodecError::Decode(e)) => {
+            assert!(matches!(
+                e.kind(),
+                DecodeErrorKind::TrailingBytes { offset: 2 }
+            ))
+        }
+        r => panic!("unexpected result: {r:?}"),
+    }
+    let mut buf = BytesMut::from(&[0, 2, 130, 106][..]);
+    assert!(matches!(
+        codec.decode(&mut buf),
+        Err(FramedCodecError::Decode(_))
+    ));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_format_test() {
+    use eetf::{SerdeError, SerdeOptions};
+    use serde::{Deserialize, Serialize};
+    use std::collections::BTreeMap;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Shape {
+        #[serde(rename = "empty")]
+        Empty,
+        #[serde(rename = "circle")]
+        Circle(f64),
+        #[serde(rename = "rect")]
+        Rect(i32, i32),
+        #[serde(rename = "polygon")]
+        Polygon { points: Vec<(i32, i32)> },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Record {
+        name: String,
+        id: u128,
+        offset: i64,
+        enabled: bool,
+        parent: Option<Box<Record>>,
+        #[serde(with = "serde_bytes")]
+        payload: Vec<u8>,
+        shapes: Vec<Shape>,
+        tags: BTreeMap<String, char>,
+        unit: (),
+    }
+
+    let record = Record {
+        name: "foo".to_owned(),
+        id: u128::MAX,
+        offset: -1 << 40,
+        enabled: true,
+        parent: Some(Box::new(Record {
+            name: "bar".to_owned(),
+            id: 1,
+            offset: 0,
+            enabled: false,
+            parent: None,
+            payload: Vec::new(),
+            shapes: Vec::new(),
+            tags: BTreeMap::new(),
+            unit: (),
+        })),
+        payload: vec![0, 255],
+        shapes: vec![
+            Shape::Empty,
+            Shape::Circle(1.5),
+            Shape::Rect(2, 3),
+            Shape::Polygon {
+                points: vec![(0, 0), (1, 1)],
+            },
+        ],
+        tags: BTreeMap::from([("x".to_owned(), 'é')]),
+        unit: (),
+    };
+    let bytes = eetf::to_vec(&record).unwrap();
+    assert_eq!(eetf::from_slice::<Record>(&bytes).unwrap(), record);
+
+    let term = eetf::to_term(&record).unwrap();
+    assert_eq!(Term::decode(&bytes[..]).unwrap(), term);
+    let Term::Map(map) = &term else {
+        panic!("not a map: {term}");
+    };
+    let get = |key: &str| map.map[&Term::from(Atom::from(key))].clone();
+    assert_eq!(get("name"), Term::from(Binary::from(&b"foo"[..])));
+    assert_eq!(get("payload"), Term::from(Binary::from(vec![0, 255])));
+    assert_eq!(get("enabled"), Term::from(Atom::from("true")));
+    assert_eq!(get("unit"), Term::from(Tuple::from(vec![])));
+    assert!(matches!(get("id"), Term::BigInteger(_)));
+    assert_eq!(
+        get("shapes"),
+        Term::from(List::from(vec![
+            Term::from(Atom::from("empty")),
+            Term::from(Tuple::from(vec![
+                Term::from(Atom::from("circle")),
+                Term::from(Float::try_from(1.5).unwrap()),
+            ])),
+            Term::from(Tuple::from(vec![
+                Term::from(Atom::from("rect")),
+                Term::from(FixInteger::from(2)),
+                Term::from(FixInteger::from(3)),
+            ])),
+            Term::from(Tuple::from(vec![
+                Term::from(Atom::from("polygon")),
+                Term::from(Map::from([(
+                    Term::from(Atom::from("points")),
+                    Term::from(List::from(vec![
+                        Term::from(Tuple::from(vec![
+                            Term::from(FixInteger::from(0)),
+                            Term::from(FixInteger::from(0)),
+                        ])),
+                        Term::from(Tuple::from(vec![
+                            Term::from(FixInteger::from(1)),
+                            Term::from(FixInteger::from(1)),
+                        ])),
+                    ])),
+                )])),
+            ])),
+        ]))
+    );
+
+    // Integer sequences round-trip whether or not they fit in STRING_EXT
+    let signed = vec![-1i32, 5];
+    let bytes = eetf::to_vec(&signed).unwrap();
+    assert_eq!(eetf::from_slice::<Vec<i32>>(&bytes).unwrap(), signed);
+    let unsigned = vec![0u8, 255];
+    let bytes = eetf::to_vec(&unsigned).unwrap();
+    assert_eq!(eetf::from_slice::<Vec<u8>>(&bytes).unwrap(), unsigned);
+
+    // `None` is `undefined` by default, and configurable
+    assert_eq!(
+        eetf::to_term(&None::<i32>).unwrap(),
+        Term::from(Atom::from("undefined"))
+    );
+    let elixir = SerdeOptions::new().none_atom("nil");
+    let bytes = eetf::to_vec_with_options(&None::<i32>, &elixir).unwrap();
+    assert_eq!(bytes, [131, 119, 3, 110, 105, 108]);
+    assert_eq!(
+        eetf::from_slice_with_options::<Option<i32>>(&bytes, &elixir).unwrap(),
+        None
+    );
+    assert!(eetf::from_slice::<Option<i32>>(&bytes).is_err());
+
+    // Strings and bytes are borrowed unless compressed
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Borrowed<'a> {
+        text: &'a str,
+        #[serde(borrow)]
+        bytes: &'a [u8],
+    }
+    let value = Term::from(Map::from([
+        (
+            Term::from(Atom::from("text")),
+            Term::from(Binary::from(&b"abc"[..])),
+        ),
+        (
+            Term::from(Atom::from("bytes")),
+            Term::from(Binary::from(vec![1, 2, 3])),
+        ),
+    ]));
+    let bytes = encode(value.clone());
+    let borrowed = eetf::from_slice::<Borrowed>(&bytes).unwrap();
+    assert_eq!(borrowed.text, "abc");
+    assert_eq!(borrowed.bytes, [1, 2, 3]);
+    let mut compressed = Vec::new();
+    value
+        .encode_with_options(
+            &mut compressed,
+            EncodeOptions::new().compressed(6).always_compress(),
+        )
+        .unwrap();
+    assert_eq!(compressed[1], 80);
+    assert!(eetf::from_slice::<Borrowed>(&compressed).is_err());
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Owned {
+        text: String,
+        #[serde(with = "serde_bytes")]
+        bytes: Vec<u8>,
+    }
+    assert_eq!(
+        eetf::from_slice::<Owned>(&compressed).unwrap(),
+        Owned {
+            text: "abc".to_owned(),
+            bytes: vec![1, 2, 3],
+        }
+    );
+
+    // Erlang-specific encodings: atoms and byte lists as strings,
+    // tuples as sequences, and unknown fields of any type
+    let value = Term::from(Map::from([
+        (
+            Term::from(Binary::from(&b"text"[..])),
+            Term::from(ByteList::from(vec![104, 233, 108, 108, 111])),
+        ),
+        (
+            Term::from(Atom::from("bytes")),
+            Term::from(Tuple::from(vec![Term::from(FixInteger::from(7))])),
+        ),
+        (
+            Term::from(Atom::from("pid")),
+            Term::from(Pid::new("nonode@nohost", 1, 2, 3)),
+        ),
+    ]));
+    assert_eq!(
+        eetf::from_slice::<Owned>(&encode(value)).unwrap(),
+        Owned {
+            text: "h\u{e9}llo".to_owned(),
+            bytes: vec![7],
+        }
+    );
+    assert_eq!(
+        eetf::from_slice::<(String, String)>(&[131, 104, 2, 100, 0, 2, 111, 107, 106]).unwrap(),
+        ("ok".to_owned(), String::new())
+    );
+
+    // Errors
+    let bytes = encode(Term::from(List::from(vec![
+        Term::from(FixInteger::from(1)),
+        Term::from(Atom::from("two")),
+    ])));
+    match eetf::from_slice::<Vec<i32>>(&bytes) {
+        Err(SerdeError::Message { offset, .. }) => assert_eq!(offset, Some(8)),
+        other => panic!("unexpected result: {other:?}"),
+    }
+    let mut bytes = eetf::to_vec(&1).unwrap();
+    bytes.push(0);
+    match eetf::from_slice::<i32>(&bytes) {
+        Err(SerdeError::Decode(e)) => {
+            assert!(matches!(
+                e.kind(),
+                DecodeErrorKind::TrailingBytes { offset: 3 }
+            ))
+        }
+        other => panic!("unexpected result: {other:?}"),
+    }
+    let mut nested = vec![131];
+    nested.extend(std::iter::repeat_n([108, 0, 0, 0, 1], 200).flatten());
+    match eetf::from_slice::<serde::de::IgnoredAny>(&nested) {
+        Err(SerdeError::Decode(e)) => assert!(matches!(
+            e.kind(),
+            DecodeErrorKind::LimitExceeded {
+                limit: DecodeLimit::Depth,
+                ..
+            }
+        )),
+        other => panic!("unexpected result: {other:?}"),
+    }
+    assert!(matches!(
+        eetf::to_vec(&f64::NAN),
+        Err(SerdeError::Message { .. })
+    ));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_term_test() {
+    let node = Atom::from("nonode@nohost");
+    let pid = Pid::from(("nonode@nohost", 36, 0));
+    let term = Term::from(List::from(vec![
+        Term::from(Atom::from("é")),
+        Term::from(FixInteger::from(-1)),
+        Term::from(BigInteger::from(u64::MAX)),
+        Term::from(Float::try_from(-0.5).unwrap()),
+        Term::from(pid.clone()),
+        Term::from(Port {
+            node: node.clone(),
+            id: 1 << 40,
+            creation: 3,
+        }),
+        Term::from(Reference {
+            node,
+            id: vec![1, 2, 3],
+            creation: 300,
+        }),
+        Term::from(ExternalFun::from(("lists", "map", 2))),
+        Term::from(InternalFun::Old {
+            module: Atom::from("a"),
+            pid: pid.clone(),
+            free_vars: vec![],
+            index: 1,
+            uniq: -2,
+        }),
+        Term::from(InternalFun::New {
+            module: Atom::from("a"),
+            arity: 1,
+            pid,
+            index: 0,
+            uniq: [7; 16],
+            old_index: 0,
+            old_uniq: 1,
+            free_vars: vec![Term::from(Binary::from(vec![1, 2]))],
+        }),
+        Term::from(BitBinary::from((vec![1, 2, 3], 5))),
+        Term::from(ByteList::from(vec![1, 2, 3])),
+        Term::from(List::nil()),
+        Term::from(ImproperList::from((
+            vec![Term::from(Atom::from("a"))],
+            Term::from(Atom::from("b")),
+        ))),
+        Term::from(Tuple::from(vec![])),
+        Term::from(Map::from([(
+            Term::from(Tuple::from(vec![Term::from(Atom::from("k"))])),
+            Term::from(Binary::from(vec![0; 40])),
+        )])),
+    ]));
+
+    // A format which is not self-describing
+    let bytes = bincode::serialize(&term).unwrap();
+    assert_eq!(bincode::deserialize::<Term>(&bytes).unwrap(), term);
+
+    // Single-field types are transparent
+    assert_eq!(
+        bincode::serialize(&Atom::from("foo")).unwrap(),
+        bincode::serialize("foo").unwrap()
+    );
+    assert_eq!(
+        bincode::serialize(&Binary::from(vec![1, 2])).unwrap(),
+        bincode::serialize(&serde_bytes::Bytes::new(&[1, 2])).unwrap()
+    );
+
+    // Floats must be finite
+    let bytes = bincode::serialize(&f64::NAN).unwrap();
+    assert!(bincode::deserialize::<Float>(&bytes).is_err());
+
+    // Terms can be embedded in the external term format too
+    let bytes = eetf::to_vec(&term).unwrap();
+    assert_eq!(eetf::from_slice::<Term>(&bytes).unwrap(), term);
+    assert_eq!(
+        eetf::to_term(&Term::from(Atom::from("foo"))).unwrap(),
+        Term::from(Tuple::from(vec![
+            Term::from(Atom::from("Atom")),
+            Term::from(Binary::from(&b"foo"[..])),
+        ]))
+    );
+}
+
+#[test]
+fn convert_test() {
+    use std::collections::{BTreeMap, HashMap};
+
+    fn round_trip<T: ToTerm + FromTerm + PartialEq + std::fmt::Debug>(value: T) -> Term {
+        let term = value.to_term().unwrap();
+        assert_eq!(T::from_term(term.clone()).unwrap(), value);
+        term
+    }
+
+    // Integers
+    assert_eq!(round_trip(-1i8), Term::from(FixInteger::from(-1)));
+    assert_eq!(round_trip(i32::MAX), Term::from(FixInteger::from(i32::MAX)));
+    assert_eq!(round_trip(u32::MAX), Term::from(BigInteger::from(u32::MAX)));
+    assert_eq!(round_trip(i64::MIN), Term::from(BigInteger::from(i64::MIN)));
+    round_trip(u64::MAX);
+    round_trip(i128::MIN);
+    round_trip(u128::MAX);
+    round_trip(usize::MAX);
+    assert_eq!(
+        u8::from_term(Term::from(FixInteger::from(-1)))
+            .unwrap_err()
+            .into_kind(),
+        ConvertErrorKind::OutOfRange {
+            value: Term::from(FixInteger::from(-1)),
+            expected: "u8".to_owned(),
+        }
+    );
+    assert!(u64::from_term(u128::MAX.to_term().unwrap()).is_err());
+    assert!(i32::from_term(Term::from(Atom::from("1"))).is_err());
+
+    // Floats, booleans and strings
+    assert_eq!(
+        round_trip(1.5f64),
+        Term::from(Float::try_from(1.5).unwrap())
+    );
+    round_trip(-0.25f32);
+    assert_eq!(
+        f64::NAN.to_term().unwrap_err().to_string(),
+        "non-finite float NaN cannot be a term"
+    );
+    assert!(f64::from_term(Term::from(FixInteger::from(1))).is_err());
+    assert_eq!(round_trip(true), Term::from(Atom::from("true")));
+    assert!(bool::from_term(Term::from(Atom::from("yes"))).is_err());
+    assert_eq!(
+        round_trip("foo".to_owned()),
+        Term::from(Binary::from(&b"foo"[..]))
+    );
+    assert_eq!("foo".to_term().unwrap(), round_trip("foo".to_owned()));
+    assert_eq!(
+        String::from_term(Term::from(ByteList::from(vec![104, 233]))).unwrap(),
+        "h\u{e9}"
+    );
+    assert_eq!(String::from_term(Term::from(List::nil())).unwrap(), "");
+    assert!(String::from_term(Term::from(Binary::from(vec![255]))).is_err());
+
+    // Containers
+    assert_eq!(
+        round_trip(vec![1u8, 2]),
+        Term::from(List::from(vec![
+            Term::from(FixInteger::from(1)),
+            Term::from(FixInteger::from(2)),
+        ]))
+    );
+    assert_eq!(
+        Vec::<u8>::from_term(Term::from(ByteList::from(vec![1, 2]))).unwrap(),
+        [1, 2]
+    );
+    round_trip((Atom::from("a"), 1u32));
+    round_trip((1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, "12".to_owned()));
+    assert_eq!(
+        <(i32, i32)>::from_term(Term::from(Tuple::from(vec![Term::from(FixInteger::from(
+            1
+        ))])))
+        .unwrap_err()
+        .to_string(),
+        "{1} is not a tuple of 2 elements"
+    );
+    round_trip(HashMap::from([
+        ("a".to_owned(), 1i64),
+        ("b".to_owned(), -1),
+    ]));
+    round_trip(BTreeMap::from([(1, vec![true]), (2, vec![])]));
+    assert_eq!(round_trip(None::<f64>), Term::from(Atom::from("undefined")));
+    round_trip(Some(1.5f64));
+    assert_eq!(
+        round_trip(Ok::<_, String>(Term::from(Atom::from("x")))),
+        Term::from(Tuple::from(vec![
+            Term::from(Atom::from("ok")),
+            Term::from(Atom::from("x")),
+        ]))
+    );
+    round_trip(Err::<(), _>(Atom::from("enoent")));
+    assert!(Result::<i32, i32>::from_term(Term::from(Atom::from("ok"))).is_err());
+
+    // Errors locate the offending term
+    let term = Term::from(Map::from([(
+        Term::from(Binary::from(&b"k"[..])),
+        Term::from(List::from(vec![
+            Term::from(FixInteger::from(1)),
+            Term::from(Atom::from("two")),
+        ])),
+    )]));
+    let e = HashMap::<String, Vec<i32>>::from_term(term).unwrap_err();
+    assert_eq!(
+        e.path(),
+        [
+            PathSegment::MapValue(Term::from(Binary::from(&b"k"[..]))),
+            PathSegment::ListElement(1)
+        ]
+    );
+    assert!(matches!(
+        e.kind(),
+        ConvertErrorKind::UnexpectedType { expected, .. } if expected == "an integer"
+    ));
+    let e = vec![vec![1.0], vec![f64::INFINITY]].to_term().unwrap_err();
+    assert_eq!(
+        e.path(),
+        [PathSegment::ListElement(1), PathSegment::ListElement(0)]
+    );
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn derive_test() {
+    fn round_trip<T: ToTerm + FromTerm + PartialEq + std::fmt::Debug>(value: T) -> String {
+        let term = value.to_term().unwrap();
+        assert_eq!(T::from_term(term.clone()).unwrap(), value);
+        term.to_string()
+    }
+
+    #[derive(Debug, PartialEq, ToTerm, FromTerm)]
+    #[eetf(record)]
+    struct Person {
+        name: String,
+        age: u8,
+    }
+
+    #[derive(Debug, PartialEq, ToTerm, FromTerm)]
+    #[eetf(record, rename = "point")]
+    struct Point2D(i32, i32);
+
+    #[derive(Debug, PartialEq, ToTerm, FromTerm)]
+    #[eetf(tuple)]
+    struct Pair<T> {
+        first: T,
+        second: T,
+    }
+
+    #[derive(Debug, PartialEq, ToTerm, FromTerm)]
+    struct Meters(f64);
+
+    #[derive(Debug, PartialEq, ToTerm, FromTerm)]
+    struct HTTPOk;
+
+    #[derive(Debug, PartialEq, ToTerm, FromTerm)]
+    struct Config {
+        #[eetf(rename = "max_conns")]
+        max_connections: u32,
+        #[eetf(default)]
+        tags: Vec<Atom>,
+    }
+
+    #[derive(Debug, PartialEq, ToTerm, FromTerm)]
+    #[eetf(elixir_struct = "MyApp.User")]
+    struct User {
+        name: String,
+    }
+
+    #[derive(Debug, PartialEq, ToTerm, FromTerm)]
+    struct Message {
+        r#type: u8,
+    }
+
+    #[derive(Debug, PartialEq, ToTerm, FromTerm)]
+    #[allow(non_camel_case_types)]
+    struct r#type;
+
+    #[derive(Debug, PartialEq, ToTerm, FromTerm)]
+    enum Event {
+        Joined(Person),
+        #[eetf(rename = "bye")]
+        Left {
+            name: String,
+        },
+        Moved(i32, i32),
+        ShutDown,
+    }
+
+    // Structs
+    assert_eq!(
+        round_trip(Person {
+            name: "alice".to_owned(),
+            age: 30
+        }),
+        r#"{person,<<"alice">>,30}"#
+    );
+    assert_eq!(round_trip(Point2D(1, -2)), "{point,1,-2}");
+    assert_eq!(
+        round_trip(Pair {
+            first: 1u8,
+            second: 2
+        }),
+        "{1,2}"
+    );
+    assert_eq!(round_trip(Meters(1.5)), "1.5");
+    assert_eq!(round_trip(HTTPOk), "http_ok");
+    let config = Config {
+        max_connections: 10,
+        tags: vec![Atom::from("a")],
+    };
+    assert_eq!(
+        config.to_term().unwrap(),
+        Term::from(Map::from([
+            (
+                Term::from(Atom::from("max_conns")),
+                Term::from(FixInteger::from(10))
+            ),
+            (
+                Term::from(Atom::from("tags")),
+                Term::from(List::from(vec![Term::from(Atom::from("a"))]))
+            ),
+        ]))
+    );
+    round_trip(config);
+    let user = User {
+        name: "bob".to_owned(),
+    };
+    assert_eq!(
+        user.to_term().unwrap(),
+        Term::from(Map::from([
+            (
+                Term::from(Atom::from("__struct__")),
+                Term::from(Atom::from("Elixir.MyApp.User"))
+            ),
+            (
+                Term::from(Atom::from("name")),
+                Term::from(Binary::from(&b"bob"[..]))
+            ),
+        ]))
+    );
+    round_trip(user);
+    // Raw identifiers are named without their `r#` prefix
+    assert_eq!(round_trip(Message { r#type: 1 }), "#{type => 1}");
+    assert_eq!(
+        Message::from_term("#{type => 2}".parse().unwrap()).unwrap(),
+        Message { r#type: 2 }
+    );
+    assert_eq!(round_trip(r#type), "type");
+
+    // Enums
+    assert_eq!(
+        round_trip(Event::Joined(Person {
+            name: "carol".to_owned(),
+            age: 7
+        })),
+        r#"{joined,{person,<<"carol">>,7}}"#
+    );
+    assert_eq!(
+        round_trip(Event::Left {
+            name: "dave".to_owned()
+        }),
+        r#"{bye,<<"dave">>}"#
+    );
+    assert_eq!(round_trip(Event::Moved(1, 2)), "{moved,1,2}");
+    assert_eq!(round_trip(Event::ShutDown), "shut_down");
+
+    // Defaults and errors
+    let term = Term::from(Map::from([(
+        Term::from(Atom::from("max_conns")),
+        Term::from(FixInteger::from(5)),
+    )]));
+    assert_eq!(
+        Config::from_term(term).unwrap(),
+        Config {
+            max_connections: 5,
+            tags: Vec::new()
+        }
+    );
+    let e = Config::from_term(Term::from(Map::from([]))).unwrap_err();
+    assert_eq!(
+        e.into_kind(),
+        ConvertErrorKind::MissingKey {
+            key: Term::from(Atom::from("max_conns"))
+        }
+    );
+    let term = Term::from(Map::from([(
+        Term::from(Atom::from("name")),
+        Term::from(Binary::from(&b"eve"[..])),
+    )]));
+    assert_eq!(
+        User::from_term(term).unwrap_err().to_string(),
+        r#"#{name => <<"eve">>} is not a %MyApp.User{} struct"#
+    );
+    let term = Term::from(Tuple::from(vec![
+        Term::from(Atom::from("joined")),
+        Term::from(Tuple::from(vec![
+            Term::from(Atom::from("person")),
+            Term::from(Binary::from(&b"frank"[..])),
+            Term::from(FixInteger::from(-1)),
+        ])),
+    ]));
+    let e = Event::from_term(term).unwrap_err();
+    assert_eq!(
+        e.path(),
+        [PathSegment::TupleElement(1), PathSegment::TupleElement(2)]
+    );
+    assert_eq!(
+        Event::from_term(Term::from(Atom::from("left")))
+            .unwrap_err()
+            .to_string(),
+        "left is not one of {joined, _}, {bye, _}, {moved, _, _}, shut_down"
+    );
+    assert_eq!(
+        Person::from_term(Term::from(Tuple::from(vec![Term::from(Atom::from(
+            "person"
+        ))])))
+        .unwrap_err()
+        .to_string(),
+        "{person} is not a record {person, _, _}"
+    );
+}
+
+#[test]
+fn parse_test() {
+    fn parse(s: &str) -> Term {
+        s.parse().unwrap_or_else(|e| panic!("{s:?}: {e}"))
+    }
+    fn atom(name: &str) -> Term {
+        Term::from(Atom::from(name))
+    }
+    fn int(value: i32) -> Term {
+        Term::from(FixInteger::from(value))
+    }
+
+    // Atoms
+    assert_eq!(parse("foo"), atom("foo"));
+    assert_eq!(parse("node@host_1"), atom("node@host_1"));
+    assert_eq!(parse(r#"'Foo Bar'"#), atom("Foo Bar"));
+    assert_eq!(parse(r#"'it\'s\n'"#), atom("it's\n"));
+    assert_eq!(parse("fun"), atom("fun"));
+
+    // Numbers
+    assert_eq!(parse("42"), int(42));
+    assert_eq!(parse("-42"), int(-42));
+    assert_eq!(parse("+7"), int(7));
+    assert_eq!(parse("1_000_000"), int(1_000_000));
+    assert_eq!(parse("16#FF"), int(255));
+    assert_eq!(parse("-2#1010"), int(-10));
+    assert_eq!(parse("36#zz"), int(1295));
+    assert_eq!(parse("$a"), int(97));
+    assert_eq!(parse("$\\n"), int(10));
+    assert_eq!(parse("$\\x{1F600}"), int(0x1F600));
+    assert_eq!(parse("$\\101"), int(65));
+    assert_eq!(parse("$\\^C"), int(3));
+    assert_eq!(
+        parse("2147483648"),
+        Term::from(BigInteger::from(2_147_483_648i64))
+    );
+    assert_eq!(
+        parse("-123456789012345678901234567890"),
+        Term::from(BigInteger {
+            value: "-123456789012345678901234567890".parse().unwrap()
+        })
+    );
+    assert_eq!(parse("1.5"), Term::from(Float::try_from(1.5).unwrap()));
+    assert_eq!(
+        parse("-2.5e-3"),
+        Term::from(Float::try_from(-2.5e-3).unwrap())
+    );
+    assert_eq!(parse("1.0E10"), Term::from(Float::try_from(1e10).unwrap()));
+
+    // Strings and binaries
+    assert_eq!(parse(r#""abc""#), Term::from(ByteList::from("abc")));
+    assert_eq!(parse(r#""ab" "c""#), Term::from(ByteList::from("abc")));
+    assert_eq!(parse(r#""""#), Term::from(List::nil()));
+    assert_eq!(
+        parse(r#""h\xe9\t""#),
+        Term::from(ByteList::from(vec![104, 233, 9]))
+    );
+    assert_eq!(
+        parse(r#""\x{3b1}""#),
+        Term::from(List::from(vec![int(0x3b1)]))
+    );
+    assert_eq!(parse("<<>>"), Term::from(Binary::from(Vec::new())));
+    assert_eq!(
+        parse("<<1, 2, 300>>"),
+        Term::from(Binary::from(vec![1, 2, 44]))
+    );
+    assert_eq!(
+        parse(r#"<<"bin", $!, "\x{e9}"/utf8, 1:16>>"#),
+        Term::from(Binary::from(vec![98, 105, 110, 33, 0xc3, 0xa9, 0, 1]))
+    );
+    assert_eq!(
+        parse("<<-1:4, 1:1>>"),
+        Term::from(BitBinary::from((vec![0b1_1111], 5)))
+    );
+    assert_eq!(
+        parse("<<1, 2:3>>"),
+        Term::from(BitBinary::from((vec![1, 2], 3)))
+    );
+    assert_eq!(
+        parse("<<1:1, -2:12, 16#100000000000000000005:20, -1:20, 300:9>>"),
+        Term::from(BitBinary::from((
+            vec![255, 240, 0, 2, 255, 255, 252, 44],
+            6
+        )))
+    );
+    assert_eq!(
+        parse("<<0:8388608>>"),
+        Term::from(Binary::from(vec![0; 1 << 20]))
+    );
+
+    // Containers
+    assert_eq!(parse("[]"), Term::from(List::nil()));
+    assert_eq!(
+        parse("[1, 2]"),
+        Term::from(List::from(vec![int(1), int(2)]))
+    );
+    assert_eq!(
+        parse("[1 | 2]"),
+        Term::from(ImproperList::from((vec![int(1)], int(2))))
+    );
+    assert_eq!(
+        parse("[1 | [2 | 3]]"),
+        Term::from(ImproperList::from((vec![int(1), int(2)], int(3))))
+    );
+    assert_eq!(
+        parse("[1 | [2]]"),
+        Term::from(List::from(vec![int(1), int(2)]))
+    );
+    assert_eq!(parse("{}"), Term::from(Tuple::from(Vec::new())));
+    assert_eq!(
+        parse("#{a => 1, \"b\" => {}}"),
+        Term::from(Map::from([
+            (atom("a"), int(1)),
+            (
+                Term::from(ByteList::from("b")),
+                Term::from(Tuple::from(Vec::new()))
+            ),
+        ]))
+    );
+    assert_eq!(
+        parse(
+            "%% config
+            {ok, [1, 2, <<\"bin\">>, #{a => 1.5}]}.  % trailing comment
+            "
+        ),
+        Term::from(Tuple::from(vec![
+            atom("ok"),
+            Term::from(List::from(vec![
+                int(1),
+                int(2),
+                Term::from(Binary::from(&b"bin"[..])),
+                Term::from(Map::from([(
+                    atom("a"),
+                    Term::from(Float::try_from(1.5).unwrap())
+                )])),
+            ])),
+        ]))
+    );
+
+    // Pids, ports, references and funs
+    assert_eq!(
+        parse("<0.1.2>"),
+        Term::from(Pid::new("nonode@nohost", 1, 2, 0))
+    );
+    assert_eq!(
+        parse("#Port<0.5>"),
+        Term::from(Port::from(("nonode@nohost", 5)))
+    );
+    assert_eq!(
+        parse("#Ref<0.1.2.3>"),
+        Term::from(Reference::from(("nonode@nohost", vec![1, 2, 3])))
+    );
+    assert_eq!(
+        parse("fun lists:map/2"),
+        Term::from(ExternalFun::from(("lists", "map", 2)))
+    );
+    for term in [
+        Term::from(Pid::new("a@b", 1, 2, 0)),
+        Term::from(Port::from(("a@b", 3))),
+        Term::from(Reference::from(("a@b", vec![4, 5, 6]))),
+        Term::from(ExternalFun::from(("m", "f", 1))),
+        Term::from(BitBinary::from((vec![1, 2, 3], 5))),
+        Term::from(Tuple::from(vec![
+            atom("x y"),
+            Term::from(List::from(vec![int(-1)])),
+            Term::from(ImproperList::from((vec![atom("a")], atom("b")))),
+        ])),
+    ] {
+        assert_eq!(parse(&term.to_string()), term);
+    }
+
+    // Errors
+    let e = "{ok,\n  [1, 2 3]}".parse::<Term>().unwrap_err();
+    assert_eq!((e.line(), e.column()), (2, 9));
+    assert_eq!(
+        e.to_string(),
+        "unexpected '3', expected ',', '|' or ']' at line 2, column 9"
+    );
+    assert_eq!(
+        "Foo".parse::<Term>().unwrap_err().into_kind(),
+        ParseErrorKind::UnexpectedChar {
+            found: 'F',
+            expected: "a term".to_owned()
+        }
+    );
+    assert_eq!(
+        "'abc".parse::<Term>().unwrap_err().kind(),
+        &ParseErrorKind::UnexpectedEnd {
+            expected: "'\\''".to_owned()
+        }
+    );
+    assert_eq!(
+        "37#1".parse::<Term>().unwrap_err().into_kind(),
+        ParseErrorKind::InvalidNumber {
+            text: "37#".to_owned()
+        }
+    );
+    assert!(matches!(
+        "1.0e999".parse::<Term>().unwrap_err().into_kind(),
+        ParseErrorKind::InvalidNumber { .. }
+    ));
+    assert!(matches!(
+        "<1.2.3>".parse::<Term>().unwrap_err().into_kind(),
+        ParseErrorKind::UnknownNode { .. }
+    ));
+    assert!(matches!(
+        r#""\x{110000}""#.parse::<Term>().unwrap_err().into_kind(),
+        ParseErrorKind::InvalidEscape { .. }
+    ));
+    assert!(matches!(
+        "<<1.5>>".parse::<Term>().unwrap_err().into_kind(),
+        ParseErrorKind::InvalidSegment { .. }
+    ));
+    // Binaries are limited to 1 MiB in total
+    for input in [
+        "<<0:100000000>>".to_owned(),
+        format!("<<\"{}\":8388608>>", "a".repeat(200)),
+        "<<0:8388600, 0:8388600>>".to_owned(),
+        r#"<<0:8388600, "aa">>"#.to_owned(),
+        r#"<<0:8388600, "é"/utf8>>"#.to_owned(),
+    ] {
+        assert!(matches!(
+            input.parse::<Term>().unwrap_err().into_kind(),
+            ParseErrorKind::InvalidSegment { .. }
+        ));
+    }
+    assert_eq!(
+        parse("<<0:8388600, \"a\"/utf8>>"),
+        Term::from(Binary::from([vec![0; (1 << 20) - 1], vec![b'a']].concat()))
+    );
+    assert_eq!(
+        "[".repeat(1000).parse::<Term>().unwrap_err().into_kind(),
+        ParseErrorKind::TooDeep { max: 512 }
+    );
+    assert!("1 2".parse::<Term>().is_err());
+    assert!("".parse::<Term>().is_err());
+}
+
+#[test]
+fn print_test() {
+    fn parse(s: &str) -> Term {
+        s.parse().unwrap()
+    }
+    fn write(term: &Term) -> String {
+        term.to_erlang_string(&PrintOptions::new().style(PrintStyle::Write))
+    }
+    fn pretty(term: &Term, width: usize) -> String {
+        term.to_erlang_string(&PrintOptions::new().line_width(width))
+    }
+
+    // Floats are the shortest form reading back as the same value
+    for (value, expected) in [
+        (1.0, "1.0"),
+        (0.1, "0.1"),
+        (12.5, "12.5"),
+        (100.0, "100.0"),
+        (1000.0, "1.0e3"),
+        (123456.0, "123456.0"),
+        (1e10, "1.0e10"),
+        (0.0001, "0.0001"),
+        (1e-5, "1.0e-5"),
+        (-0.0, "-0.0"),
+        (0.1 + 0.2, "0.30000000000000004"),
+        (-1.5e300, "-1.5e300"),
+        (5e-324, "5.0e-324"),
+    ] {
+        let float = Float::try_from(value).unwrap();
+        assert_eq!(float.to_string(), expected);
+        assert_eq!(parse(expected), Term::from(float));
+    }
+
+    // Strings and binaries
+    let abc = Term::from(ByteList::from("abc"));
+    assert_eq!(abc.to_string(), r#""abc""#);
+    assert_eq!(write(&abc), "[97,98,99]");
+    let abc = Term::from(List::from(vec![
+        Term::from(FixInteger::from(97)),
+        Term::from(FixInteger::from(98)),
+        Term::from(FixInteger::from(99)),
+    ]));
+    assert_eq!(abc.to_string(), r#""abc""#);
+    assert_eq!(write(&abc), "[97,98,99]");
+    assert_eq!(
+        Term::from(ByteList::from(vec![b'a', b'"', b'\n', 233])).to_string(),
+        "\"a\\\"\\n\u{e9}\""
+    );
+    assert_eq!(Term::from(ByteList::from(vec![1, 2])).to_string(), "[1,2]");
+    assert_eq!(Term::from(List::nil()).to_string(), "[]");
+    let bin = Term::from(Binary::from(&b"abc"[..]));
+    assert_eq!(bin.to_string(), r#"<<"abc">>"#);
+    assert_eq!(write(&bin), "<<97,98,99>>");
+    assert_eq!(Binary::from(vec![0, 255]).to_string(), "<<0,255>>");
+    assert_eq!(Binary::from(Vec::new()).to_string(), "<<>>");
+    assert_eq!(
+        BitBinary::from((b"ab\x03".to_vec(), 5)).to_string(),
+        r#"<<"ab",3:5>>"#
+    );
+    assert_eq!(BitBinary::from((vec![1], 3)).to_string(), "<<1:3>>");
+
+    // Containers
+    let term = parse(r#"{'EXIT', [a | b], #{b => 2, a => "x", 1 => 1.5}, fun m:f/1}"#);
+    assert_eq!(
+        term.to_string(),
+        r#"{'EXIT',[a|b],#{1 => 1.5,a => "x",b => 2},fun m:f/1}"#
+    );
+    assert_eq!(
+        write(&term),
+        "{'EXIT',[a|b],#{1=>1.5,a=>[120],b=>2},fun m:f/1}"
+    );
+
+    // Line breaking
+    let term = parse(
+        r#"{ok, [{user, <<"alice">>, #{age => 30, roles => [admin, dev]}},
+                 {user, <<"bob">>, #{age => 25, roles => []}}]}"#,
+    );
+    assert_eq!(
+        pretty(&term, 40),
+        r#"{ok,[{user,<<"alice">>,
+           #{age => 30,
+             roles => [admin,dev]}},
+     {user,<<"bob">>,
+           #{age => 25,roles => []}}]}"#
+    );
+    assert_eq!(
+        pretty(&term, 80),
+        r#"{ok,[{user,<<"alice">>,#{age => 30,roles => [admin,dev]}},
+     {user,<<"bob">>,#{age => 25,roles => []}}]}"#
+    );
+    assert_eq!(pretty(&term, usize::MAX), term.to_string(),);
+    let list = Term::from(List::from(
+        (1..=12)
+            .map(|i| Term::from(FixInteger::from(i * 1000)))
+            .collect::<Vec<_>>(),
+    ));
+    assert_eq!(
+        pretty(&list, 30),
+        "[1000,2000,3000,4000,5000,\n 6000,7000,8000,9000,10000,\n 11000,12000]"
+    );
+    let map = parse("#{config => #{name => \"server\", ports => [8080, 8443]}, key => {a, b}}");
+    assert_eq!(
+        format!("{map:#}"),
+        "#{config => #{name => \"server\",ports => [8080,8443]},key => {a,b}}"
+    );
+    assert_eq!(
+        pretty(&map, 40),
+        "#{config => #{name => \"server\",\n              ports => [8080,8443]},\n  key => {a,b}}"
+    );
+
+    // Printed terms read back
+    let term = parse(
+        r#"{'hello world', "abc", <<"bin">>, <<1, 2:3>>, [1 | 2], -1.5e-7,
+            #{<0.1.2> => #Ref<0.1.2.3>, 'node@127.0.0.1' => #Port<0.5>}, 123456789012345678901234567890}"#,
+    );
+    // (`~w` would print the string as `[97,98,99]`, which reads back as a `List`)
+    for printed in [term.to_string(), pretty(&term, 20)] {
+        assert_eq!(parse(&printed), term, "{printed}");
+    }
+
+    // Depth (`~W` and `~P`)
+    let limited = |s: &str, depth: usize| {
+        let options = PrintOptions::new().style(PrintStyle::Write).depth(depth);
+        parse(s).to_erlang_string(&options)
+    };
+    assert_eq!(limited("[1, 2, 3, 4, 5]", 3), "[1,2|...]");
+    assert_eq!(limited("[1 | 2]", 2), "[1|...]");
+    assert_eq!(limited("{a, b, c, d}", 3), "{a,b,...}");
+    assert_eq!(limited("{a, {b, {c, d}}}", 3), "{a,{...}}");
+    assert_eq!(limited("#{a => 1, b => 2, c => 3}", 3), "#{a=>1,b=>2,...}");
+    assert_eq!(limited("<<1, 2, 3, 4>>", 3), "<<1,2,...>>");
+    assert_eq!(limited("<<1, 2:3>>", 3), "<<1,2:3>>");
+    assert_eq!(limited("<<1, 2:3>>", 2), "<<1,...>>");
+    assert_eq!(limited(r#""abc""#, 3), "[97,98|...]");
+    assert_eq!(limited("[1]", 1), "[...]");
+    assert_eq!(limited("[]", 1), "[]");
+    assert_eq!(limited("{a}", 0), "...");
+    let term = parse(r#"{"abcd", <<"abcd">>, [x, y, z]}"#);
+    assert_eq!(
+        term.to_erlang_string(&PrintOptions::new().depth(4)),
+        r#"{"ab"...,<<"a"...>>,[...]}"#
+    );
+    let term = parse(
+        r#"{ok, [{user, <<"alice">>, #{age => 30, roles => [admin, dev]}},
+                 {user, <<"bob">>, #{age => 25, roles => []}},
+                 {user, <<"carol">>, #{age => 41, roles => [ops]}}]}"#,
+    );
+    assert_eq!(
+        term.to_erlang_string(&PrintOptions::new().depth(6).line_width(30)),
+        "{ok,[{user,<<...>>,...},\n     {user,...},\n     {...}]}"
+    );
+    assert_eq!(
+        term.to_erlang_string(&PrintOptions::new().depth(5).line_width(10)),
+        "{ok,[{user,...},\n     {...}|...]}"
+    );
+
+    // Character limit
+    let term = Term::from(List::from(
+        (0..1000)
+            .map(|i| Term::from(FixInteger::from(i)))
+            .collect::<Vec<_>>(),
+    ));
+    let full = write(&term);
+    let options = PrintOptions::new().style(PrintStyle::Write).chars_limit(20);
+    assert_eq!(
+        term.to_erlang_string(&options),
+        format!("{}...(990 more elements)", &full[..20])
+    );
+    assert_eq!(
+        term.to_erlang_string(&options.chars_limit(full.len())),
+        full
+    );
+    let limited =
+        |s: &str, limit: usize| parse(s).to_erlang_string(&PrintOptions::new().chars_limit(limit));
+    assert_eq!(
+        limited("{ok, [1, 2, 3, 4, 5], x}", 8),
+        "{ok,[1,2...(4 more elements)"
+    );
+    assert_eq!(limited("[1, 2, 3]", 6), "[1,2,3...");
+    assert_eq!(limited("'a long atom'", 3), "'a ...");
+    assert_eq!(
+        limited(r#"<<"abcdef", 1:2>>"#, 5),
+        r#"<<"ab...(5 more elements)"#
+    );
+    assert_eq!(
+        limited(r#"["abcdef" | x]"#, 4),
+        r#"["ab...(5 more elements)"#
+    );
+    assert_eq!(
+        limited("#{c => 3, a => 1, b => 2}", 6),
+        "#{a =>...(2 more elements)"
+    );
+    let big = Term::from(ByteList::from(vec![0; 1_000_000]));
+    assert_eq!(
+        big.to_erlang_string(&PrintOptions::new().chars_limit(8)),
+        "[0,0,0,0...(999996 more elements)"
+    );
+    assert_eq!(
+        big.to_erlang_string(&PrintOptions::new().chars_limit(8).line_width(4)),
+        "[0,\n 0,\n...(999997 more elements)"
+    );
+    let term = parse(
+        r#"{ok, [{user, <<"alice">>, #{name => "Ålice", roles => [admin, dev]}},
+                 {user, <<1, 2, 3:5>>, [[98, 111, 98], 'é' | {x, 1.5}]}]}"#,
+    );
+    for options in [
+        PrintOptions::new().style(PrintStyle::Write),
+        PrintOptions::new(),
+        PrintOptions::new().line_width(30),
+        PrintOptions::new().line_width(30).depth(6),
+    ] {
+        let full = term.to_erlang_string(&options);
+        let len = full.chars().count();
+        assert_eq!(
+            term.to_erlang_string(&options.clone().chars_limit(len)),
+            full
+        );
+        // The output is cut after the limit, whatever the limit is
+        for limit in 0..len {
+            let printed = term.to_erlang_string(&options.clone().chars_limit(limit));
+            let prefix: String = full.chars().take(limit).collect();
+            let elided = printed
+                .strip_prefix(&format!("{prefix}..."))
+                .unwrap_or_else(|| panic!("{printed}"));
+            assert!(
+                elided.is_empty()
+                    || elided == "(1 more element)"
+                    || elided
+                        .strip_prefix('(')
+                        .and_then(|x| x.strip_suffix(" more elements)"))
+                        .is_some_and(|n| n.parse::<usize>().is_ok()),
+                "{printed}"
+            );
+        }
+    }
+}
+
+#[test]
+fn inspect_test() {
+    fn inspect(s: &str) -> String {
+        s.parse::<Term>()
+            .unwrap()
+            .to_elixir_string(&InspectOptions::new())
+    }
+
+    // Atoms
+    assert_eq!(inspect("ok"), ":ok");
+    assert_eq!(inspect("'foo bar'"), r#":"foo bar""#);
+    assert_eq!(inspect("'valid?'"), ":valid?");
+    assert_eq!(inspect("'Foo'"), ":Foo");
+    assert_eq!(inspect("'++'"), ":++");
+    assert_eq!(inspect("[true, false, nil]"), "[true, false, nil]");
+    assert_eq!(inspect("'Elixir.MyApp.User'"), "MyApp.User");
+
+    // Numbers, strings and bitstrings
+    assert_eq!(inspect("[-1, 1.0e3, 0.5]"), "[-1, 1.0e3, 0.5]");
+    assert_eq!(inspect(r#"<<"h\"é #{x}\n"/utf8>>"#), r#""h\"é \#{x}\n""#);
+    assert_eq!(inspect("<<1, 2, 255>>"), "<<1, 2, 255>>");
+    assert_eq!(inspect("<<1, 5:3>>"), "<<1, 5::size(3)>>");
+    assert_eq!(inspect("<<>>"), r#""""#);
+
+    // Lists
+    assert_eq!(inspect(r#""abc""#), r#"~c"abc""#);
+    assert_eq!(inspect("[1, 2 | 3]"), "[1, 2 | 3]");
+    assert_eq!(inspect("[]"), "[]");
+    assert_eq!(inspect("[233]"), "[233]");
+    assert_eq!(
+        inspect("[{a, 1}, {'foo bar', ok}]"),
+        r#"[a: 1, "foo bar": :ok]"#
+    );
+    assert_eq!(inspect("[{a, 1}, {2, b}]"), "[{:a, 1}, {2, :b}]");
+
+    // Tuples, maps and structs
+    assert_eq!(inspect("{ok, {}}"), "{:ok, {}}");
+    assert_eq!(inspect("#{b => 2, a => 1}"), "%{a: 1, b: 2}");
+    assert_eq!(
+        inspect(r#"#{<<"a">> => 1, b => 2}"#),
+        r#"%{:b => 2, "a" => 1}"#
+    );
+    assert_eq!(inspect("#{}"), "%{}");
+    assert_eq!(
+        inspect(r#"#{'__struct__' => 'Elixir.MyApp.User', name => <<"alice">>, age => 30}"#),
+        r#"%MyApp.User{age: 30, name: "alice"}"#
+    );
+    assert_eq!(
+        inspect("#{'__struct__' => foo, a => 1}"),
+        "%{__struct__: :foo, a: 1}"
+    );
+
+    // Pids, ports, references and funs
+    assert_eq!(inspect("<0.1.2>"), "#PID<nonode@nohost.1.2>");
+    assert_eq!(inspect("#Port<0.5>"), "#Port<nonode@nohost.5>");
+    assert_eq!(inspect("#Ref<0.1.2.3>"), "#Reference<nonode@nohost.1.2.3>");
+    assert_eq!(inspect("fun lists:map/2"), "&:lists.map/2");
+    assert_eq!(inspect("fun 'Elixir.Enum':map/2"), "&Enum.map/2");
+
+    // Limit
+    let term: Term = "[1, [2, 3, 4], #{a => 1, b => 2}, 5]".parse().unwrap();
+    assert_eq!(
+        term.to_elixir_string(&InspectOptions::new().limit(3)),
+        "[1, [2, ...], %{...}, ...]"
+    );
+    assert_eq!(
+        term.to_elixir_string(&InspectOptions::new().limit(usize::MAX)),
+        "[1, [2, 3, 4], %{a: 1, b: 2}, 5]"
+    );
+
+    // Line breaking
+    let term: Term = r#"{ok, #{'__struct__' => 'Elixir.MyApp.User',
+                              name => <<"alice">>,
+                              roles => [admin, developer, operator],
+                              tags => [{team, <<"core">>}, {level, 3}]}}"#
+        .parse()
+        .unwrap();
+    assert_eq!(
+        term.to_elixir_string(&InspectOptions::new().width(40)),
+        r#"{:ok,
+ %MyApp.User{
+   name: "alice",
+   roles: [:admin, :developer,
+    :operator],
+   tags: [team: "core", level: 3]
+ }}"#
+    );
+    assert_eq!(
+        term.to_elixir_string(&InspectOptions::new().width(30)),
+        r#"{:ok,
+ %MyApp.User{
+   name: "alice",
+   roles: [:admin, :developer,
+    :operator],
+   tags: [team: "core",
+    level: 3]
+ }}"#
+    );
+    assert!(
+        !term
+            .to_elixir_string(&InspectOptions::new().width(usize::MAX))
+            .contains('\n')
+    );
+}
+
+#[test]
+fn ord_test() {
+    fn parse(s: &str) -> Term {
+        s.parse().unwrap()
+    }
+
+    // Classes
+    let sorted = [
+        "1",
+        "ok",
+        "#Ref<0.1.2.3>",
+        "fun m:f/1",
+        "#Port<0.5>",
+        "<0.1.2>",
+        "{}",
+        "#{}",
+        "[]",
+        "[1]",
+        "<<>>",
+    ]
+    .map(parse);
+    let mut terms = sorted.to_vec();
+    terms.reverse();
+    terms.sort();
+    assert_eq!(terms, sorted);
+
+    // Numbers
+    let one = Term::from(FixInteger::from(1));
+    let big_one = Term::from(BigInteger::from(1));
+    assert!(parse("1") < parse("1.5"));
+    assert!(parse("-1.5") < parse("-1"));
+    // (1.0e30 is 1000000000000000019884624838656.0)
+    assert!(parse("1000000000000000000000000000001") < parse("1.0e30"));
+    assert!(one < parse("1.0"));
+    assert!(parse("1.0") < parse("2"));
+    assert!(one < big_one);
+    assert!(big_one < parse("2"));
+    assert_eq!(
+        Term::from(Float::try_from(-0.0).unwrap()).cmp(&parse("0.0")),
+        std::cmp::Ordering::Equal
+    );
+
+    // Local funs before external funs, as in erts_cmp
+    let local = Term::from(InternalFun::New {
+        module: Atom::from("z"),
+        arity: 1,
+        pid: Pid::from(("nonode@nohost", 36, 0)),
+        index: 0,
+        uniq: [0; 16],
+        old_index: 0,
+        old_uniq: 0,
+        free_vars: vec![],
+    });
+    assert!(local < parse("fun a:f/1"));
+    assert!(parse("fun a:f/1") < parse("fun a:g/0"));
+
+    // Tuples by size, maps by size, then keys, then values
+    assert!(parse("{b}") < parse("{a, a}"));
+    assert!(parse("{a, b}") < parse("{b, a}"));
+    assert!(parse("#{c => 1}") < parse("#{a => 1, b => 1}"));
+    assert!(parse("#{a => 2}") < parse("#{b => 1}"));
+    assert!(parse("#{a => 1}") < parse("#{a => 2}"));
+    assert!(parse("#{1 => a}") < parse("#{1.0 => a}"));
+    // Keys in map key order, where integers come before floats
+    assert!(parse("#{2 => x, 1.0 => y}") < parse("#{3 => x, 0.5 => y}"));
+    assert!(parse("#{a => 1.0}") < parse("#{a => 2}"));
+
+    // Lists of any representation
+    let ab = Term::from(ByteList::from("ab"));
+    let ab_list = Term::from(List::from(vec![
+        Term::from(FixInteger::from(97)),
+        Term::from(FixInteger::from(98)),
+    ]));
+    assert!(ab < parse("[97, 99]"));
+    assert!(parse("[97]") < ab);
+    assert!(parse("[97, 98 | 0]") < ab);
+    assert!(parse("[97, 98, 0]") > ab);
+    assert!(parse("[1 | 2]") < parse("[1, 2]"));
+    assert!(parse("[1 | 2]") < parse("[1]"));
+    assert!(Term::from(List::nil()) < parse("[0]"));
+    assert!(parse("[a]") < parse("<<>>"));
+
+    // Consistent with `Eq`
+    assert_ne!(ab, ab_list);
+    assert_ne!(ab.cmp(&ab_list), std::cmp::Ordering::Equal);
+    let terms = [
+        ab,
+        ab_list,
+        parse("{[97, 98]}"),
+        Term::from(Tuple::from(vec![Term::from(ByteList::from("ab"))])),
+        one,
+        big_one,
+        Term::from(BitBinary::from((vec![1, 2], 8))),
+        Term::from(Binary::from(vec![1, 2])),
+        parse("#{a => 1}"),
+        parse("#{a => 1.0}"),
+        Term::from(ByteList::from(Vec::new())),
+        Term::from(List::nil()),
+    ];
+    for a in &terms {
+        for b in &terms {
+            assert_eq!(a == b, a.cmp(b).is_eq(), "{a:?} {b:?}");
+            assert_eq!(a.cmp(b), b.cmp(a).reverse(), "{a:?} {b:?}");
+        }
+    }
+
+    // Sorted collections
+    let set: std::collections::BTreeSet<_> = ["b", "{}", "a", "2", "b"].map(parse).into();
+    assert_eq!(
+        set.iter().map(|x| x.to_string()).collect::<Vec<_>>(),
+        ["2", "a", "b", "{}"]
+    );
+}
+
+#[test]
+fn encode_limits_test() {
+    // Byte lists too long for STRING_EXT are encoded as LIST_EXT
+    let bytes = (0..70_000).map(|i| i as u8).collect::<Vec<_>>();
+    let encoded = encode(Term::from(ByteList::from(bytes.clone())));
+    assert_eq!(&encoded[..6], &[131, 108, 0, 1, 17, 112]);
+    assert_eq!(
+        decode(&encoded),
+        Term::from(List::from(
+            bytes
+                .iter()
+                .map(|&b| Term::from(FixInteger::from(i32::from(b))))
+                .collect::<Vec<_>>()
+        ))
+    );
+    assert_eq!(
+        Term::from(ByteList::from(bytes)).encoded_len().unwrap(),
+        encoded.len()
+    );
+
+    // Only lists of integers in 0..=255 are encoded as STRING_EXT
+    for (elements, encoded) in [
+        (vec![0, 255], vec![131, 107, 0, 2, 0, 255]),
+        (
+            vec![-1, 5],
+            vec![131, 108, 0, 0, 0, 2, 98, 255, 255, 255, 255, 97, 5, 106],
+        ),
+        (vec![256], vec![131, 108, 0, 0, 0, 1, 98, 0, 0, 1, 0, 106]),
+    ] {
+        let list = Term::from(List::from(
+            elements
+                .into_iter()
+                .map(|i| Term::from(FixInteger::from(i)))
+                .collect::<Vec<_>>(),
+        ));
+        assert_eq!(list.encoded_len().unwrap(), encoded.len());
+        assert_eq!(encode(list), encoded);
+    }
+
+    // Bit counts
+    for bits in [0, 9] {
+        let mut buf = Vec::new();
+        assert!(matches!(
+            Term::from(BitBinary::from((vec![1], bits))).encode(&mut buf),
+            Err(EncodeError::InvalidBitCount { size: 1, tail_bits_size }) if tail_bits_size == bits
+        ));
+    }
+    let empty = BitBinary::from(Binary::from(vec![]));
+    assert_eq!(encode(Term::from(empty)), [131, 77, 0, 0, 0, 0, 0]);
+    let full = Term::from(BitBinary::from((vec![1, 2], 8)));
+    assert_eq!(decode(&encode(full.clone())), full);
+
+    // Atoms are limited to 255 characters, not bytes
+    let atom = Term::from(Atom::from("é".repeat(255)));
+    assert_eq!(decode(&encode(atom.clone())), atom);
+    let mut buf = Vec::new();
+    assert!(matches!(
+        Term::from(Atom::from("a".repeat(256))).encode(&mut buf),
+        Err(EncodeError::TooLongAtomName(_))
+    ));
+
+    // References have at most 5 ID words
+    let reference = Term::from(Reference::from(("a", vec![1, 2, 3, 4, 5])));
+    assert_eq!(decode(&encode(reference.clone())), reference);
+    let mut buf = Vec::new();
+    assert!(matches!(
+        Term::from(Reference::from(("a", vec![1, 2, 3, 4, 5, 6]))).encode(&mut buf),
+        Err(EncodeError::TooLargeReferenceId(_))
+    ));
+}
+
+/// Feeds random and mutated inputs to all decoders, which must return an error instead of panicking.
+#[test]
+fn decode_arbitrary_input_test() {
+    // xorshift64*, to make failures reproducible without extra dependencies.
+    struct Rng(u64);
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+        }
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+        fn byte(&mut self) -> u8 {
+            self.next() as u8
+        }
+    }
+
+    fn decode_all(bytes: &[u8], chunk_size: usize) {
+        let options = DecodeOptions::new().max_depth(64);
+        if let Ok(term) = Term::decode_with_options(Cursor::new(bytes), options.clone()) {
+            let _ = term.to_string();
+        }
+        if let Ok(term) = TermRef::decode(bytes) {
+            let _ = Term::from(term).to_string();
+        }
+        let _ = Decoder::with_options(Cursor::new(bytes), options.clone()).decode_exact();
+        let _ = DistributionMessage::decode(Cursor::new(bytes), &mut AtomCache::new());
+        let mut decoder = IncrementalDecoder::with_options(options.clone());
+        for chunk in bytes.chunks(chunk_size) {
+            if !matches!(decoder.feed(chunk), Ok(DecodeStatus::NeedMore(_))) {
+                break;
+            }
+        }
+        let _ = TermReader::with_options(bytes, options).count();
+    }
+
+    let node = Atom::from("nonode@nohost");
+    let terms = vec![
+        Term::from(Atom::from("foo")),
+        Term::from(Atom::from("é")),
+        Term::from(FixInteger::from(-1)),
+        Term::from(FixInteger::from(300)),
+        Term::from(BigInteger::from(i64::MIN)),
+        Term::from(Float::try_from(1.5).unwrap()),
+        Term::from(Pid::from(("nonode@nohost", 1, 2))),
+        Term::from(Port {
+            node: node.clone(),
+            id: 1 << 40,
+            creation: 3,
+        }),
+        Term::from(Reference {
+            node: node.clone(),
+            id: vec![1, 2, 3],
+            creation: 300,
+        }),
+        Term::from(ExternalFun::from(("lists", "map", 2))),
+        Term::from(InternalFun::New {
+            module: Atom::from("a"),
+            arity: 1,
+            pid: Pid::from(("nonode@nohost", 36, 0)),
+            index: 0,
+            uniq: [7; 16],
+            old_index: 0,
+            old_uniq: 1,
+            free_vars: vec![Term::from(FixInteger::from(10))],
+        }),
+        Term::from(Binary::from(vec![1, 2, 3])),
+        Term::from(BitBinary::from((vec![1, 2, 3], 5))),
+        Term::from(ByteList::from(vec![1, 2, 3])),
+        Term::from(List::from(vec![
+            Term::from(Atom::from("a")),
+            Term::from(Tuple::from(vec![Term::from(List::nil())])),
+        ])),
+        Term::from(ImproperList::from((
+            vec![Term::from(Atom::from("a"))],
+            Term::from(Atom::from("b")),
+        ))),
+        Term::from(Map::from([(
+            Term::from(Atom::from("k")),
+            Term::from(Binary::from(vec![0; 40])),
+        )])),
+    ];
+    let mut samples = Vec::new();
+    for term in terms {
+        samples.push(encode(term.clone()));
+        for options in [
+            EncodeOptions::new().compressed(6).always_compress(),
+            EncodeOptions::new().distribution_flags(DistributionFlags::empty()),
+        ] {
+            let mut buf = Vec::new();
+            if term.encode_with_options(&mut buf, options).is_ok() {
+                samples.push(buf);
+            }
+        }
+    }
+
+    let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+    for _ in 0..20_000 {
+        let mut bytes = samples[rng.below(samples.len())].clone();
+        match rng.below(4) {
+            0 => {
+                let len = rng.below(64);
+                bytes = std::iter::once(131)
+                    .chain((0..len).map(|_| rng.byte()))
+                    .collect();
+            }
+            1 => bytes.truncate(rng.below(bytes.len() + 1)),
+            _ => {
+                for _ in 0..=rng.below(4) {
+                    let i = rng.below(bytes.len());
+                    match rng.below(3) {
+                        0 => bytes[i] = rng.byte(),
+                        1 => bytes.insert(i, rng.byte()),
+                        _ => {
+                            bytes.remove(i);
+                        }
+                    }
+                    if bytes.is_empty() {
+                        break;
+                    }
+                }
+            }
+        }
+        decode_all(&bytes, 1 + rng.below(8));
+    }
+
+    // Deeply nested lists must hit the default depth limit instead of overflowing the stack.
+    fn nested_lists(depth: usize) -> Vec<u8> {
+        let mut bytes = vec![131];
+        for _ in 0..depth {
+            bytes.extend_from_slice(&[108, 0, 0, 0, 1]);
+        }
+        bytes.push(106);
+        bytes.extend(std::iter::repeat_n(106, depth));
+        bytes
+    }
+    fn is_depth_error(result: Result<Term, DecodeError>) -> bool {
+        matches!(
+            result.map_err(DecodeError::into_kind),
+            Err(DecodeErrorKind::LimitExceeded {
+                limit: DecodeLimit::Depth,
+                ..
+            })
+        )
+    }
+
+    let bytes = nested_lists(240_000);
+    assert!(is_depth_error(Term::decode(Cursor::new(&bytes))));
+    assert!(is_depth_error(TermRef::decode(&bytes).map(Term::from)));
+    assert!(is_depth_error(IncrementalDecoder::new().feed(&bytes).map(
+        |status| match status {
+            DecodeStatus::Complete(term, _) => term,
+            DecodeStatus::NeedMore(_) => unreachable!(),
+        }
+    )));
+    assert!(is_depth_error(TermReader::new(&bytes[..]).next().unwrap()));
+
+    let bytes = nested_lists(DecodeOptions::DEFAULT_MAX_DEPTH - 1);
+    assert!(Term::decode(Cursor::new(&bytes)).is_ok());
+    assert!(TermRef::decode(&bytes).is_ok());
+}
+
 fn encode(term: Term) -> Vec<u8> {
     let mut buf = Vec::new();
     term.encode(&mut buf).unwrap();